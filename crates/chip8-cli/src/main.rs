@@ -1,21 +1,36 @@
 //! CHIP-8 shell.
 
-use std::{path::PathBuf, process};
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process,
+};
 
 use argh::FromArgs;
 use chip8_core::{
-    core::assembler::Assembler,
-    debugger::{Debugger, DebuggerContext},
+    core::{
+        assembler::Assembler,
+        cpu::ExitReason,
+        savestate::MachineState,
+        types::{convert_hex_addr, C8Addr},
+    },
+    debugger::{default_history_file, BadBreakpoint, Debugger, DebuggerContext},
     drivers::WindowInterface,
-    emulator::{Emulator, EmulatorContext},
+    emulator::{Emulator, EmulatorContext, TracefileMode},
     errors::CResult,
     peripherals::{cartridge::Cartridge, memory::INITIAL_MEMORY_POINTER},
 };
-use chip8_drivers::MQWindowDriver;
+use chip8_drivers::{KeyMap, MQWindowDriver, TermWindowDriver};
+use tracing_subscriber::{filter::LevelFilter, EnvFilter};
 
 /// CHIP-8 Emulator CLI
 #[derive(FromArgs)]
 struct Args {
+    /// verbose output (repeat for more, e.g. `-v -v`)
+    #[argh(switch, short = 'v')]
+    pub verbose: u8,
+
     /// subcommand
     #[argh(subcommand)]
     pub nested: SubCommands,
@@ -33,6 +48,10 @@ enum SubCommands {
     Assemble(AssembleCommand),
     /// Disassemble command
     Disassemble(DisassembleCommand),
+    /// Diff command
+    Diff(DiffCommand),
+    /// Eval command
+    Eval(EvalCommand),
     /// Version command
     Version(VersionCommand),
 }
@@ -41,13 +60,53 @@ enum SubCommands {
 #[derive(FromArgs)]
 #[argh(subcommand, name = "play")]
 struct PlayCommand {
-    /// cartridge path
+    /// cartridge path (`.asm` sources are assembled first)
     #[argh(positional)]
     pub file: PathBuf,
 
     /// trace output file
     #[argh(option, short = 't')]
     pub trace: Option<PathBuf>,
+
+    /// append to trace output file instead of truncating it
+    #[argh(switch)]
+    pub trace_append: bool,
+
+    /// instructions to run before the first frame
+    #[argh(option, short = 'w', default = "0")]
+    pub warmup: usize,
+
+    /// print executed opcodes on exit
+    #[argh(switch)]
+    pub coverage: bool,
+
+    /// keep quick key taps pressed until the game reads them
+    #[argh(switch)]
+    pub sticky_keys: bool,
+
+    /// quit when the game jumps to its own address
+    #[argh(switch)]
+    pub halt_on_self_jump: bool,
+
+    /// render in the terminal instead of a GPU window (no input, no audio)
+    #[argh(switch)]
+    pub software: bool,
+
+    /// disable audio (no audio device is opened)
+    #[argh(switch)]
+    pub no_audio: bool,
+
+    /// map arrow keys and space to the keypad right column, for a second player
+    #[argh(switch)]
+    pub two_player: bool,
+
+    /// IPS patch to apply to the cartridge
+    #[argh(option)]
+    pub patch: Option<PathBuf>,
+
+    /// write the machine state as JSON to this file on exit
+    #[argh(option)]
+    pub dump_state_on_exit: Option<PathBuf>,
 }
 
 /// debug cartridge
@@ -61,13 +120,37 @@ struct DebugCommand {
     /// add breakpoint at address
     #[argh(option, short = 'b')]
     pub breakpoint: Vec<String>,
+
+    /// breakpoints file (defaults to `<file>.bp` next to the cartridge)
+    #[argh(option)]
+    pub breakpoints_file: Option<PathBuf>,
+
+    /// continue until address is reached, then pause
+    #[argh(option)]
+    pub run_to: Option<String>,
+
+    /// command history file (defaults to `~/.chip8_history`)
+    #[argh(option)]
+    pub history_file: Option<PathBuf>,
+
+    /// run without a GPU window (no input, no audio)
+    #[argh(switch)]
+    pub software: bool,
+
+    /// disable audio (no audio device is opened)
+    #[argh(switch)]
+    pub no_audio: bool,
+
+    /// write the machine state as JSON to this file on exit
+    #[argh(option)]
+    pub dump_state_on_exit: Option<PathBuf>,
 }
 
 /// assemble cartridge
 #[derive(FromArgs)]
 #[argh(subcommand, name = "assemble")]
 struct AssembleCommand {
-    /// source assembly path
+    /// source assembly path (`-` for stdin)
     #[argh(positional)]
     pub source: PathBuf,
 
@@ -80,13 +163,51 @@ struct AssembleCommand {
 #[derive(FromArgs)]
 #[argh(subcommand, name = "disassemble")]
 struct DisassembleCommand {
-    /// cartridge path
+    /// cartridge path (`-` for stdin)
     #[argh(positional)]
     pub file: PathBuf,
 
     /// output file (omit argument for stdout)
     #[argh(option, short = 'o')]
     pub output: Option<PathBuf>,
+
+    /// output an HTML table with syntax highlighting
+    #[argh(switch)]
+    pub html: bool,
+}
+
+/// compare two cartridges
+#[derive(FromArgs)]
+#[argh(subcommand, name = "diff")]
+struct DiffCommand {
+    /// original cartridge path
+    #[argh(positional)]
+    pub old: PathBuf,
+
+    /// modified cartridge path
+    #[argh(positional)]
+    pub new: PathBuf,
+}
+
+/// evaluate inline assembly or hexadecimal code
+#[derive(FromArgs)]
+#[argh(subcommand, name = "eval")]
+struct EvalCommand {
+    /// inline assembly (`\n` separates lines)
+    #[argh(option)]
+    pub asm: Option<String>,
+
+    /// hexadecimal bytes (e.g. "6005 7001")
+    #[argh(option)]
+    pub hex: Option<String>,
+
+    /// print the disassembly instead of playing
+    #[argh(switch)]
+    pub dump: bool,
+
+    /// render in the terminal instead of a GPU window (no input, no audio)
+    #[argh(switch)]
+    pub software: bool,
 }
 
 /// show version
@@ -94,6 +215,13 @@ struct DisassembleCommand {
 #[argh(subcommand, name = "version")]
 struct VersionCommand {}
 
+/// Exit code: clean exit.
+const EXIT_OK: i32 = 0;
+/// Exit code: runtime error.
+const EXIT_RUNTIME_ERROR: i32 = 2;
+/// Exit code: bad cartridge.
+const EXIT_BAD_CARTRIDGE: i32 = 3;
+
 /// Start shell.
 fn main() -> CResult {
     let args: Args = argh::from_env();
@@ -105,9 +233,201 @@ fn start_shell_using_args(args: Args) -> CResult {
     parse_args(args)
 }
 
+/// Get log level for chip8 targets from verbosity count.
+fn verbosity_level(verbose: u8) -> LevelFilter {
+    match verbose {
+        0 => LevelFilter::INFO,
+        1 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    }
+}
+
+/// Build log filter.
+///
+/// Verbose switches take precedence over `RUST_LOG`.
+fn build_env_filter(verbose: u8) -> EnvFilter {
+    if verbose > 0 {
+        EnvFilter::new(format!("info,chip8={}", verbosity_level(verbose)))
+    } else {
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"))
+    }
+}
+
+/// Get process exit code from emulation outcome.
+fn emulation_exit_code(result: &CResult<Emulator>) -> i32 {
+    match result {
+        Ok(emulator) => match emulator.cpu.exit_reason {
+            Some(
+                ExitReason::StackUnderflow | ExitReason::StackOverflow | ExitReason::MemoryTrap,
+            ) => EXIT_RUNTIME_ERROR,
+            Some(ExitReason::Exit | ExitReason::Halt) | None => EXIT_OK,
+        },
+        Err(_) => EXIT_RUNTIME_ERROR,
+    }
+}
+
+/// Dump machine state on exit, if requested.
+///
+/// Nothing is written when the driver failed, as the emulator is lost.
+fn dump_state_on_exit(result: &CResult<Emulator>, path: &Option<PathBuf>) {
+    if let (Ok(emulator), Some(path)) = (result, path) {
+        if let Err(e) = MachineState::from_cpu(&emulator.cpu).save_to_path(path) {
+            eprintln!("could not dump machine state: {}", e);
+        }
+    }
+}
+
+/// Build window driver.
+///
+/// The software driver renders in the terminal, for hosts without a usable GPU.
+fn build_window_driver(software: bool, keymap: KeyMap) -> Box<dyn WindowInterface> {
+    if software {
+        Box::new(TermWindowDriver::new())
+    } else {
+        Box::new(MQWindowDriver::new().with_keymap(keymap))
+    }
+}
+
+/// Open input file, or stdin for `-`.
+fn open_input(path: &Path) -> CResult<Box<dyn Read>> {
+    if path == Path::new("-") {
+        Ok(Box::new(io::stdin()))
+    } else {
+        Ok(Box::new(File::open(path)?))
+    }
+}
+
+/// Assemble cartridge from source reader.
+fn assemble_from_reader<R: Read>(mut reader: R) -> CResult<Cartridge> {
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+
+    Assembler::from_string(&contents).assemble_cartridge()
+}
+
+/// Build cartridge from eval command, from assembly or hexadecimal code.
+fn build_eval_cartridge(cmd: &EvalCommand) -> CResult<Cartridge> {
+    match (&cmd.asm, &cmd.hex) {
+        (Some(asm), None) => Assembler::from_string(&asm.replace("\\n", "\n")).assemble_cartridge(),
+        (None, Some(hex)) => Ok(Cartridge::from_hex_str(hex)?),
+        _ => Err("eval expects either --asm or --hex".into()),
+    }
+}
+
+/// Load cartridge for disassembly, reading raw bytes from stdin for `-`.
+fn load_disassembly_input(path: &Path) -> CResult<Cartridge> {
+    if path == Path::new("-") {
+        let mut data = Vec::new();
+        io::stdin().read_to_end(&mut data)?;
+        Ok(Cartridge::from_bytes(&data)?)
+    } else {
+        Ok(Cartridge::load_from_path(path)?)
+    }
+}
+
+/// Find the disassembled instruction covering an address.
+fn instruction_at(disassembly: &[(C8Addr, String, String)], addr: C8Addr) -> String {
+    disassembly
+        .iter()
+        .take_while(|(line_addr, _, _)| *line_addr <= addr)
+        .last()
+        .map(|(line_addr, assembly, _)| format!("{:04X}: {}", line_addr, assembly))
+        .unwrap_or_else(|| "-".into())
+}
+
+/// Format cartridge differences, with disassembly context.
+fn format_diff(old: &Cartridge, new: &Cartridge) -> Vec<String> {
+    let old_disassembly = old.disassembly();
+    let new_disassembly = new.disassembly();
+    let old_end = INITIAL_MEMORY_POINTER as usize + old.get_data().len();
+    let new_end = INITIAL_MEMORY_POINTER as usize + new.get_data().len();
+
+    old.diff(new)
+        .into_iter()
+        .map(|(addr, old_byte, new_byte)| {
+            let show = |byte: u8, end: usize| {
+                if (addr as usize) < end {
+                    format!("{:02X}", byte)
+                } else {
+                    "--".into()
+                }
+            };
+            let context = |disassembly: &[(C8Addr, String, String)], end: usize| {
+                if (addr as usize) < end {
+                    instruction_at(disassembly, addr)
+                } else {
+                    "-".into()
+                }
+            };
+
+            format!(
+                "{:04X}: {} -> {}    ; {} -> {}",
+                addr,
+                show(old_byte, old_end),
+                show(new_byte, new_end),
+                context(&old_disassembly, old_end),
+                context(&new_disassembly, new_end)
+            )
+        })
+        .collect()
+}
+
+/// Load cartridge, assembling it first for `.asm` sources.
+fn load_cartridge(path: &Path) -> CResult<Cartridge> {
+    match path.extension() {
+        Some(ext) if ext == "asm" => Assembler::from_path(path)?.assemble_cartridge(),
+        _ => Ok(Cartridge::load_from_path(path)?),
+    }
+}
+
+/// Load cartridge, exiting on error.
+fn load_cartridge_or_exit(path: &Path) -> Cartridge {
+    match load_cartridge(path) {
+        Ok(cartridge) => cartridge,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(EXIT_BAD_CARTRIDGE);
+        }
+    }
+}
+
+/// Build debugger context from debug command.
+///
+/// Without `--run-to`, execution pauses on the first instruction.
+fn build_debugger_context(cmd: &DebugCommand) -> CResult<DebuggerContext> {
+    let mut debugger_context = DebuggerContext::new();
+    debugger_context.set_address(INITIAL_MEMORY_POINTER);
+
+    let breakpoints_file = cmd
+        .breakpoints_file
+        .clone()
+        .unwrap_or_else(|| cmd.file.with_extension("bp"));
+    if breakpoints_file.exists() {
+        debugger_context.load_breakpoints(&breakpoints_file)?;
+    }
+    debugger_context.breakpoints_file = Some(breakpoints_file);
+
+    debugger_context.history_file = cmd.history_file.clone().or_else(default_history_file);
+    debugger_context.load_history()?;
+
+    for v in &cmd.breakpoint {
+        debugger_context.register_breakpoint_str(v)?;
+    }
+
+    match &cmd.run_to {
+        Some(addr) => {
+            let addr = convert_hex_addr(addr).ok_or_else(|| BadBreakpoint(addr.clone()))?;
+            debugger_context.temporary_breakpoint = Some(addr.get());
+        }
+        None => debugger_context.register_breakpoint(INITIAL_MEMORY_POINTER),
+    }
+
+    Ok(debugger_context)
+}
+
 /// Parse arguments.
 fn parse_args(args: Args) -> CResult {
-    let s = tracing_subscriber::fmt();
+    let s = tracing_subscriber::fmt().with_env_filter(build_env_filter(args.verbose));
     s.compact().init();
 
     match args.nested {
@@ -121,82 +441,432 @@ fn parse_args(args: Args) -> CResult {
             println!("{} {}", cmd_name, env!("CARGO_PKG_VERSION"));
         }
         SubCommands::Assemble(cmd) => {
-            let assembler =
-                Assembler::from_path(&cmd.source).expect("error while reading assembly");
-            let cartridge = assembler
-                .assemble_cartridge()
-                .expect("error while assembling cartridge");
+            let source = open_input(&cmd.source).expect("error while reading assembly");
+            let cartridge = assemble_from_reader(source).expect("error while assembling cartridge");
             cartridge
                 .save_to_path(&cmd.output)
                 .expect("error while saving cartridge");
         }
+        SubCommands::Diff(cmd) => {
+            let old = load_cartridge_or_exit(&cmd.old);
+            let new = load_cartridge_or_exit(&cmd.new);
+
+            for line in format_diff(&old, &new) {
+                println!("{}", line);
+            }
+        }
+        SubCommands::Eval(cmd) => {
+            let cartridge = match build_eval_cartridge(&cmd) {
+                Ok(cartridge) => cartridge,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(EXIT_BAD_CARTRIDGE);
+                }
+            };
+
+            if cmd.dump {
+                cartridge.write_disassembly_to_file(None);
+                return Ok(());
+            }
+
+            let mut emulator = Emulator::new();
+            emulator.load_game(&cartridge)?;
+
+            let mut driver = build_window_driver(cmd.software, KeyMap::default());
+            let result = driver.run_emulator(emulator, EmulatorContext::new(), cartridge);
+            if let Err(e) = &result {
+                eprintln!("execution error: {}", e);
+            }
+
+            process::exit(emulation_exit_code(&result));
+        }
         SubCommands::Disassemble(cmd) => {
-            let cartridge_handle = Cartridge::load_from_path(&cmd.file)?;
-            cartridge_handle.write_disassembly_to_file(cmd.output);
+            let cartridge_handle = load_disassembly_input(&cmd.file)?;
+            if cmd.html {
+                let html = cartridge_handle.disassemble_to_html();
+                match cmd.output {
+                    Some(path) => std::fs::write(path, html)?,
+                    None => print!("{}", html),
+                }
+            } else {
+                cartridge_handle.write_disassembly_to_file(cmd.output);
+            }
         }
         SubCommands::Play(cmd) => {
             // CLI mode.
-            let cartridge_handle = Cartridge::load_from_path(&cmd.file);
-            if let Err(error) = cartridge_handle {
-                eprintln!("{}", error);
-                process::exit(1);
+            let mut cartridge = load_cartridge_or_exit(&cmd.file);
+            if let Some(patch) = &cmd.patch {
+                let applied = std::fs::read(patch)
+                    .map_err(Into::into)
+                    .and_then(|ips| cartridge.apply_ips(&ips));
+                if let Err(e) = applied {
+                    eprintln!("{}", e);
+                    process::exit(EXIT_BAD_CARTRIDGE);
+                }
             }
 
-            // Extract cartridge.
-            let cartridge = cartridge_handle.unwrap();
-
             let mut emulator = Emulator::new();
-            let emulator_context = EmulatorContext::new();
-            emulator.load_game(&cartridge);
+            let mut emulator_context = EmulatorContext::new();
+            emulator_context.set_warmup_instructions(cmd.warmup);
+            if cmd.trace_append {
+                emulator_context.set_tracefile_mode(TracefileMode::Append);
+            }
+            emulator_context.set_coverage_tracking(cmd.coverage);
+            emulator_context.set_halt_on_self_jump(cmd.halt_on_self_jump);
+            if let Some(mode) = cartridge.get_compat_mode() {
+                emulator.cpu.set_compat_mode(mode);
+            }
+            emulator.load_game(&cartridge)?;
+            emulator
+                .cpu
+                .peripherals
+                .input
+                .set_sticky_keys(cmd.sticky_keys);
+            if cmd.no_audio {
+                emulator.cpu.drivers.disable_audio();
+            }
 
             if let Some(trace) = cmd.trace {
                 emulator.set_tracefile(&trace.to_string_lossy().to_string());
             }
 
-            let mut driver = MQWindowDriver::new();
-            if let Err(e) = driver.run_emulator(emulator, emulator_context, cartridge) {
-                eprintln!("execution error: {}", e);
-                process::exit(1);
+            let keymap = if cmd.two_player {
+                KeyMap::two_player()
+            } else {
+                KeyMap::default()
+            };
+            let mut driver = build_window_driver(cmd.software, keymap);
+            let result = driver.run_emulator(emulator, emulator_context, cartridge);
+            match &result {
+                Ok(emulator) => {
+                    if cmd.coverage {
+                        let mut coverage: Vec<_> = emulator.coverage().into_iter().collect();
+                        coverage.sort_unstable();
+
+                        println!("executed opcodes ({}):", coverage.len());
+                        for name in coverage {
+                            println!("  {}", name);
+                        }
+                    }
+                }
+                Err(e) => eprintln!("execution error: {}", e),
             }
+
+            dump_state_on_exit(&result, &cmd.dump_state_on_exit);
+            process::exit(emulation_exit_code(&result));
         }
         SubCommands::Debug(cmd) => {
             // CLI mode.
-            let cartridge_handle = Cartridge::load_from_path(&cmd.file);
-            if let Err(error) = cartridge_handle {
-                eprintln!("{}", error);
-                process::exit(1);
-            }
-
-            // Extract cartridge.
-            let cartridge = cartridge_handle.unwrap();
+            let cartridge = load_cartridge_or_exit(&cmd.file);
 
             let mut emulator = Emulator::new();
             let emulator_context = EmulatorContext::new();
-            emulator.load_game(&cartridge);
-
-            let debugger = Debugger::new();
-            let mut debugger_context = DebuggerContext::new();
-            debugger_context.set_address(INITIAL_MEMORY_POINTER);
-
-            for v in &cmd.breakpoint {
-                debugger_context.register_breakpoint_str(v).unwrap();
+            emulator.load_game(&cartridge)?;
+            if cmd.no_audio {
+                emulator.cpu.drivers.disable_audio();
             }
 
-            debugger_context.register_breakpoint(INITIAL_MEMORY_POINTER);
+            let debugger = Debugger::new();
+            let debugger_context = match build_debugger_context(&cmd) {
+                Ok(ctx) => ctx,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            };
 
-            let mut driver = MQWindowDriver::new();
-            if let Err(e) = driver.run_debugger(
+            let mut driver = build_window_driver(cmd.software, KeyMap::default());
+            let result = driver.run_debugger(
                 debugger,
                 debugger_context,
                 emulator,
                 emulator_context,
                 cartridge,
-            ) {
+            );
+            if let Err(e) = &result {
                 eprintln!("execution error: {}", e);
-                process::exit(1);
             }
+
+            dump_state_on_exit(&result, &cmd.dump_state_on_exit);
+            process::exit(emulation_exit_code(&result));
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8_core::core::quirks::CompatMode;
+
+    #[test]
+    fn test_verbosity_level() {
+        assert_eq!(verbosity_level(0), LevelFilter::INFO);
+        assert_eq!(verbosity_level(1), LevelFilter::DEBUG);
+        assert_eq!(verbosity_level(2), LevelFilter::TRACE);
+        assert_eq!(verbosity_level(5), LevelFilter::TRACE);
+    }
+
+    #[test]
+    fn test_assemble_from_reader() {
+        let source = "CLS\nLD V0, 12\nJP 0202\n";
+        let cartridge = assemble_from_reader(source.as_bytes()).unwrap();
+        assert_eq!(cartridge.get_data(), &[0x00, 0xE0, 0x60, 0x12, 0x12, 0x02]);
+
+        assert!(assemble_from_reader("NOPE V0".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_eval_asm() {
+        let args = Args::from_args(
+            &["chip8-cli"],
+            &["eval", "--asm", "LD V0, 05\\nADD V0, 01", "--dump"],
+        )
+        .unwrap();
+        let cmd = match args.nested {
+            SubCommands::Eval(cmd) => cmd,
+            _ => unreachable!(),
+        };
+
+        assert!(cmd.dump);
+        let cartridge = build_eval_cartridge(&cmd).unwrap();
+        assert_eq!(cartridge.get_data(), &[0x60, 0x05, 0x70, 0x01]);
+    }
+
+    #[test]
+    fn test_eval_hex() {
+        let args = Args::from_args(&["chip8-cli"], &["eval", "--hex", "6005 7001"]).unwrap();
+        let cmd = match args.nested {
+            SubCommands::Eval(cmd) => cmd,
+            _ => unreachable!(),
+        };
+        let cartridge = build_eval_cartridge(&cmd).unwrap();
+        assert_eq!(cartridge.get_data(), &[0x60, 0x05, 0x70, 0x01]);
+
+        let args = Args::from_args(&["chip8-cli"], &["eval", "--hex", "60Z5"]).unwrap();
+        let cmd = match args.nested {
+            SubCommands::Eval(cmd) => cmd,
+            _ => unreachable!(),
+        };
+        assert!(build_eval_cartridge(&cmd).is_err());
+
+        let args = Args::from_args(&["chip8-cli"], &["eval"]).unwrap();
+        let cmd = match args.nested {
+            SubCommands::Eval(cmd) => cmd,
+            _ => unreachable!(),
+        };
+        assert!(build_eval_cartridge(&cmd).is_err());
+    }
+
+    #[test]
+    fn test_format_diff() {
+        let old = Cartridge::from_bytes(&[0x00, 0xE0, 0x63, 0x00]).unwrap();
+        let new = Cartridge::from_bytes(&[0x00, 0xE0, 0x63, 0x2A, 0x00, 0xFD]).unwrap();
+
+        assert_eq!(
+            format_diff(&old, &new),
+            vec![
+                "0203: 00 -> 2A    ; 0202: LD V3, 00 -> 0202: LD V3, 2A",
+                "0204: -- -> 00    ; - -> 0204: EXIT",
+                "0205: -- -> FD    ; - -> 0204: EXIT",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_cartridge_from_source() {
+        let path = std::env::temp_dir().join(format!("chip8-cli-{}.asm", process::id()));
+        std::fs::write(&path, ".compat schip\nHIGH\nEXIT\n").unwrap();
+
+        let cartridge = load_cartridge(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(cartridge.get_compat_mode(), Some(CompatMode::SChip));
+        assert_eq!(cartridge.get_data(), &[0x00, 0xFF, 0x00, 0xFD]);
+    }
+
+    #[test]
+    fn test_run_to() {
+        use chip8_core::{debugger::DebuggerStream, emulator::EmulatorBuilder};
+
+        let history_path =
+            std::env::temp_dir().join(format!("chip8-cli-history-{}.txt", process::id()));
+        let history = history_path.to_string_lossy().into_owned();
+
+        let args = Args::from_args(
+            &["chip8-cli"],
+            &[
+                "debug",
+                "missing.ch8",
+                "--run-to",
+                "0x0206",
+                "--history-file",
+                &history,
+            ],
+        )
+        .unwrap();
+        let cmd = match args.nested {
+            SubCommands::Debug(cmd) => cmd,
+            _ => unreachable!(),
+        };
+
+        let mut debug_ctx = build_debugger_context(&cmd).unwrap();
+        debug_ctx.set_manual();
+        debug_ctx.is_continuing = true;
+
+        let cartridge = Assembler::from_string("LD V0, 01\nADD V0, 01\nCALL 0208\nJP 0204\nRET")
+            .assemble_cartridge()
+            .unwrap();
+        let (mut emulator, mut emulator_ctx) = EmulatorBuilder::new()
+            .with_cartridge(cartridge)
+            .build()
+            .unwrap();
+        let debugger = Debugger::new();
+        let mut stream = DebuggerStream::new();
+
+        for _ in 0..1000 {
+            if debug_ctx.is_paused() {
+                break;
+            }
+
+            debugger.step(
+                &mut emulator,
+                &mut emulator_ctx,
+                &mut debug_ctx,
+                &mut stream,
+            );
+        }
+
+        assert!(debug_ctx.is_paused());
+        assert_eq!(emulator.cpu.peripherals.memory.get_pointer(), 0x0206);
+        assert_eq!(debug_ctx.temporary_breakpoint, None);
+
+        let args = Args::from_args(
+            &["chip8-cli"],
+            &[
+                "debug",
+                "missing.ch8",
+                "--run-to",
+                "zz",
+                "--history-file",
+                &history,
+            ],
+        );
+        match args.unwrap().nested {
+            SubCommands::Debug(cmd) => assert!(build_debugger_context(&cmd).is_err()),
+            _ => unreachable!(),
+        }
+
+        std::fs::remove_file(&history_path).ok();
+    }
+
+    #[test]
+    fn test_emulation_exit_code() {
+        use chip8_core::emulator::{EmulationState, EmulatorBuilder};
+
+        let run = |source: &str| -> CResult<Emulator> {
+            let cartridge = Assembler::from_string(source).assemble_cartridge()?;
+            let (mut emulator, mut ctx) =
+                EmulatorBuilder::new().with_cartridge(cartridge).build()?;
+            while !matches!(
+                emulator.step(&mut ctx),
+                EmulationState::Quit | EmulationState::Error
+            ) {}
+            Ok(emulator)
+        };
+
+        assert_eq!(
+            emulation_exit_code(&run("LD V0, 01\nRET")),
+            EXIT_RUNTIME_ERROR
+        );
+        assert_eq!(
+            emulation_exit_code(&run("LD V0, 01\nCALL 0202")),
+            EXIT_RUNTIME_ERROR
+        );
+        assert_eq!(emulation_exit_code(&run("LD V0, 01\nEXIT")), EXIT_OK);
+        assert_eq!(
+            emulation_exit_code(&Err("driver error".into())),
+            EXIT_RUNTIME_ERROR
+        );
+    }
+
+    #[test]
+    fn test_dump_state_on_exit() {
+        use chip8_core::{
+            drivers::WindowInterface,
+            emulator::{EmulationState, EmulatorBuilder},
+        };
+
+        let path = std::env::temp_dir().join(format!("chip8-cli-state-{}.json", process::id()));
+        let args = Args::from_args(
+            &["chip8-cli"],
+            &[
+                "play",
+                "missing.ch8",
+                "--software",
+                "--dump-state-on-exit",
+                path.to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+        let cmd = match args.nested {
+            SubCommands::Play(cmd) => cmd,
+            _ => unreachable!(),
+        };
+
+        // Headless run, without the terminal renderer output.
+        struct HeadlessDriver;
+        impl WindowInterface for HeadlessDriver {
+            fn run_emulator(
+                &mut self,
+                mut emulator: Emulator,
+                mut ctx: EmulatorContext,
+                _cartridge: Cartridge,
+            ) -> CResult<Emulator> {
+                while !matches!(
+                    emulator.step(&mut ctx),
+                    EmulationState::Quit | EmulationState::Error
+                ) {}
+                Ok(emulator)
+            }
+
+            fn run_debugger(
+                &mut self,
+                _debugger: Debugger,
+                _debugger_ctx: DebuggerContext,
+                emulator: Emulator,
+                _emulator_ctx: EmulatorContext,
+                _cartridge: Cartridge,
+            ) -> CResult<Emulator> {
+                Ok(emulator)
+            }
+        }
+
+        let assemble = || {
+            Assembler::from_string("LD V0, 2A\nLD V1, 01\nEXIT")
+                .assemble_cartridge()
+                .unwrap()
+        };
+        let (emulator, ctx) = EmulatorBuilder::new()
+            .with_cartridge(assemble())
+            .build()
+            .unwrap();
+        let result = HeadlessDriver.run_emulator(emulator, ctx, assemble());
+        dump_state_on_exit(&result, &cmd.dump_state_on_exit);
+
+        let json = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let state = MachineState::from_json(&json).unwrap();
+        assert_eq!(state.pc, 0x0204);
+        assert_eq!(state.registers[0], 0x2A);
+        assert_eq!(state.exit_reason, "Exit");
+    }
+
+    #[test]
+    fn test_verbose_args() {
+        let args = Args::from_args(&["chip8-cli"], &["-v", "-v", "version"]).unwrap();
+        assert_eq!(args.verbose, 2);
+    }
+}
@@ -0,0 +1,39 @@
+//! Core benchmarks.
+
+mod inputs;
+
+use chip8_core::{core::opcodes::get_opcode_enum, peripherals::screen::Screen};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use inputs::{OPCODES, SPRITE};
+
+fn bench_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Elements(OPCODES.len() as u64));
+    group.bench_function("get_opcode_enum", |b| {
+        b.iter(|| {
+            for (opcode, _) in OPCODES {
+                black_box(get_opcode_enum(black_box(*opcode)));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_draw(c: &mut Criterion) {
+    let mut group = c.benchmark_group("draw");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("draw_sprite", |b| {
+        let mut screen = Screen::new();
+        let mut x = 0;
+
+        b.iter(|| {
+            // Move around to cover wrapping.
+            x = (x + 3) % 64;
+            black_box(screen.draw_sprite(x, x / 2, black_box(&SPRITE)));
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode, bench_draw);
+criterion_main!(benches);
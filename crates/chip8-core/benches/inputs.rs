@@ -0,0 +1,33 @@
+//! Benchmark inputs, shared with the smoke tests.
+
+#![allow(dead_code)]
+
+/// Representative opcodes, with their expected disassembly.
+pub const OPCODES: &[(u16, &str)] = &[
+    (0x00E0, "CLS"),
+    (0x00EE, "RET"),
+    (0x1228, "JP 0228"),
+    (0x2300, "CALL 0300"),
+    (0x3A12, "SE VA, 12"),
+    (0x4B00, "SNE VB, 00"),
+    (0x5120, "SE V1, V2"),
+    (0x6142, "LD V1, 42"),
+    (0x7105, "ADD V1, 05"),
+    (0x8120, "LD V1, V2"),
+    (0x8124, "ADD V1, V2"),
+    (0x8125, "SUB V1, V2"),
+    (0xA300, "LD I, 0300"),
+    (0xC0FF, "RND V0, FF"),
+    (0xD015, "DRW V0, V1, 05"),
+    (0xE19E, "SKP V1"),
+    (0xF007, "LD V0, DT"),
+    (0xF029, "LD F, V0"),
+    (0xF033, "LD B, V0"),
+    (0xF065, "LD V0, [I]"),
+];
+
+/// 8x8 sprite.
+pub const SPRITE: [u8; 8] = [0xFF, 0x81, 0xBD, 0xA5, 0xA5, 0xBD, 0x81, 0xFF];
+
+/// Lit pixels in `SPRITE`.
+pub const SPRITE_LIT_PIXELS: usize = 40;
@@ -0,0 +1,49 @@
+//! Embed the emulator in another program, without any window.
+//!
+//! Run with `cargo run -p chip8-core --example embed`.
+
+use chip8_core::emulator::{EmulationState, EmulatorBuilder};
+
+/// Draw the `0` to `3` font sprites side by side, then loop forever.
+const ROM: &[u8] = &[
+    0x00, 0xE0, // CLS
+    0x60, 0x00, // LD V0, 00 (X)
+    0x61, 0x00, // LD V1, 00 (font character)
+    0xF1, 0x29, // LD F, V1
+    0xD0, 0x15, // DRW V0, V1, 5
+    0x70, 0x05, // ADD V0, 05
+    0x71, 0x01, // ADD V1, 01
+    0x31, 0x04, // SE V1, 04
+    0x12, 0x06, // JP 0206
+    0x12, 0x12, // JP 0212
+];
+
+fn main() {
+    let (mut emulator, mut ctx) = EmulatorBuilder::new()
+        .from_bytes(ROM)
+        .expect("ROM should fit in a cartridge")
+        .build()
+        .expect("cartridge should fit in memory");
+
+    for _ in 0..10 {
+        if let EmulationState::Quit | EmulationState::Error = emulator.step_frame(&mut ctx) {
+            break;
+        }
+    }
+
+    let snapshot = emulator.snapshot();
+    let lit = snapshot
+        .screen_data
+        .get_pixels()
+        .iter()
+        .filter(|p| **p != 0)
+        .count();
+
+    println!("{} instructions executed", snapshot.instruction_count);
+    println!("{} pixels lit", lit);
+
+    // Only show the top-left corner of the screen.
+    for line in emulator.cpu.peripherals.screen.to_ascii().lines().take(6) {
+        println!("{}", &line[..24]);
+    }
+}
@@ -1,6 +1,6 @@
 //! Assembler.
 
-use std::{fs::File, io::Read, path::Path};
+use std::{error::Error, fmt, fs::File, io::Read, path::Path};
 
 use once_cell::sync::Lazy;
 use regex::Regex;
@@ -8,13 +8,30 @@ use tracing::debug;
 
 use crate::{
     core::{
-        opcodes::{BadInstruction, OpCode},
+        opcodes::{get_opcode_enum, get_opcode_str, BadInstruction, OpCode},
+        quirks::CompatMode,
         types::{convert_hex_addr, convert_hex_byte, C8Addr, C8Byte, C8RegIdx},
     },
     errors::CResult,
     peripherals::cartridge::{Cartridge, CARTRIDGE_MAX_SIZE},
 };
 
+/// Bad directive.
+#[derive(Debug)]
+pub struct BadDirective(pub String);
+
+impl Error for BadDirective {
+    fn description(&self) -> &str {
+        "bad directive"
+    }
+}
+
+impl fmt::Display for BadDirective {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bad directive: {}.", self.0)
+    }
+}
+
 /// Assembler.
 #[derive(Debug, PartialEq)]
 pub struct Assembler {
@@ -35,8 +52,12 @@ pub struct Instruction {
 pub enum ArgToken {
     /// Register.
     Register(C8Byte),
+    /// Register range.
+    RegisterRange(C8RegIdx, C8RegIdx),
     /// Byte.
     Byte(C8Byte),
+    /// Nibble (single hexadecimal digit).
+    Nibble(C8Byte),
     /// Address.
     Address(C8Addr),
     /// Key.
@@ -70,6 +91,22 @@ impl ArgToken {
         }
     }
 
+    /// To register range.
+    ///
+    /// # Returns
+    ///
+    /// * Register range result.
+    ///
+    pub fn to_register_range(&self) -> CResult<(C8RegIdx, C8RegIdx)> {
+        if let ArgToken::RegisterRange(x, y) = self {
+            Ok((*x, *y))
+        } else {
+            Err(Box::new(BadInstruction(
+                "should be a register range".to_owned(),
+            )))
+        }
+    }
+
     /// To byte.
     ///
     /// # Returns
@@ -84,6 +121,22 @@ impl ArgToken {
         }
     }
 
+    /// To nibble.
+    ///
+    /// Bytes are accepted too, as printed by the disassembler (e.g.
+    /// `DRW V0, V1, 05`).
+    ///
+    /// # Returns
+    ///
+    /// * Nibble result.
+    ///
+    pub fn to_nibble(&self) -> CResult<C8Byte> {
+        match self {
+            ArgToken::Nibble(x) | ArgToken::Byte(x) => Ok(*x),
+            _ => Err(Box::new(BadInstruction("should be a nibble".to_owned()))),
+        }
+    }
+
     /// To address.
     ///
     /// # Returns
@@ -100,10 +153,24 @@ impl ArgToken {
 }
 
 fn parse_arg_token(arg: &str) -> CResult<ArgToken> {
-    if arg.len() == 4 {
+    if arg.len() == 5 {
+        // Register range.
+        match (arg.get(0..1), arg.get(2..4), arg.get(1..2), arg.get(4..5)) {
+            (Some("V"), Some("-V"), Some(x), Some(y)) => {
+                match (convert_hex_byte(x), convert_hex_byte(y)) {
+                    (Some(x), Some(y)) => Ok(ArgToken::RegisterRange(x.get(), y.get())),
+                    _ => Err(Box::new(BadInstruction(format!(
+                        "bad register range: {}",
+                        arg
+                    )))),
+                }
+            }
+            _ => Err(Box::new(BadInstruction("bad instruction".to_owned()))),
+        }
+    } else if arg.len() == 4 {
         // Address.
         let addr = convert_hex_addr(arg).unwrap();
-        Ok(ArgToken::Address(addr))
+        Ok(ArgToken::Address(addr.get()))
     } else if arg.len() == 3 {
         // I value.
         if arg.get(0..3).unwrap() == "[I]" {
@@ -121,29 +188,24 @@ fn parse_arg_token(arg: &str) -> CResult<ArgToken> {
         } else if arg.get(0..1).unwrap() == "V" {
             // Register.
             let reg = convert_hex_byte(arg.get(1..2).unwrap()).unwrap();
-            Ok(ArgToken::Register(reg))
+            Ok(ArgToken::Register(reg.get()))
         } else {
             // Byte.
             let byte = convert_hex_byte(arg).unwrap();
-            Ok(ArgToken::Byte(byte))
+            Ok(ArgToken::Byte(byte.get()))
         }
     } else if arg.len() == 1 {
         if arg.get(0..1).unwrap() == "I" {
             // I pointer.
             Ok(ArgToken::IPointer)
-        } else if arg.get(0..1).unwrap() == "F" {
-            // Sprite.
-            Ok(ArgToken::Sprite)
-        } else if arg.get(0..1).unwrap() == "B" {
-            // BCD.
-            Ok(ArgToken::BCD)
         } else if arg.get(0..1).unwrap() == "K" {
             // Key.
             Ok(ArgToken::Key)
         } else {
-            // Byte.
-            let byte = convert_hex_byte(arg).unwrap();
-            Ok(ArgToken::Byte(byte))
+            // Nibble, including `B` and `F` (see `parse_ld_arg_tokens`).
+            let nibble = convert_hex_byte(arg)
+                .ok_or_else(|| BadInstruction(format!("bad nibble: {}", arg)))?;
+            Ok(ArgToken::Nibble(nibble.get()))
         }
     } else {
         Err(Box::new(BadInstruction("bad instruction".to_owned())))
@@ -171,6 +233,27 @@ fn parse_2_arg_token(args: Vec<&str>) -> CResult<(ArgToken, ArgToken)> {
     Ok((v1, v2))
 }
 
+/// Parse `LD`/`LDX` arguments.
+///
+/// A lone `B` or `F` target is the BCD or sprite keyword, as in `LD B, V0`.
+fn parse_ld_arg_tokens(args: Vec<&str>) -> CResult<(ArgToken, ArgToken)> {
+    let arg1 = args
+        .first()
+        .ok_or_else(|| BadInstruction("missing first argument".to_owned()))?;
+    let arg2 = args
+        .get(1)
+        .ok_or_else(|| BadInstruction("missing second argument".to_owned()))?;
+
+    let v1 = match *arg1 {
+        "B" => ArgToken::BCD,
+        "F" => ArgToken::Sprite,
+        _ => parse_arg_token(arg1)?,
+    };
+    let v2 = parse_arg_token(arg2)?;
+
+    Ok((v1, v2))
+}
+
 fn parse_3_arg_token(args: Vec<&str>) -> CResult<(ArgToken, ArgToken, ArgToken)> {
     let arg1 = args
         .get(0)
@@ -201,7 +284,7 @@ fn parse_3_arg_token(args: Vec<&str>) -> CResult<(ArgToken, ArgToken, ArgToken)>
 ///
 pub fn words_to_opcode(words: &str) -> CResult<OpCode> {
     static RE: Lazy<Regex> =
-        Lazy::new(|| Regex::new(r"(?P<opcode>[A-Z]+)( (?P<args>.*))?").unwrap());
+        Lazy::new(|| Regex::new(r"(?P<opcode>[A-Z][A-Z.]*)( (?P<args>.*))?").unwrap());
 
     let caps: Vec<_> = RE.captures_iter(words).collect();
     if caps.is_empty() {
@@ -265,7 +348,7 @@ pub fn words_to_opcode(words: &str) -> CResult<OpCode> {
             }
         }
         "LD" => {
-            let (arg1, arg2) = parse_2_arg_token(args)?;
+            let (arg1, arg2) = parse_ld_arg_tokens(args)?;
 
             if let ArgToken::Sprite = arg1 {
                 OpCode::LDSprite(arg2.to_register()?)
@@ -273,6 +356,10 @@ pub fn words_to_opcode(words: &str) -> CResult<OpCode> {
                 OpCode::LDBCD(arg2.to_register()?)
             } else if let ArgToken::IPointer = arg1 {
                 OpCode::LDI(arg2.to_address()?)
+            } else if let (ArgToken::IValue, ArgToken::RegisterRange(x, y)) = (&arg1, &arg2) {
+                OpCode::StoreRange(*x, *y)
+            } else if let (ArgToken::RegisterRange(x, y), ArgToken::IValue) = (&arg1, &arg2) {
+                OpCode::LoadRange(*x, *y)
             } else if let ArgToken::IValue = arg1 {
                 OpCode::LDS(arg2.to_register()?)
             } else if let ArgToken::DelayTimer = arg2 {
@@ -312,24 +399,38 @@ pub fn words_to_opcode(words: &str) -> CResult<OpCode> {
             OpCode::SUBN(arg1.to_register()?, arg2.to_register()?)
         }
         "SHL" => {
-            let arg1 = parse_1_arg_token(args)?;
-            OpCode::SHL(arg1.to_register()?, 0x0)
+            if args.len() == 1 {
+                let arg1 = parse_1_arg_token(args)?;
+                OpCode::SHL(arg1.to_register()?, 0x0)
+            } else {
+                let (arg1, arg2) = parse_2_arg_token(args)?;
+                OpCode::SHL(arg1.to_register()?, arg2.to_register()?)
+            }
         }
         "SHR" => {
-            let arg1 = parse_1_arg_token(args)?;
-            OpCode::SHR(arg1.to_register()?, 0x0)
+            if args.len() == 1 {
+                let arg1 = parse_1_arg_token(args)?;
+                OpCode::SHR(arg1.to_register()?, 0x0)
+            } else {
+                let (arg1, arg2) = parse_2_arg_token(args)?;
+                OpCode::SHR(arg1.to_register()?, arg2.to_register()?)
+            }
         }
         "LDI" => {
             let arg1 = parse_1_arg_token(args)?;
             OpCode::LDI(arg1.to_address()?)
         }
+        "LDI.L" => {
+            let arg1 = parse_1_arg_token(args)?;
+            OpCode::LongI(arg1.to_address()?)
+        }
         "RND" => {
             let (arg1, arg2) = parse_2_arg_token(args)?;
             OpCode::RND(arg1.to_register()?, arg2.to_byte()?)
         }
         "DRW" => {
             let (arg1, arg2, arg3) = parse_3_arg_token(args)?;
-            OpCode::DRW(arg1.to_register()?, arg2.to_register()?, arg3.to_byte()?)
+            OpCode::DRW(arg1.to_register()?, arg2.to_register()?, arg3.to_nibble()?)
         }
         "SKP" => {
             let arg = parse_1_arg_token(args)?;
@@ -341,7 +442,11 @@ pub fn words_to_opcode(words: &str) -> CResult<OpCode> {
         }
         "SCRD" => {
             let arg = parse_1_arg_token(args)?;
-            OpCode::SCRD(arg.to_byte()?)
+            OpCode::SCRD(arg.to_nibble()?)
+        }
+        "SCRU" => {
+            let arg = parse_1_arg_token(args)?;
+            OpCode::SCRU(arg.to_nibble()?)
         }
         "SCRR" => OpCode::SCRR,
         "SCRL" => OpCode::SCRL,
@@ -353,7 +458,7 @@ pub fn words_to_opcode(words: &str) -> CResult<OpCode> {
             OpCode::DRWX(arg1.to_register()?, arg2.to_register()?)
         }
         "LDX" => {
-            let (arg1, arg2) = parse_2_arg_token(args)?;
+            let (arg1, arg2) = parse_ld_arg_tokens(args)?;
             if let ArgToken::Sprite = arg1 {
                 // LDXSprite.
                 OpCode::LDXSprite(arg2.to_register()?)
@@ -465,11 +570,41 @@ pub fn opcode_enum_to_addr(opcode: OpCode) -> C8Addr {
         OpCode::LDXSprite(reg) => convert_reg(0xF030, reg),
         OpCode::LDXS(reg) => convert_reg(0xF075, reg),
         OpCode::LDXR(reg) => convert_reg(0xF085, reg),
+        OpCode::LongI(_) => 0xF000,
+        OpCode::StoreRange(reg1, reg2) => convert_reg1_reg2(0x5002, reg1, reg2),
+        OpCode::LoadRange(reg1, reg2) => convert_reg1_reg2(0x5003, reg1, reg2),
+        OpCode::SCRU(reg) => 0x00D0 + C8Addr::from(reg),
         OpCode::EMPTY => 0x0000,
         OpCode::DATA(addr) => addr,
     }
 }
 
+/// Opcode enum to bytes.
+///
+/// Two-word instructions also emit their immediate word.
+///
+/// # Arguments
+///
+/// * `opcode` - Opcode enum.
+///
+/// # Returns
+///
+/// * Big-endian bytes.
+///
+pub fn opcode_enum_to_bytes(opcode: OpCode) -> Vec<C8Byte> {
+    let immediate = match opcode {
+        OpCode::LongI(addr) => Some(addr),
+        _ => None,
+    };
+
+    let mut bytes = opcode_enum_to_addr(opcode).to_be_bytes().to_vec();
+    if let Some(addr) = immediate {
+        bytes.extend_from_slice(&addr.to_be_bytes());
+    }
+
+    bytes
+}
+
 /// Resolve instruction.
 ///
 /// # Arguments
@@ -485,6 +620,37 @@ pub fn resolve_instruction(words: &str) -> CResult<C8Addr> {
     Ok(opcode_enum_to_addr(opcode_enum))
 }
 
+/// Assemble a single instruction to bytes.
+///
+/// Two-word instructions also emit their immediate word.
+///
+/// # Arguments
+///
+/// * `line` - Instruction line.
+///
+/// # Returns
+///
+/// * Big-endian bytes result.
+///
+pub fn assemble_instruction(line: &str) -> CResult<Vec<C8Byte>> {
+    let opcode_enum = words_to_opcode(line)?;
+    Ok(opcode_enum_to_bytes(opcode_enum))
+}
+
+/// Disassemble a single word.
+///
+/// # Arguments
+///
+/// * `word` - Word.
+///
+/// # Returns
+///
+/// * String tuple (opcode, verbose opcode).
+///
+pub fn disassemble_word(word: C8Addr) -> (String, String) {
+    get_opcode_str(&get_opcode_enum(word))
+}
+
 impl Instruction {
     /// Resolve instruction.
     ///
@@ -499,6 +665,21 @@ impl Instruction {
     pub fn resolve(&self) -> CResult<C8Addr> {
         resolve_instruction(&self.words)
     }
+
+    /// Resolve instruction to bytes.
+    ///
+    /// # Returns
+    ///
+    /// * Bytes result.
+    ///
+    pub fn resolve_bytes(&self) -> CResult<Vec<C8Byte>> {
+        if let Some(byte) = self.words.strip_prefix("DB ") {
+            // Raw byte.
+            return Ok(vec![parse_arg_token(byte.trim())?.to_byte()?]);
+        }
+
+        Ok(opcode_enum_to_bytes(words_to_opcode(&self.words)?))
+    }
 }
 
 impl Default for Assembler {
@@ -570,7 +751,7 @@ impl Assembler {
     ///
     pub fn assemble_line_from_str(&self, line: &str) -> Option<Instruction> {
         static RE: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(r"((?P<line>[0-9A-Z]{4})\|)?([ *]?\((?P<opcode>[0-9A-Z]{4})\) )? ?((?P<instr>[A-Z0-9, \[\]]+))?(;(?P<comment>.*))?").unwrap()
+            Regex::new(r"((?P<line>[0-9A-Z]{4})\|)?([ *]?\((?P<opcode>[0-9A-Z]{4})\) )? ?((?P<instr>[A-Z0-9., \[\]-]+))?(;(?P<comment>.*))?").unwrap()
         });
 
         let caps: Vec<_> = RE.captures_iter(line).collect();
@@ -582,10 +763,10 @@ impl Assembler {
         let cap = &caps[0];
         let line = cap
             .name("line")
-            .map(|c| convert_hex_addr(c.as_str()).unwrap());
+            .map(|c| convert_hex_addr(c.as_str()).unwrap().get());
         let opcode = cap
             .name("opcode")
-            .map(|c| convert_hex_addr(c.as_str()).unwrap());
+            .map(|c| convert_hex_addr(c.as_str()).unwrap().get());
         let words = cap.name("instr").map(|c| c.as_str().trim().to_owned())?;
         let comment = cap.name("comment").map(|c| c.as_str().trim().to_owned());
 
@@ -597,8 +778,57 @@ impl Assembler {
         })
     }
 
+    /// Parse directive line.
+    ///
+    /// Supported directives:
+    ///
+    /// * `.compat chip8|schip|xochip` - Target compatibility mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - Line, without leading whitespace.
+    ///
+    /// # Returns
+    ///
+    /// * Compatibility mode result.
+    ///
+    fn parse_directive(line: &str) -> CResult<CompatMode> {
+        let line = line.split(';').next().unwrap_or("").trim();
+        let mut words = line.split_whitespace();
+
+        match (words.next(), words.next(), words.next()) {
+            (Some(".compat"), Some(mode), None) => match mode.to_lowercase().as_ref() {
+                "chip8" => Ok(CompatMode::Chip8),
+                "schip" => Ok(CompatMode::SChip),
+                "xochip" => Ok(CompatMode::XOChip),
+                _ => Err(Box::new(BadDirective(line.to_owned()))),
+            },
+            _ => Err(Box::new(BadDirective(line.to_owned()))),
+        }
+    }
+
+    /// Get the compatibility mode declared by a `.compat` directive.
+    ///
+    /// # Returns
+    ///
+    /// * Compatibility mode option result.
+    ///
+    pub fn compat_mode(&self) -> CResult<Option<CompatMode>> {
+        let mut mode = None;
+        for line in self.contents.split('\n') {
+            let line = line.trim_start();
+            if line.starts_with('.') {
+                mode = Some(Self::parse_directive(line)?);
+            }
+        }
+
+        Ok(mode)
+    }
+
     /// Assemble cartridge data.
     ///
+    /// Directive lines are skipped.
+    ///
     /// # Arguments
     ///
     /// * `contents` - Contents.
@@ -612,13 +842,13 @@ impl Assembler {
         debug!("assembling instructions ...");
         let mut data: Vec<C8Byte> = Vec::with_capacity(CARTRIDGE_MAX_SIZE);
         for line in self.contents.split('\n') {
+            if line.trim_start().starts_with('.') {
+                continue;
+            }
+
             let instruction = self.assemble_line_from_str(line);
             if let Some(x) = instruction {
-                let code = x.resolve()?;
-                let b1 = ((0xFF00 & code) >> 8) as C8Byte;
-                let b2 = (0x00FF & code) as C8Byte;
-                data.push(b1);
-                data.push(b2);
+                data.extend(x.resolve_bytes()?);
             }
         }
         debug!("{} instructions assembled", data.len());
@@ -641,6 +871,7 @@ impl Assembler {
         let data = self.assemble_data()?;
         let mut cartridge = Cartridge::new_empty();
         cartridge.set_data(data);
+        cartridge.set_compat_mode(self.compat_mode()?);
 
         Ok(cartridge)
     }
@@ -649,6 +880,7 @@ impl Assembler {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::opcodes::get_opcode_enum_from_array;
 
     #[test]
     fn test_assemble_from_str() {
@@ -808,6 +1040,14 @@ mod tests {
         );
         assert_eq!(words_to_opcode("SHR V1").unwrap(), OpCode::SHR(0x1, 0x0));
         assert_eq!(words_to_opcode("SHL V1").unwrap(), OpCode::SHL(0x1, 0x0));
+        assert_eq!(
+            words_to_opcode("SHR V1, V2").unwrap(),
+            OpCode::SHR(0x1, 0x2)
+        );
+        assert_eq!(
+            words_to_opcode("SHL V1, V2").unwrap(),
+            OpCode::SHL(0x1, 0x2)
+        );
         assert_eq!(
             words_to_opcode("SNE V1, V2").unwrap(),
             OpCode::SNE(0x1, 0x2)
@@ -916,4 +1156,162 @@ mod tests {
         assert_eq!(opcode_enum_to_addr(OpCode::EMPTY), 0x0000);
         assert_eq!(opcode_enum_to_addr(OpCode::DATA(0x9999)), 0x9999);
     }
+
+    #[test]
+    fn test_assemble_instruction() {
+        assert_eq!(assemble_instruction("LD V0, 05").unwrap(), [0x60, 0x05]);
+        assert_eq!(assemble_instruction("CLS").unwrap(), [0x00, 0xE0]);
+        assert_eq!(assemble_instruction("JP 0234").unwrap(), [0x12, 0x34]);
+        assert_eq!(
+            assemble_instruction("DRW V1, V2, 05").unwrap(),
+            [0xD1, 0x25]
+        );
+        assert!(assemble_instruction("NOPE V1").is_err());
+        assert_eq!(
+            assemble_instruction("LDI.L ABCD").unwrap(),
+            [0xF0, 0x00, 0xAB, 0xCD]
+        );
+    }
+
+    #[test]
+    fn test_disassemble_word() {
+        assert_eq!(disassemble_word(0x6005).0, "LD V0, 05");
+        assert_eq!(disassemble_word(0x00E0).0, "CLS");
+        assert_eq!(disassemble_word(0xA123).0, "LD I, 0123");
+        assert_eq!(disassemble_word(0xF165).0, "LD V1, [I]");
+    }
+
+    #[test]
+    fn test_assemble_long_i() {
+        assert_eq!(
+            words_to_opcode("LDI.L ABCD").unwrap(),
+            OpCode::LongI(0xABCD)
+        );
+
+        let assembler = Assembler::from_string("LDI.L ABCD\nCLS");
+        let data = assembler.assemble_data().unwrap();
+        assert_eq!(data, vec![0xF0, 0x00, 0xAB, 0xCD, 0x00, 0xE0]);
+    }
+
+    #[test]
+    fn test_shift_round_trip() {
+        for word in &[0x8126, 0x812E] {
+            let (assembly, _) = get_opcode_str(&get_opcode_enum(*word));
+            assert_eq!(resolve_instruction(&assembly).unwrap(), *word);
+        }
+    }
+
+    /// Decode then re-encode every 16-bit word, directly and through its
+    /// assembly string.
+    ///
+    /// Every decoded variant keeps all of its fields, so re-encoding must give
+    /// back the original word, and re-assembling the original opcode.
+    ///
+    /// Lossy cases:
+    /// * `DATA` - catch-all for unknown words (including `0nnn`, as `SYS` is
+    ///   never produced by the decoder), only the variant is checked.
+    /// * `LongI` - its immediate lives in the following word, so a lone `F000`
+    ///   decodes to `LongI(0)`.
+    #[test]
+    fn test_opcode_round_trip_corpus() {
+        for word in 0x0000..=0xFFFF {
+            let opcode = get_opcode_enum(word);
+
+            if let OpCode::DATA(value) = opcode {
+                assert_eq!(value, word, "DATA should keep word {:04X}", word);
+            } else {
+                let (assembly, _) = get_opcode_str(&opcode);
+                assert_eq!(
+                    opcode_enum_to_addr(opcode),
+                    word,
+                    "re-encoding mismatch for {:04X} ({})",
+                    word,
+                    assembly
+                );
+
+                let bytes = assemble_instruction(&assembly).unwrap_or_else(|e| {
+                    panic!("could not assemble {:04X} ({}): {}", word, assembly, e)
+                });
+                assert_eq!(
+                    get_opcode_enum_from_array(&bytes, 0),
+                    get_opcode_enum(word),
+                    "re-assembling mismatch for {:04X} ({})",
+                    word,
+                    assembly
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_compat_directive() {
+        let cartridge = Assembler::from_string(".compat schip\nHIGH\nEXIT")
+            .assemble_cartridge()
+            .unwrap();
+        assert_eq!(cartridge.get_compat_mode(), Some(CompatMode::SChip));
+        assert_eq!(cartridge.get_data(), &[0x00, 0xFF, 0x00, 0xFD]);
+
+        let cartridge = Assembler::from_string("CLS").assemble_cartridge().unwrap();
+        assert_eq!(cartridge.get_compat_mode(), None);
+
+        assert!(Assembler::from_string(".compat megachip\nCLS")
+            .assemble_cartridge()
+            .is_err());
+    }
+
+    #[test]
+    fn test_register_range() {
+        assert_eq!(
+            words_to_opcode("LD [I], V2-V5").unwrap(),
+            OpCode::StoreRange(0x2, 0x5)
+        );
+        assert_eq!(
+            words_to_opcode("LD V5-V2, [I]").unwrap(),
+            OpCode::LoadRange(0x5, 0x2)
+        );
+        assert_eq!(resolve_instruction("LD [I], V2-V5").unwrap(), 0x5252);
+        assert_eq!(resolve_instruction("LD V5-V2, [I]").unwrap(), 0x5523);
+
+        let assembler = Assembler::from_string("LD [I], V2-V5 ; store");
+        assert_eq!(assembler.assemble_data().unwrap(), vec![0x52, 0x52]);
+
+        assert!(words_to_opcode("LD [I], VZ-V5").is_err());
+        assert!(words_to_opcode("LD [I], V2-VG").is_err());
+    }
+
+    #[test]
+    fn test_scroll_up() {
+        assert_eq!(words_to_opcode("SCRU 4").unwrap(), OpCode::SCRU(0x4));
+        assert_eq!(words_to_opcode("SCRU F").unwrap(), OpCode::SCRU(0xF));
+        assert_eq!(words_to_opcode("SCRD B").unwrap(), OpCode::SCRD(0xB));
+        assert_eq!(opcode_enum_to_addr(OpCode::SCRU(0x4)), 0x00D4);
+
+        let (assembly, _) = get_opcode_str(&get_opcode_enum(0x00D4));
+        assert_eq!(resolve_instruction(&assembly).unwrap(), 0x00D4);
+    }
+
+    #[test]
+    fn test_keyword_arguments() {
+        // `B`/`F` keywords only as `LD`/`LDX` targets.
+        assert_eq!(words_to_opcode("LD B, V3").unwrap(), OpCode::LDBCD(0x3));
+        assert_eq!(words_to_opcode("LD F, V3").unwrap(), OpCode::LDSprite(0x3));
+        assert_eq!(
+            words_to_opcode("LDX F, V3").unwrap(),
+            OpCode::LDXSprite(0x3)
+        );
+        assert!(words_to_opcode("LD V0, B").is_err());
+        assert!(words_to_opcode("LD V0, F").is_err());
+        assert!(words_to_opcode("RND V0, B").is_err());
+
+        // Nibbles elsewhere.
+        assert_eq!(
+            words_to_opcode("DRW V0, V1, F").unwrap(),
+            OpCode::DRW(0x0, 0x1, 0xF)
+        );
+        assert_eq!(
+            words_to_opcode("DRW V0, V1, 0F").unwrap(),
+            OpCode::DRW(0x0, 0x1, 0xF)
+        );
+        assert!(words_to_opcode("SCRD Z").is_err());
+    }
 }
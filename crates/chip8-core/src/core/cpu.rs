@@ -2,35 +2,71 @@
 
 use std::fmt;
 
-use quad_rand::gen_range;
+use tracing::{debug, trace, warn};
 
 use super::{
-    font::{Font, FONT_CHAR_HEIGHT, FONT_DATA_ADDR, SUPER_FONT_CHAR_HEIGHT, SUPER_FONT_DATA_ADDR},
-    opcodes::OpCode,
+    font::{Font, FONT_DATA_ADDR, SUPER_FONT_DATA_ADDR},
+    opcodes::{decode_fields, OpCode},
+    quirks::{CompatMode, QuirkSet},
+    rand::{RandSource, UniformRandSource},
     registers::Registers,
+    rpl::{RplFlags, RPL_FLAG_COUNT},
     savestate::SaveState,
     stack::Stack,
     timer::Timer,
-    types::{C8Addr, C8Byte},
+    types::{C8Addr, C8Byte, C8RegIdx},
 };
 use crate::{
     drivers::Drivers,
+    errors::Chip8Error,
     peripherals::{
         cartridge::Cartridge,
+        input::INPUT_STATE_COUNT,
+        memory::{Memory, INITIAL_MEMORY_POINTER},
         screen::{ScreenMode, ScreenScrollDirection},
         Peripherals,
     },
 };
 
+/// Exit reason.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    /// `EXIT` instruction.
+    Exit,
+    /// `RET` with an empty stack.
+    StackUnderflow,
+    /// `CALL` with a full stack.
+    StackOverflow,
+    /// Memory access trapped (see `CPU::trap_memory`).
+    MemoryTrap,
+    /// Jump to its own address (see `EmulatorContext::set_halt_on_self_jump`).
+    Halt,
+}
+
+/// Trapped memory access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryTrap {
+    /// Address of the instruction.
+    pub pc: C8Addr,
+    /// Access start address.
+    pub start: usize,
+    /// Access length, in bytes.
+    pub len: usize,
+}
+
 /// CHIP-8 CPU.
 pub struct CPU {
     /// Peripherals.
     pub peripherals: Peripherals,
     /// Drivers.
     pub drivers: Drivers,
+    /// Random source, used by `RND`.
+    pub rand: Box<dyn RandSource>,
 
     /// Registers.
     pub registers: Registers,
+    /// RPL user flags.
+    pub rpl_flags: RplFlags,
     /// Stack.
     pub stack: Stack,
 
@@ -56,7 +92,27 @@ pub struct CPU {
     pub speed_multiplicator: u16,
 
     /// SCHIP mode.
+    ///
+    /// Enabled by SCHIP/XO-CHIP compatibility modes, or when a game switches
+    /// resolution. Without it, `Dxy0` draws nothing.
     pub schip_mode: bool,
+
+    /// Quirks.
+    pub quirks: QuirkSet,
+
+    /// Compatibility mode.
+    pub compat_mode: CompatMode,
+
+    /// Why the CPU stopped, if it did.
+    pub exit_reason: Option<ExitReason>,
+
+    /// Trap LDS/LDR accesses to the interpreter area (below 0x200) or past
+    /// memory end, instead of silently overwriting or reading them.
+    pub trap_memory: bool,
+    /// Last trapped memory access.
+    pub memory_trap: Option<MemoryTrap>,
+
+    input_snapshot: Option<[C8Byte; INPUT_STATE_COUNT]>,
 }
 
 impl CPU {
@@ -72,9 +128,11 @@ impl CPU {
         CPU {
             peripherals: Peripherals::new(),
             drivers: Drivers::new(),
+            rand: Box::new(UniformRandSource::new()),
 
             registers: Registers::new(),
-            stack: Stack::new(),
+            rpl_flags: RplFlags::new(),
+            stack: Stack::with_capacity(CompatMode::Chip8.stack_depth()),
 
             delay_timer: Timer::new("Delay".to_string()),
             sound_timer: Timer::new("Sound".to_string()),
@@ -87,9 +145,40 @@ impl CPU {
             tracefile: None,
             savestate: None,
             schip_mode: false,
+            quirks: QuirkSet::chip8(),
+            compat_mode: CompatMode::Chip8,
+            exit_reason: None,
+            trap_memory: false,
+            memory_trap: None,
+            input_snapshot: None,
         }
     }
 
+    /// Set compatibility mode.
+    ///
+    /// Reallocate memory and stack, and apply the quirk set for the mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - Compatibility mode.
+    ///
+    pub fn set_compat_mode(&mut self, mode: CompatMode) {
+        self.compat_mode = mode;
+        self.quirks = mode.quirks();
+        self.schip_mode = mode != CompatMode::Chip8;
+
+        let initial_pointer = self.peripherals.memory.get_initial_pointer();
+        self.peripherals.memory = Memory::with_size(mode.memory_size());
+        self.peripherals.memory.set_initial_pointer(initial_pointer);
+        self.stack = Stack::with_capacity(mode.stack_depth());
+
+        debug!(
+            target: "chip8::cpu",
+            message = "Compatibility mode changed.",
+            mode = ?mode
+        );
+    }
+
     /// Set tracefile.
     ///
     /// Enable tracefile during game execution.
@@ -109,11 +198,39 @@ impl CPU {
     pub fn load_font_in_memory(&mut self) {
         self.peripherals
             .memory
-            .write_data_at_offset(FONT_DATA_ADDR, self.font.get_data());
+            .write_data_at_offset(FONT_DATA_ADDR, self.font.get_data())
+            .expect("font fits in memory");
 
         self.peripherals
             .memory
-            .write_data_at_offset(SUPER_FONT_DATA_ADDR, self.super_font.get_data());
+            .write_data_at_offset(SUPER_FONT_DATA_ADDR, self.super_font.get_data())
+            .expect("super font fits in memory");
+    }
+
+    /// Set default font.
+    ///
+    /// Replace the font used by `LD F, Vx` and load it in memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `font` - Font.
+    ///
+    pub fn set_font(&mut self, font: Font) {
+        self.font = font;
+        self.load_font_in_memory();
+    }
+
+    /// Set super font.
+    ///
+    /// Replace the font used by `LDX F, Vx` and load it in memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `font` - Font.
+    ///
+    pub fn set_super_font(&mut self, font: Font) {
+        self.super_font = font;
+        self.load_font_in_memory();
     }
 
     /// Load savestate.
@@ -135,31 +252,71 @@ impl CPU {
 
     /// Load cartridge data.
     ///
+    /// Data is written at the memory initial pointer (entry point).
+    ///
     /// # Arguments
     ///
     /// * `cartridge` - Cartridge.
     ///
-    pub fn load_cartridge_data(&mut self, cartridge: &Cartridge) {
-        self.peripherals.memory.reset_pointer();
-        self.peripherals
-            .memory
-            .write_data_at_pointer(cartridge.get_data());
+    /// # Returns
+    ///
+    /// * Result, failing if data does not fit between the entry point and
+    ///   memory end.
+    ///
+    pub fn load_cartridge_data(&mut self, cartridge: &Cartridge) -> Result<(), Chip8Error> {
+        let memory = &mut self.peripherals.memory;
+        let size = cartridge.get_data().len();
+        let max = memory
+            .get_size()
+            .saturating_sub(memory.get_initial_pointer() as usize);
+        if size > max {
+            return Err(Chip8Error::CartridgeTooLarge(size, max));
+        }
+
+        memory.reset_pointer();
+        memory.write_data_at_pointer(cartridge.get_data())
     }
 
     /// Decrement timers.
+    ///
+    /// Beep while the sound timer is active, and stop when it reaches zero.
     pub fn decrement_timers(&mut self) {
+        let sound_active = self.sound_timer.get_value() > 0;
+
         self.delay_timer.decrement();
         self.sound_timer.decrement();
 
-        if self.sound_timer.get_value() > 0 {
-            if let Some(audio) = self.drivers.audio.as_deref_mut() {
-                self.peripherals.sound.play_beep(audio);
+        if let Some(audio) = self.drivers.audio.as_deref_mut() {
+            if sound_active {
+                self.peripherals.sound.on_beep_start(audio);
             }
+
+            if sound_active && self.sound_timer.get_value() == 0 {
+                self.peripherals.sound.on_beep_stop(audio);
+            }
+        }
+    }
+
+    /// Stop sound, e.g. when emulation is paused.
+    ///
+    /// Sound resumes on next timer decrement if the sound timer is still set.
+    pub fn stop_sound(&mut self) {
+        if let Some(audio) = self.drivers.audio.as_deref_mut() {
+            self.peripherals.sound.stop(audio);
         }
     }
 
     /// Reset CPU.
+    ///
+    /// Only clear runtime state: configuration (quirks, compatibility
+    /// mode, SCHIP mode, speed, entry point) is kept.
+    ///
     pub fn reset(&mut self) {
+        // Silence a sustained beep.
+        if let Some(audio) = self.drivers.audio.as_deref_mut() {
+            self.peripherals.sound.on_beep_stop(audio);
+        }
+
         // Reset peripherals.
         self.peripherals.reset();
 
@@ -168,6 +325,88 @@ impl CPU {
         self.stack.reset();
         self.delay_timer.reset(0);
         self.sound_timer.reset(0);
+        self.instruction_count = 0;
+        self.exit_reason = None;
+        self.memory_trap = None;
+        self.input_snapshot = None;
+    }
+
+    /// Check a memory access against the memory trap.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - Access start address.
+    /// * `len` - Access length, in bytes.
+    ///
+    /// # Returns
+    ///
+    /// * `true` if the access is trapped.
+    /// * `false` if not.
+    ///
+    fn check_memory_trap(&mut self, start: C8Addr, len: usize) -> bool {
+        let start = start as usize;
+        let in_bounds = start >= INITIAL_MEMORY_POINTER as usize
+            && start + len <= self.peripherals.memory.get_size();
+        if !self.trap_memory || in_bounds {
+            return false;
+        }
+
+        let trap = MemoryTrap {
+            pc: self.peripherals.memory.get_pointer(),
+            start,
+            len,
+        };
+        warn!(
+            target: "chip8::cpu",
+            message = "Memory access trapped.",
+            pc = trap.pc,
+            start = trap.start,
+            len = trap.len
+        );
+
+        self.memory_trap = Some(trap);
+        self.exit_reason = Some(ExitReason::MemoryTrap);
+        true
+    }
+
+    /// Latch input state.
+    ///
+    /// With the `latched_input` quirk, SKP/SKNP read this snapshot until
+    /// the next latch.
+    pub fn latch_input(&mut self) {
+        self.input_snapshot = Some(self.peripherals.input.snapshot());
+    }
+
+    fn read_key(&mut self, key: C8Byte) -> C8Byte {
+        let value = self.peripherals.input.poll(key);
+
+        match self.input_snapshot {
+            Some(snapshot) if self.quirks.latched_input => snapshot[key as usize],
+            _ => value,
+        }
+    }
+
+    /// Check if an opcode is unknown in the current compatibility mode.
+    ///
+    /// XO-CHIP only instructions are unknown in other modes.
+    ///
+    /// # Arguments
+    ///
+    /// * `opcode` - Opcode.
+    ///
+    /// # Returns
+    ///
+    /// * `true` if unknown.
+    /// * `false` if not.
+    ///
+    pub fn is_unknown_opcode(&self, opcode: &OpCode) -> bool {
+        match opcode {
+            OpCode::DATA(_) => true,
+            OpCode::LongI(_) | OpCode::StoreRange(..) | OpCode::LoadRange(..) => {
+                self.compat_mode != CompatMode::XOChip
+            }
+            _ => false,
+        }
     }
 
     /// Execute instruction.
@@ -182,8 +421,29 @@ impl CPU {
     /// * `false` if not.
     ///
     pub fn execute_instruction(&mut self, opcode: &OpCode) -> bool {
+        self.execute(opcode).is_some()
+    }
+
+    /// Execute instruction, reporting why the CPU should exit.
+    ///
+    /// # Arguments
+    ///
+    /// * `opcode` - Instruction to execute.
+    ///
+    /// # Returns
+    ///
+    /// * Exit reason, if CPU should exit.
+    ///
+    pub fn execute(&mut self, opcode: &OpCode) -> Option<ExitReason> {
         let mut advance_pointer = true;
 
+        trace!(
+            target: "chip8::cpu",
+            message = "Executing instruction.",
+            pointer = self.peripherals.memory.get_pointer(),
+            opcode = ?opcode
+        );
+
         match *opcode {
             OpCode::SYS(_addr) => {
                 // Do nothing.
@@ -195,7 +455,8 @@ impl CPU {
             OpCode::RET => {
                 // Get last stored address.
                 if self.stack.empty() {
-                    return true;
+                    self.exit_reason = Some(ExitReason::StackUnderflow);
+                    return self.exit_reason;
                 }
 
                 let addr = self.stack.pop();
@@ -208,7 +469,12 @@ impl CPU {
             }
             OpCode::CALL(addr) => {
                 // Store current address and set pointer.
-                self.stack.push(self.peripherals.memory.get_pointer());
+                if let Err(e) = self.stack.try_push(self.peripherals.memory.get_pointer()) {
+                    warn!(target: "chip8::cpu", message = "Stack overflow.", error = %e);
+                    self.exit_reason = Some(ExitReason::StackOverflow);
+                    return self.exit_reason;
+                }
+
                 self.peripherals.memory.set_pointer(addr);
                 advance_pointer = false;
             }
@@ -356,16 +622,21 @@ impl CPU {
                 self.registers.set_i_register(addr);
             }
             OpCode::JP0(addr) => {
-                // Set pointer to address + V0.
-                let v0 = self.registers.get_register(0);
+                // Set pointer to address + V0 (or xnn + Vx with the SUPER-CHIP quirk).
+                let reg = if self.quirks.jump_vx {
+                    decode_fields(addr).x
+                } else {
+                    0
+                };
+                let value = self.registers.get_register(reg);
                 self.peripherals
                     .memory
-                    .set_pointer(addr + (C8Addr::from(v0)));
+                    .set_pointer(addr + (C8Addr::from(value)));
                 advance_pointer = false;
             }
             OpCode::RND(reg, byte) => {
                 // Set random value AND byte in register.
-                let rand_value = gen_range(0, C8Byte::MAX) & byte;
+                let rand_value = self.rand.next_byte() & byte;
                 self.registers.set_register(reg, rand_value);
             }
             OpCode::DRW(reg1, reg2, byte) => {
@@ -378,13 +649,14 @@ impl CPU {
                     .memory
                     .read_data_at_offset(ri, C8Addr::from(byte));
 
-                let collision = self.peripherals.screen.draw_sprite(r1, r2, sprite_data);
-                self.registers.set_carry_register(collision as C8Byte);
+                let collisions = self.peripherals.screen.draw_sprite(r1, r2, &sprite_data);
+                self.registers
+                    .set_carry_register((collisions > 0) as C8Byte);
             }
             OpCode::SKP(reg) => {
                 // Skip next instruction if key is pressed.
                 let r = self.registers.get_register(reg);
-                let is = self.peripherals.input.get(r);
+                let is = self.read_key(r);
 
                 if is == 1 {
                     self.peripherals.memory.advance_pointer();
@@ -393,7 +665,7 @@ impl CPU {
             OpCode::SKNP(reg) => {
                 // Skip next instruction if key is not pressed.
                 let r = self.registers.get_register(reg);
-                let is = self.peripherals.input.get(r);
+                let is = self.read_key(r);
 
                 if is == 0 {
                     self.peripherals.memory.advance_pointer();
@@ -420,16 +692,24 @@ impl CPU {
                 self.sound_timer.reset(r);
             }
             OpCode::ADDI(reg) => {
-                // Add register value to I.
+                // Add register value to I, wrapping around memory size.
                 let i = self.registers.get_i_register();
                 let r = self.registers.get_register(reg);
 
-                self.registers.set_i_register(i + C8Addr::from(r));
+                let sum = usize::from(i) + usize::from(r);
+                self.registers
+                    .set_i_register(self.peripherals.memory.wrap_address(sum));
+
+                if self.quirks.addi_overflow {
+                    // Amiga interpreter: flag I going past the 12-bit address space.
+                    let overflow = u32::from(i) + u32::from(r) > 0x0FFF;
+                    self.registers.set_carry_register(overflow as C8Byte);
+                }
             }
             OpCode::LDSprite(reg) => {
                 // Set I = location of sprite for reg.
-                let r = C8Addr::from(self.registers.get_register(reg));
-                let sprite_addr = FONT_DATA_ADDR + (FONT_CHAR_HEIGHT as C8Addr * r);
+                let r = self.registers.get_register(reg);
+                let sprite_addr = self.font.get_char_address(FONT_DATA_ADDR, r);
 
                 self.registers.set_i_register(sprite_addr);
             }
@@ -442,30 +722,38 @@ impl CPU {
                 let n2 = (r % 100) / 10;
                 let n1 = r % 10;
 
-                self.peripherals
-                    .memory
-                    .write_data_at_offset(i, &[n3, n2, n1]);
+                for (offset, digit) in [n3, n2, n1].iter().enumerate() {
+                    self.peripherals
+                        .memory
+                        .write_byte_at_offset(i.wrapping_add(offset as C8Addr), *digit);
+                }
             }
             OpCode::LDS(reg) => {
                 // Store registers V0 through reg in memory starting at I.
                 let ri = self.registers.get_i_register();
+                if self.check_memory_trap(ri, reg as usize + 1) {
+                    return self.exit_reason;
+                }
 
                 for ridx in 0..=reg {
                     let r = self.registers.get_register(ridx);
                     self.peripherals
                         .memory
-                        .write_byte_at_offset(ri + C8Addr::from(ridx), r);
+                        .write_byte_at_offset(ri.wrapping_add(C8Addr::from(ridx)), r);
                 }
             }
             OpCode::LDR(reg) => {
                 // Read registers V0 through reg from memory starting at I.
                 let ri = self.registers.get_i_register();
+                if self.check_memory_trap(ri, reg as usize + 1) {
+                    return self.exit_reason;
+                }
 
                 for ridx in 0..=reg {
                     let byte = self
                         .peripherals
                         .memory
-                        .read_byte_at_offset(ri + C8Addr::from(ridx));
+                        .read_byte_at_offset(ri.wrapping_add(C8Addr::from(ridx)));
                     self.registers.set_register(ridx, byte);
                 }
             }
@@ -476,6 +764,11 @@ impl CPU {
                 self.peripherals.screen.data.scroll.lines = lines;
                 self.peripherals.screen.data.scroll.direction = ScreenScrollDirection::Down;
             }
+            OpCode::SCRU(lines) => {
+                self.peripherals.screen.data.scroll.scrolling = true;
+                self.peripherals.screen.data.scroll.lines = lines;
+                self.peripherals.screen.data.scroll.direction = ScreenScrollDirection::Up;
+            }
             OpCode::SCRR => {
                 self.peripherals.screen.data.scroll.scrolling = true;
                 self.peripherals.screen.data.scroll.direction = ScreenScrollDirection::Right;
@@ -485,19 +778,31 @@ impl CPU {
                 self.peripherals.screen.data.scroll.direction = ScreenScrollDirection::Left;
             }
             OpCode::EXIT => {
-                return true;
+                self.exit_reason = Some(ExitReason::Exit);
+                return self.exit_reason;
             }
             OpCode::LOW => {
                 self.peripherals
                     .screen
                     .reload_screen_for_mode(ScreenMode::Standard);
                 self.speed_multiplicator = 8;
+                self.schip_mode = true;
             }
             OpCode::HIGH => {
                 self.peripherals
                     .screen
                     .reload_screen_for_mode(ScreenMode::Extended);
                 self.speed_multiplicator = 16;
+                self.schip_mode = true;
+            }
+            OpCode::DRWX(reg1, reg2) if !self.schip_mode => {
+                // Standard `Dxy0`: 0-byte sprite.
+                let r1 = self.registers.get_register(reg1);
+                let r2 = self.registers.get_register(reg2);
+
+                let collisions = self.peripherals.screen.draw_sprite(r1, r2, &[]);
+                self.registers
+                    .set_carry_register((collisions > 0) as C8Byte);
             }
             OpCode::DRWX(reg1, reg2) => {
                 // Draw sprite.
@@ -507,25 +812,85 @@ impl CPU {
                 let sprite_data = self
                     .peripherals
                     .memory
-                    .read_data_at_offset(ri, C8Addr::from(16u8));
+                    .read_data_at_offset(ri, C8Addr::from(32u8));
 
-                let collision = self
+                let collided_rows = self
                     .peripherals
                     .screen
-                    .draw_super_sprite(r1, r2, sprite_data);
-                self.registers.set_carry_register(collision as C8Byte);
+                    .draw_super_sprite(r1, r2, &sprite_data);
+
+                if self.quirks.schip_row_collisions
+                    && self.peripherals.screen.get_mode() == ScreenMode::Extended
+                {
+                    self.registers.set_carry_register(collided_rows);
+                } else {
+                    self.registers
+                        .set_carry_register((collided_rows > 0) as C8Byte);
+                }
             }
             OpCode::LDXSprite(reg) => {
-                let r = C8Addr::from(self.registers.get_register(reg));
-                let sprite_addr = SUPER_FONT_DATA_ADDR + (SUPER_FONT_CHAR_HEIGHT as C8Addr * r);
+                let r = self.registers.get_register(reg);
+                let sprite_addr = self.super_font.get_char_address(SUPER_FONT_DATA_ADDR, r);
 
                 self.registers.set_i_register(sprite_addr);
             }
             OpCode::LDXS(reg) => {
-                println!("Executing LDXS on register V{:X} (does nothing)", reg);
+                // Store V0 through reg in RPL user flags.
+                let count = (reg as usize + 1).min(RPL_FLAG_COUNT);
+                let values = &self.registers.get_registers()[..count];
+
+                self.rpl_flags.store(values);
             }
             OpCode::LDXR(reg) => {
-                println!("Executing LDXR on register V{:X} (does nothing)", reg);
+                // Read V0 through reg from RPL user flags.
+                let count = (reg as usize + 1).min(RPL_FLAG_COUNT);
+
+                for ridx in 0..count as C8RegIdx {
+                    let value = self.rpl_flags.get_flag(ridx);
+                    self.registers.set_register(ridx, value);
+                }
+            }
+
+            OpCode::LongI(_) if self.is_unknown_opcode(opcode) => {
+                // Unknown code, still skipping the immediate word (see
+                // `get_opcode_size`).
+                self.peripherals.memory.advance_pointer();
+            }
+            OpCode::StoreRange(..) | OpCode::LoadRange(..) if self.is_unknown_opcode(opcode) => {
+                // Unknown code.
+            }
+            OpCode::LongI(addr) => {
+                // Set I to a 16-bit address, skipping the immediate word.
+                self.registers.set_i_register(addr);
+                self.peripherals.memory.advance_pointer();
+            }
+
+            OpCode::StoreRange(reg1, reg2) => {
+                // Store registers reg1 through reg2 in memory starting at I.
+                let ri = self.registers.get_i_register();
+
+                let (lo, hi, reversed) = register_range(reg1, reg2);
+                for ridx in lo..=hi {
+                    let offset = if reversed { hi - ridx } else { ridx - lo };
+                    let r = self.registers.get_register(ridx);
+                    self.peripherals
+                        .memory
+                        .write_byte_at_offset(ri.wrapping_add(C8Addr::from(offset)), r);
+                }
+            }
+            OpCode::LoadRange(reg1, reg2) => {
+                // Read registers reg1 through reg2 from memory starting at I.
+                let ri = self.registers.get_i_register();
+
+                let (lo, hi, reversed) = register_range(reg1, reg2);
+                for ridx in lo..=hi {
+                    let offset = if reversed { hi - ridx } else { ridx - lo };
+                    let byte = self
+                        .peripherals
+                        .memory
+                        .read_byte_at_offset(ri.wrapping_add(C8Addr::from(offset)));
+                    self.registers.set_register(ridx, byte);
+                }
             }
 
             OpCode::EMPTY => {
@@ -540,7 +905,16 @@ impl CPU {
             self.peripherals.memory.advance_pointer();
         }
 
-        false
+        None
+    }
+}
+
+/// Get register range bounds, and whether registers are in descending order.
+fn register_range(from: C8RegIdx, to: C8RegIdx) -> (C8RegIdx, C8RegIdx, bool) {
+    if from <= to {
+        (from, to, false)
+    } else {
+        (to, from, true)
     }
 }
 
@@ -580,3 +954,582 @@ impl fmt::Debug for CPU {
         writeln!(f, "}}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::{
+        span::{Attributes, Id, Record},
+        Event, Metadata, Subscriber,
+    };
+
+    use super::*;
+    use crate::{core::opcodes::get_opcode_enum, drivers::AudioInterface};
+
+    /// Subscriber capturing event targets.
+    struct CapturingSubscriber(Arc<Mutex<Vec<String>>>);
+
+    impl Subscriber for CapturingSubscriber {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _span: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(event.metadata().target().to_string());
+        }
+
+        fn enter(&self, _span: &Id) {}
+
+        fn exit(&self, _span: &Id) {}
+    }
+
+    #[test]
+    fn test_set_compat_mode() {
+        let mut cpu = CPU::new();
+        assert_eq!(cpu.peripherals.memory.get_size(), 4096);
+
+        cpu.set_compat_mode(CompatMode::XOChip);
+        assert_eq!(cpu.peripherals.memory.get_size(), 65536);
+
+        cpu.reset();
+        assert_eq!(cpu.peripherals.memory.get_size(), 65536);
+    }
+
+    #[test]
+    fn test_long_i() {
+        let mut cpu = CPU::new();
+        cpu.set_compat_mode(CompatMode::XOChip);
+
+        cpu.execute_instruction(&OpCode::LongI(0xABCD));
+        assert_eq!(cpu.registers.get_i_register(), 0xABCD);
+        assert_eq!(cpu.peripherals.memory.get_pointer(), 0x204);
+
+        // Unknown code outside XO-CHIP.
+        for &mode in &[CompatMode::Chip8, CompatMode::SChip] {
+            let mut cpu = CPU::new();
+            cpu.set_compat_mode(mode);
+            cpu.registers.set_register(0x1, 0xAA);
+
+            cpu.execute_instruction(&OpCode::LongI(0xABCD));
+            assert_eq!(cpu.registers.get_i_register(), 0x0000);
+            assert_eq!(cpu.peripherals.memory.get_pointer(), 0x204);
+            cpu.execute_instruction(&OpCode::DRW(0x0, 0x0, 0xF));
+
+            cpu.registers.set_i_register(0x0300);
+            cpu.execute_instruction(&OpCode::StoreRange(0x1, 0x1));
+            assert_eq!(cpu.peripherals.memory.read_byte_at_offset(0x0300), 0x00);
+            cpu.execute_instruction(&OpCode::LoadRange(0x0, 0x1));
+            assert_eq!(cpu.registers.get_register(0x1), 0xAA);
+        }
+    }
+
+    #[test]
+    fn test_addi_wrapping() {
+        let mut cpu = CPU::new();
+        cpu.registers.set_register(0x1, 0xFF);
+
+        // Wraps around 4 KB memory.
+        cpu.registers.set_i_register(0x0FFF);
+        cpu.execute_instruction(&OpCode::ADDI(0x1));
+        assert_eq!(cpu.registers.get_i_register(), 0x00FE);
+
+        cpu.registers.set_i_register(0xFFF0);
+        cpu.execute_instruction(&OpCode::ADDI(0x1));
+        assert_eq!(cpu.registers.get_i_register(), 0x00EF);
+
+        // Wraps around 64 KB memory.
+        let mut cpu = CPU::new();
+        cpu.set_compat_mode(CompatMode::XOChip);
+        cpu.registers.set_register(0x1, 0xFF);
+        cpu.registers.set_i_register(0xFFF0);
+        cpu.execute_instruction(&OpCode::ADDI(0x1));
+        assert_eq!(cpu.registers.get_i_register(), 0x00EF);
+    }
+
+    #[test]
+    fn test_addi_top_of_memory() {
+        let mut cpu = CPU::new();
+        cpu.peripherals.memory.write_byte_at_offset(0x0FFF, 0xAA);
+        cpu.peripherals.memory.write_byte_at_offset(0x0000, 0xBB);
+        cpu.registers.set_register(0x1, 0x0E);
+        cpu.registers.set_i_register(0x0FF1);
+
+        // I = 0x0FFF, sprites and reads go past memory end.
+        cpu.execute_instruction(&OpCode::ADDI(0x1));
+        assert_eq!(cpu.registers.get_i_register(), 0x0FFF);
+        cpu.execute_instruction(&OpCode::DRW(0x0, 0x0, 0xF));
+        cpu.execute_instruction(&OpCode::LDR(0x1));
+        assert_eq!(cpu.registers.get_register(0x0), 0xAA);
+        assert_eq!(cpu.registers.get_register(0x1), 0xBB);
+
+        cpu.registers.set_register(0x2, 123);
+        cpu.execute_instruction(&OpCode::LDBCD(0x2));
+        assert_eq!(cpu.peripherals.memory.read_byte_at_offset(0x0FFF), 1);
+        assert_eq!(
+            *cpu.peripherals.memory.read_data_at_offset(0x0000, 2),
+            [2, 3]
+        );
+    }
+
+    #[test]
+    fn test_drw_top_of_memory() {
+        let mut cpu = CPU::new();
+        cpu.peripherals.memory.write_byte_at_offset(0x0FFF, 0xFF);
+        cpu.peripherals.memory.write_byte_at_offset(0x0000, 0x81);
+        cpu.registers.set_i_register(0x0FFF);
+
+        // Second sprite row wraps to address 0.
+        cpu.execute_instruction(&OpCode::DRW(0x0, 0x0, 0x2));
+        let (width, _) = cpu.peripherals.screen.data.get_dimensions();
+        let pixels = cpu.peripherals.screen.data.get_pixels();
+        let lit = |row: usize| -> Vec<bool> {
+            pixels[row * width..row * width + 8]
+                .iter()
+                .map(|p| *p != 0)
+                .collect()
+        };
+
+        assert_eq!(lit(0), [true; 8]);
+        assert_eq!(
+            lit(1),
+            [true, false, false, false, false, false, false, true]
+        );
+        assert_eq!(cpu.registers.get_register(0xF), 0);
+    }
+
+    #[test]
+    fn test_addi_overflow_quirk() {
+        for &quirk in &[false, true] {
+            let mut cpu = CPU::new();
+            cpu.quirks.addi_overflow = quirk;
+            cpu.registers.set_register(0x1, 0x10);
+
+            // Overflowing.
+            cpu.registers.set_carry_register(0);
+            cpu.registers.set_i_register(0x0FF8);
+            cpu.execute_instruction(&OpCode::ADDI(0x1));
+            assert_eq!(cpu.registers.get_i_register(), 0x0008);
+            assert_eq!(cpu.registers.get_register(0xF), quirk as C8Byte);
+
+            // Not overflowing.
+            cpu.registers.set_carry_register(1);
+            cpu.registers.set_i_register(0x0200);
+            cpu.execute_instruction(&OpCode::ADDI(0x1));
+            assert_eq!(cpu.registers.get_i_register(), 0x0210);
+            assert_eq!(cpu.registers.get_register(0xF), !quirk as C8Byte);
+        }
+    }
+
+    #[test]
+    fn test_jp0_quirk() {
+        let mut cpu = CPU::new();
+        cpu.registers.set_register(0x0, 0x02);
+        cpu.registers.set_register(0x3, 0x10);
+
+        cpu.execute_instruction(&OpCode::JP0(0x0340));
+        assert_eq!(cpu.peripherals.memory.get_pointer(), 0x0342);
+
+        cpu.quirks.jump_vx = true;
+        cpu.execute_instruction(&OpCode::JP0(0x0340));
+        assert_eq!(cpu.peripherals.memory.get_pointer(), 0x0350);
+    }
+
+    #[test]
+    fn test_stack_depth_from_compat_mode() {
+        let mut cpu = CPU::new();
+        assert_eq!(cpu.stack.get_capacity(), 12);
+
+        // COSMAC VIP depth: the 13th nested CALL stops the CPU.
+        for _ in 0..12 {
+            assert_eq!(cpu.execute(&OpCode::CALL(0x0300)), None);
+        }
+        assert_eq!(
+            cpu.execute(&OpCode::CALL(0x0300)),
+            Some(ExitReason::StackOverflow)
+        );
+        assert_eq!(cpu.stack.get_pointer(), 12);
+        assert_eq!(cpu.peripherals.memory.get_pointer(), 0x0300);
+
+        cpu.reset();
+
+        cpu.set_compat_mode(CompatMode::SChip);
+        assert_eq!(cpu.stack.get_capacity(), 16);
+
+        cpu.reset();
+        assert_eq!(cpu.stack.get_capacity(), 16);
+    }
+
+    #[test]
+    fn test_dxy0_dispatch() {
+        assert_eq!(get_opcode_enum(0xD120), OpCode::DRWX(0x1, 0x2));
+        assert_eq!(get_opcode_enum(0xD125), OpCode::DRW(0x1, 0x2, 0x5));
+
+        let lit = |cpu: &CPU| {
+            cpu.peripherals
+                .screen
+                .data
+                .get_pixels()
+                .iter()
+                .filter(|p| **p != 0)
+                .count()
+        };
+
+        for &(schip, expected) in &[(false, 0), (true, 16 * 16)] {
+            let mut cpu = CPU::new();
+            if schip {
+                cpu.set_compat_mode(CompatMode::SChip);
+            }
+            cpu.peripherals
+                .memory
+                .write_data_at_offset(0x0300, &[0xFF; 32])
+                .unwrap();
+            cpu.registers.set_i_register(0x0300);
+            cpu.registers.set_register(0xF, 0x1);
+
+            cpu.execute_instruction(&get_opcode_enum(0xD120));
+            assert_eq!(lit(&cpu), expected);
+            assert_eq!(cpu.registers.get_register(0xF), 0);
+        }
+
+        // Switching resolution enables SCHIP mode.
+        let mut cpu = CPU::new();
+        cpu.execute_instruction(&OpCode::HIGH);
+        assert!(cpu.schip_mode);
+    }
+
+    #[test]
+    fn test_reset_keeps_configuration() {
+        let mut cpu = CPU::new();
+        cpu.quirks.jump_vx = true;
+        cpu.schip_mode = true;
+        cpu.speed_multiplicator = 16;
+        cpu.peripherals.memory.set_initial_pointer(0x600);
+
+        cpu.registers.set_register(0x3, 0x42);
+        cpu.registers.set_i_register(0x0300);
+        cpu.stack.try_push(0x0204).unwrap();
+        cpu.instruction_count = 10;
+        cpu.peripherals.memory.set_pointer(0x0640);
+
+        cpu.reset();
+
+        assert!(cpu.quirks.jump_vx);
+        assert!(cpu.schip_mode);
+        assert_eq!(cpu.speed_multiplicator, 16);
+        assert_eq!(cpu.peripherals.memory.get_initial_pointer(), 0x600);
+        assert_eq!(cpu.peripherals.memory.get_pointer(), 0x600);
+
+        assert_eq!(cpu.registers.get_register(0x3), 0);
+        assert_eq!(cpu.registers.get_i_register(), 0);
+        assert!(cpu.stack.empty());
+        assert_eq!(cpu.instruction_count, 0);
+    }
+
+    #[test]
+    fn test_register_ranges() {
+        let mut cpu = CPU::new();
+        cpu.set_compat_mode(CompatMode::XOChip);
+        cpu.registers.set_i_register(0x0300);
+        for reg in 0x2..=0x5 {
+            cpu.registers.set_register(reg, 0x10 + reg);
+        }
+
+        cpu.execute_instruction(&OpCode::StoreRange(0x2, 0x5));
+        assert_eq!(
+            *cpu.peripherals.memory.read_data_at_offset(0x0300, 4),
+            [0x12, 0x13, 0x14, 0x15]
+        );
+        assert_eq!(cpu.registers.get_i_register(), 0x0300);
+
+        cpu.registers.reset();
+        cpu.registers.set_i_register(0x0300);
+        cpu.execute_instruction(&OpCode::LoadRange(0x2, 0x5));
+        for reg in 0x2..=0x5 {
+            assert_eq!(cpu.registers.get_register(reg), 0x10 + reg);
+        }
+
+        // Descending.
+        cpu.execute_instruction(&OpCode::StoreRange(0x5, 0x2));
+        assert_eq!(
+            *cpu.peripherals.memory.read_data_at_offset(0x0300, 4),
+            [0x15, 0x14, 0x13, 0x12]
+        );
+        cpu.execute_instruction(&OpCode::LoadRange(0x3, 0x0));
+        assert_eq!(cpu.registers.get_register(0x3), 0x15);
+        assert_eq!(cpu.registers.get_register(0x0), 0x12);
+    }
+
+    #[test]
+    fn test_last_collision() {
+        let mut cpu = CPU::new();
+        cpu.registers.set_i_register(0x0300);
+        cpu.peripherals
+            .memory
+            .write_data_at_offset(0x0300, &[0b1111_0000])
+            .unwrap();
+
+        cpu.execute_instruction(&OpCode::DRW(0x0, 0x0, 1));
+        assert_eq!(cpu.peripherals.screen.last_collision(), 0);
+        cpu.execute_instruction(&OpCode::DRW(0x0, 0x0, 1));
+        assert_eq!(cpu.peripherals.screen.last_collision(), 4);
+
+        // VF gets overwritten, the draw result is kept.
+        cpu.execute_instruction(&OpCode::LDByte(0xF, 0x00));
+        assert_eq!(cpu.registers.get_register(0xF), 0);
+        assert_eq!(cpu.peripherals.screen.last_collision(), 4);
+    }
+
+    #[test]
+    fn test_custom_font() {
+        // 16 glyphs of 4 bytes, each glyph filled with its index.
+        let data: Vec<C8Byte> = (0..16).flat_map(|c| vec![c; 4]).collect();
+
+        let mut cpu = CPU::new();
+        cpu.set_font(Font::from_bytes(&data, 4));
+        cpu.registers.set_register(0x1, 0x3);
+
+        cpu.execute_instruction(&OpCode::LDSprite(0x1));
+        assert_eq!(cpu.registers.get_i_register(), FONT_DATA_ADDR + 12);
+        assert_eq!(
+            *cpu.peripherals
+                .memory
+                .read_data_at_offset(FONT_DATA_ADDR + 12, 4),
+            [0x3; 4]
+        );
+    }
+
+    #[test]
+    fn test_sticky_keys_skp() {
+        for &sticky in &[false, true] {
+            let mut cpu = CPU::new();
+            cpu.peripherals.input.set_sticky_keys(sticky);
+            cpu.registers.set_register(0x0, 0x5);
+
+            // Tap within a single window frame.
+            cpu.peripherals.input.press(0x5);
+            cpu.peripherals.input.release(0x5);
+
+            cpu.peripherals.memory.set_pointer(0x0200);
+            cpu.execute_instruction(&OpCode::SKP(0x0));
+            cpu.execute_instruction(&OpCode::SKP(0x0));
+
+            // Each SKP advances by 2, plus 2 for each skip.
+            let expected = if sticky { 0x0206 } else { 0x0204 };
+            assert_eq!(cpu.peripherals.memory.get_pointer(), expected);
+        }
+    }
+
+    #[test]
+    fn test_latched_input_skp() {
+        let mut cpu = CPU::new();
+        cpu.quirks.latched_input = true;
+        cpu.registers.set_register(0x0, 0x5);
+        cpu.peripherals.memory.set_pointer(0x0200);
+
+        // Pressed after the latch: not seen until the next one.
+        cpu.latch_input();
+        cpu.peripherals.input.press(0x5);
+        cpu.execute_instruction(&OpCode::SKP(0x0));
+        assert_eq!(cpu.peripherals.memory.get_pointer(), 0x0202);
+
+        cpu.latch_input();
+        cpu.peripherals.input.release(0x5);
+        cpu.execute_instruction(&OpCode::SKP(0x0));
+        assert_eq!(cpu.peripherals.memory.get_pointer(), 0x0206);
+
+        cpu.quirks.latched_input = false;
+        cpu.execute_instruction(&OpCode::SKNP(0x0));
+        assert_eq!(cpu.peripherals.memory.get_pointer(), 0x020A);
+    }
+
+    #[test]
+    fn test_rpl_flags() {
+        let mut cpu = CPU::new();
+        for reg in 0..10 {
+            cpu.registers.set_register(reg, reg + 1);
+        }
+
+        cpu.execute_instruction(&OpCode::LDXS(0x9));
+        assert_eq!(cpu.rpl_flags.get_data(), &[1, 2, 3, 4, 5, 6, 7, 8]);
+
+        cpu.registers.reset();
+        cpu.execute_instruction(&OpCode::LDXR(0x2));
+        assert_eq!(&cpu.registers.get_registers()[..4], &[1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn test_drwx_row_collisions() {
+        let mut cpu = CPU::new();
+        cpu.quirks = QuirkSet::schip();
+        cpu.peripherals
+            .memory
+            .write_data_at_offset(0x300, &[0xFF; 32])
+            .unwrap();
+        cpu.registers.set_i_register(0x300);
+
+        cpu.execute_instruction(&OpCode::HIGH);
+        cpu.peripherals
+            .screen
+            .draw_sprite(4, 2, &[0x80, 0x00, 0x80]);
+        cpu.execute_instruction(&OpCode::DRWX(0, 0));
+        assert_eq!(cpu.registers.get_register(0xF), 2);
+
+        cpu.quirks = QuirkSet::chip8();
+        cpu.execute_instruction(&OpCode::DRWX(0, 0));
+        assert_eq!(cpu.registers.get_register(0xF), 1);
+    }
+
+    #[test]
+    fn test_instruction_trace_target() {
+        let targets = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = CapturingSubscriber(targets.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let mut cpu = CPU::new();
+            cpu.execute_instruction(&OpCode::LD(0, 0x05));
+        });
+
+        assert!(targets.lock().unwrap().iter().any(|t| t == "chip8::cpu"));
+    }
+
+    #[test]
+    fn test_timers_without_audio() {
+        struct PanickingAudioDriver;
+
+        impl AudioInterface for PanickingAudioDriver {
+            fn play_beep(&mut self) {
+                panic!("audio is disabled");
+            }
+
+            fn stop_beep(&mut self) {
+                panic!("audio is disabled");
+            }
+        }
+
+        // No audio driver set.
+        let mut cpu = CPU::new();
+        cpu.sound_timer.reset(3);
+        for _ in 0..4 {
+            cpu.decrement_timers();
+        }
+        assert_eq!(cpu.sound_timer.get_value(), 0);
+
+        // Drivers installed after disabling audio are ignored.
+        let mut cpu = CPU::new();
+        cpu.drivers.disable_audio();
+        cpu.drivers.set_audio_driver(Box::new(PanickingAudioDriver));
+        assert!(!cpu.drivers.is_audio_enabled());
+        assert!(cpu.drivers.audio.is_none());
+
+        cpu.sound_timer.reset(3);
+        for _ in 0..4 {
+            cpu.decrement_timers();
+        }
+        cpu.stop_sound();
+        assert_eq!(cpu.sound_timer.get_value(), 0);
+    }
+
+    #[test]
+    fn test_rand_source() {
+        struct RecordedRandSource(Vec<C8Byte>);
+
+        impl RandSource for RecordedRandSource {
+            fn next_byte(&mut self) -> C8Byte {
+                self.0.remove(0)
+            }
+        }
+
+        let mut cpu = CPU::new();
+        cpu.rand = Box::new(RecordedRandSource(vec![0xFF, 0xA5, 0x3C]));
+
+        cpu.execute_instruction(&OpCode::RND(0, 0xFF));
+        cpu.execute_instruction(&OpCode::RND(1, 0x0F));
+        cpu.execute_instruction(&OpCode::RND(2, 0xF0));
+        assert_eq!(cpu.registers.get_register(0), 0xFF);
+        assert_eq!(cpu.registers.get_register(1), 0x05);
+        assert_eq!(cpu.registers.get_register(2), 0x30);
+    }
+
+    #[test]
+    fn test_exit_reason() {
+        let mut cpu = CPU::new();
+        assert_eq!(cpu.execute(&OpCode::CLS), None);
+        assert_eq!(cpu.execute(&OpCode::EXIT), Some(ExitReason::Exit));
+        assert_eq!(cpu.exit_reason, Some(ExitReason::Exit));
+
+        let mut cpu = CPU::new();
+        assert_eq!(cpu.execute(&OpCode::RET), Some(ExitReason::StackUnderflow));
+        assert_eq!(cpu.exit_reason, Some(ExitReason::StackUnderflow));
+
+        let mut cpu = CPU::new();
+        for _ in 0..cpu.stack.get_capacity() {
+            assert_eq!(cpu.execute(&OpCode::CALL(0x0200)), None);
+        }
+        assert_eq!(
+            cpu.execute(&OpCode::CALL(0x0200)),
+            Some(ExitReason::StackOverflow)
+        );
+        assert_eq!(cpu.exit_reason, Some(ExitReason::StackOverflow));
+
+        // Bool wrapper.
+        let mut cpu = CPU::new();
+        assert!(!cpu.execute_instruction(&OpCode::CLS));
+        assert!(cpu.execute_instruction(&OpCode::RET));
+    }
+
+    #[test]
+    fn test_memory_trap() {
+        let mut cpu = CPU::new();
+        let font = cpu.peripherals.memory.read_data_at_offset(0, 6).to_vec();
+        cpu.registers.set_i_register(0x000);
+
+        // Disabled: writes over the font.
+        assert!(!cpu.execute_instruction(&OpCode::LDS(5)));
+        assert_eq!(cpu.memory_trap, None);
+
+        cpu.peripherals
+            .memory
+            .write_data_at_offset(0, &font)
+            .unwrap();
+        cpu.trap_memory = true;
+        for reg in 0..6 {
+            cpu.registers.set_register(reg, 0xAA);
+        }
+
+        assert!(cpu.execute_instruction(&OpCode::LDS(5)));
+        assert_eq!(cpu.exit_reason, Some(ExitReason::MemoryTrap));
+        assert_eq!(
+            cpu.memory_trap,
+            Some(MemoryTrap {
+                pc: INITIAL_MEMORY_POINTER + 2,
+                start: 0x000,
+                len: 6
+            })
+        );
+        assert_eq!(cpu.peripherals.memory.read_data_at_offset(0, 6), font);
+
+        // Past memory end.
+        cpu.reset();
+        cpu.registers.set_i_register(0x0FFE);
+        assert!(cpu.execute_instruction(&OpCode::LDR(2)));
+        assert_eq!(cpu.exit_reason, Some(ExitReason::MemoryTrap));
+
+        // In program memory.
+        cpu.reset();
+        cpu.registers.set_i_register(0x0300);
+        assert!(!cpu.execute_instruction(&OpCode::LDS(5)));
+        assert_eq!(cpu.memory_trap, None);
+    }
+}
@@ -3,7 +3,10 @@
 use super::types::{C8Addr, C8Byte};
 
 /// System font struct.
-pub struct Font(Vec<C8Byte>);
+pub struct Font {
+    data: Vec<C8Byte>,
+    char_height: u8,
+}
 
 /// Font data address in memory.
 pub const FONT_DATA_ADDR: C8Addr = 0;
@@ -56,16 +59,64 @@ impl Font {
     /// * Font instance.
     ///
     pub fn new_system_font() -> Self {
-        Font(SYSTEM_FONT_DATA.to_vec())
+        Self::from_bytes(SYSTEM_FONT_DATA, FONT_CHAR_HEIGHT as u8)
     }
 
     /// Create new super system font.
     pub fn new_super_system_font() -> Self {
-        Font(SUPER_SYSTEM_FONT_DATA.to_vec())
+        Self::from_bytes(SUPER_SYSTEM_FONT_DATA, SUPER_FONT_CHAR_HEIGHT as u8)
+    }
+
+    /// Create font from raw glyph data.
+    ///
+    /// Glyphs are stored one after the other, `char_height` bytes each.
+    /// A default font should fit before `SUPER_FONT_DATA_ADDR`.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Glyph data.
+    /// * `char_height` - Glyph height in bytes.
+    ///
+    /// # Returns
+    ///
+    /// * Font instance.
+    ///
+    pub fn from_bytes(data: &[C8Byte], char_height: u8) -> Self {
+        assert!(char_height > 0, "font char height should not be zero");
+
+        Font {
+            data: data.to_vec(),
+            char_height,
+        }
     }
 
     /// Get font data.
     pub fn get_data(&self) -> &[C8Byte] {
-        &self.0
+        &self.data
+    }
+
+    /// Get char height.
+    ///
+    /// # Returns
+    ///
+    /// * Glyph height in bytes.
+    ///
+    pub fn get_char_height(&self) -> u8 {
+        self.char_height
+    }
+
+    /// Get char address.
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - Font address in memory.
+    /// * `glyph` - Glyph index.
+    ///
+    /// # Returns
+    ///
+    /// * Glyph address.
+    ///
+    pub fn get_char_address(&self, base: C8Addr, glyph: C8Byte) -> C8Addr {
+        base + C8Addr::from(self.char_height) * C8Addr::from(glyph)
     }
 }
@@ -9,7 +9,10 @@ mod macros;
 
 pub mod math;
 pub mod opcodes;
+pub mod quirks;
+pub mod rand;
 pub mod registers;
+pub mod rpl;
 pub mod savestate;
 pub mod stack;
 pub mod timer;
@@ -5,6 +5,7 @@ use std::{collections::HashMap, error::Error, fmt};
 use once_cell::sync::Lazy;
 
 use super::types::{C8Addr, C8Byte, C8RegIdx};
+use crate::peripherals::memory::INITIAL_MEMORY_POINTER;
 
 /// Bad instruction.
 #[derive(Debug)]
@@ -350,6 +351,33 @@ pub enum OpCode {
     /// | HP48 implementation) (X < 8).
     LDXR(C8RegIdx),
 
+    // XO-CHIP.
+    /// F000 NNNN - LDI.L NNNN.
+    /// * Set I = NNNN.
+    ///
+    /// | Two-word instruction: the 16-bit address is read from the following word.
+    LongI(C8Addr),
+
+    /// 5xy2 - LD [I], Vx-Vy.
+    /// * Store Vx..Vy in memory starting at location I.
+    ///
+    /// | Registers are stored in the order given, so the range can be descending.
+    /// | I is not modified.
+    StoreRange(C8RegIdx, C8RegIdx),
+
+    /// 5xy3 - LD Vx-Vy, [I].
+    /// * Read Vx..Vy from memory starting at location I.
+    ///
+    /// | Registers are read in the order given, so the range can be descending.
+    /// | I is not modified.
+    LoadRange(C8RegIdx, C8RegIdx),
+
+    /// 00DN - SCRU N.
+    /// * Scroll display N lines up.
+    ///
+    /// | Counterpart of SCRD, applied the same way.
+    SCRU(C8Byte),
+
     /// 0000 - EMPTY.
     EMPTY,
 
@@ -412,11 +440,57 @@ static OPCODE_FLAG_MASKS: Lazy<HashMap<C8Addr, OpCodeFlagMask>> = Lazy::new(|| {
 
     m.insert(45, (0x0000, 0xFFFF)); // 0000
 
+    // XO-CHIP.
+    m.insert(46, (0xF000, 0xFFFF)); // F000
+    m.insert(47, (0x5002, 0xF00F)); // 5xy2
+    m.insert(48, (0x5003, 0xF00F)); // 5xy3
+    m.insert(49, (0x00D0, 0xFFF0)); // 00Dn
+
     m
 });
 
+/// Opcode fields.
+///
+/// Raw operands of an opcode, whatever its kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpcodeFields {
+    /// Second nibble (`x`).
+    pub x: C8RegIdx,
+    /// Third nibble (`y`).
+    pub y: C8RegIdx,
+    /// Lowest nibble (`n`).
+    pub n: C8Byte,
+    /// Lowest byte (`kk`).
+    pub kk: C8Byte,
+    /// Lowest 12 bits (`nnn`).
+    pub nnn: C8Addr,
+}
+
+/// Decode opcode fields.
+///
+/// # Arguments
+///
+/// * `opcode` - Opcode value.
+///
+/// # Returns
+///
+/// * Opcode fields.
+///
+pub fn decode_fields(opcode: C8Addr) -> OpcodeFields {
+    OpcodeFields {
+        x: ((opcode & 0x0F00) >> 8) as C8RegIdx,
+        y: ((opcode & 0x00F0) >> 4) as C8RegIdx,
+        n: (opcode & 0x000F) as C8Byte,
+        kk: (opcode & 0x00FF) as C8Byte,
+        nnn: opcode & 0x0FFF,
+    }
+}
+
 /// Extract opcode ID.
 ///
+/// When several entries match (e.g. `Dxy0` and `Dxyn`), the most specific
+/// mask wins.
+///
 /// # Arguments
 ///
 /// * `opcode` - Opcode value.
@@ -426,22 +500,11 @@ static OPCODE_FLAG_MASKS: Lazy<HashMap<C8Addr, OpCodeFlagMask>> = Lazy::new(|| {
 /// * Opcode address.
 ///
 fn extract_opcode_id(opcode: C8Addr) -> C8Addr {
-    let mut extracted_key = None;
-
-    for (key, flag_mask) in OPCODE_FLAG_MASKS.iter() {
-        let flag = flag_mask.0;
-        let mask = flag_mask.1;
-
-        if mask & opcode == flag {
-            extracted_key = Some(*key);
-        }
-    }
-
-    if extracted_key.is_none() {
-        extracted_key = Some(255)
-    }
-
-    extracted_key.unwrap()
+    OPCODE_FLAG_MASKS
+        .iter()
+        .filter(|(_, (flag, mask))| mask & opcode == *flag)
+        .max_by_key(|(key, (_, mask))| (mask.count_ones(), std::cmp::Reverse(**key)))
+        .map_or(255, |(key, _)| *key)
 }
 
 /// Get opcode enum.
@@ -456,69 +519,210 @@ fn extract_opcode_id(opcode: C8Addr) -> C8Addr {
 ///
 pub fn get_opcode_enum(opcode: C8Addr) -> OpCode {
     let action_id = extract_opcode_id(opcode);
-
-    let b3 = ((opcode & 0x0F00) >> 8) as C8Byte;
-    let b2 = ((opcode & 0x00F0) >> 4) as C8Byte;
-    let b1 = (opcode & 0x000F) as C8Byte;
-
-    let addr = (C8Addr::from(b3) << 8) + (C8Addr::from(b2) << 4) + C8Addr::from(b1);
-    let kk = (b2 << 4) + b1;
+    let f = decode_fields(opcode);
 
     match action_id {
-        0 => OpCode::SYS(addr),
+        0 => OpCode::SYS(f.nnn),
         1 => OpCode::CLS,
         2 => OpCode::RET,
-        3 => OpCode::JP(addr),
-        4 => OpCode::CALL(addr),
-        5 => OpCode::SEByte(b3, kk),
-        6 => OpCode::SNEByte(b3, kk),
-        7 => OpCode::SE(b3, b2),
-        8 => OpCode::LDByte(b3, kk),
-        9 => OpCode::ADDByte(b3, kk),
-        10 => OpCode::LD(b3, b2),
-        11 => OpCode::OR(b3, b2),
-        12 => OpCode::AND(b3, b2),
-        13 => OpCode::XOR(b3, b2),
-        14 => OpCode::ADD(b3, b2),
-        15 => OpCode::SUB(b3, b2),
-        16 => OpCode::SHR(b3, b2),
-        17 => OpCode::SUBN(b3, b2),
-        18 => OpCode::SHL(b3, b2),
-        19 => OpCode::SNE(b3, b2),
-        20 => OpCode::LDI(addr),
-        21 => OpCode::JP0(addr),
-        22 => OpCode::RND(b3, kk),
-        23 => OpCode::DRWX(b3, b2),
-        24 => OpCode::DRW(b3, b2, b1),
-        25 => OpCode::SKP(b3),
-        26 => OpCode::SKNP(b3),
-        27 => OpCode::LDGetDelayTimer(b3),
-        28 => OpCode::LDGetKey(b3),
-        29 => OpCode::LDSetDelayTimer(b3),
-        30 => OpCode::LDSetSoundTimer(b3),
-        31 => OpCode::ADDI(b3),
-        32 => OpCode::LDSprite(b3),
-        33 => OpCode::LDBCD(b3),
-        34 => OpCode::LDS(b3),
-        35 => OpCode::LDR(b3),
+        3 => OpCode::JP(f.nnn),
+        4 => OpCode::CALL(f.nnn),
+        5 => OpCode::SEByte(f.x, f.kk),
+        6 => OpCode::SNEByte(f.x, f.kk),
+        7 => OpCode::SE(f.x, f.y),
+        8 => OpCode::LDByte(f.x, f.kk),
+        9 => OpCode::ADDByte(f.x, f.kk),
+        10 => OpCode::LD(f.x, f.y),
+        11 => OpCode::OR(f.x, f.y),
+        12 => OpCode::AND(f.x, f.y),
+        13 => OpCode::XOR(f.x, f.y),
+        14 => OpCode::ADD(f.x, f.y),
+        15 => OpCode::SUB(f.x, f.y),
+        16 => OpCode::SHR(f.x, f.y),
+        17 => OpCode::SUBN(f.x, f.y),
+        18 => OpCode::SHL(f.x, f.y),
+        19 => OpCode::SNE(f.x, f.y),
+        20 => OpCode::LDI(f.nnn),
+        21 => OpCode::JP0(f.nnn),
+        22 => OpCode::RND(f.x, f.kk),
+        23 => OpCode::DRWX(f.x, f.y),
+        24 => OpCode::DRW(f.x, f.y, f.n),
+        25 => OpCode::SKP(f.x),
+        26 => OpCode::SKNP(f.x),
+        27 => OpCode::LDGetDelayTimer(f.x),
+        28 => OpCode::LDGetKey(f.x),
+        29 => OpCode::LDSetDelayTimer(f.x),
+        30 => OpCode::LDSetSoundTimer(f.x),
+        31 => OpCode::ADDI(f.x),
+        32 => OpCode::LDSprite(f.x),
+        33 => OpCode::LDBCD(f.x),
+        34 => OpCode::LDS(f.x),
+        35 => OpCode::LDR(f.x),
 
         // S-CHIP
-        36 => OpCode::SCRD(b1),
+        36 => OpCode::SCRD(f.n),
         37 => OpCode::SCRR,
         38 => OpCode::SCRL,
         39 => OpCode::EXIT,
         40 => OpCode::LOW,
         41 => OpCode::HIGH,
-        42 => OpCode::LDXSprite(b3),
-        43 => OpCode::LDXS(b3),
-        44 => OpCode::LDXR(b3),
+        42 => OpCode::LDXSprite(f.x),
+        43 => OpCode::LDXS(f.x),
+        44 => OpCode::LDXR(f.x),
 
         45 => OpCode::EMPTY,
 
+        // XO-CHIP
+        46 => OpCode::LongI(0),
+        47 => OpCode::StoreRange(f.x, f.y),
+        48 => OpCode::LoadRange(f.x, f.y),
+        49 => OpCode::SCRU(f.n),
+
         _ => OpCode::DATA(opcode),
     }
 }
 
+/// Get opcode enum from array.
+///
+/// Handle two-word instructions, reading the following word when needed.
+///
+/// # Arguments
+///
+/// * `array` - Array.
+/// * `ptr` - Pointer.
+///
+/// # Returns
+///
+/// * Opcode enum.
+///
+pub fn get_opcode_enum_from_array(array: &[u8], ptr: usize) -> OpCode {
+    match get_opcode_enum(extract_opcode_from_array(array, ptr)) {
+        OpCode::LongI(_) => OpCode::LongI(extract_opcode_from_array(array, ptr + 2)),
+        opcode => opcode,
+    }
+}
+
+/// Decode all opcodes from a byte slice, without loading it in memory.
+///
+/// Two-word instructions are decoded as one opcode. When the remaining
+/// bytes are too short for their instruction, they are yielded one by one
+/// as `DATA`.
+///
+/// # Arguments
+///
+/// * `bytes` - Bytes, e.g. cartridge data.
+///
+/// # Returns
+///
+/// * Iterator of (address, opcode), starting at `INITIAL_MEMORY_POINTER`.
+///
+pub fn decode_all(bytes: &[u8]) -> impl Iterator<Item = (C8Addr, OpCode)> + '_ {
+    let mut ptr = 0;
+    let mut truncated = false;
+
+    std::iter::from_fn(move || {
+        if ptr >= bytes.len() {
+            return None;
+        }
+
+        let addr = INITIAL_MEMORY_POINTER + ptr as C8Addr;
+        let opcode = get_opcode_enum_from_array(bytes, ptr);
+        let size = get_opcode_size(&opcode) as usize;
+
+        truncated = truncated || ptr + size > bytes.len();
+        if truncated {
+            ptr += 1;
+            return Some((addr, OpCode::DATA(C8Addr::from(bytes[ptr - 1]))));
+        }
+
+        ptr += size;
+        Some((addr, opcode))
+    })
+}
+
+/// Get opcode size.
+///
+/// # Arguments
+///
+/// * `opcode` - Opcode enum.
+///
+/// # Returns
+///
+/// * Size in bytes.
+///
+pub fn get_opcode_size(opcode: &OpCode) -> C8Addr {
+    match opcode {
+        OpCode::LongI(_) => 4,
+        _ => 2,
+    }
+}
+
+/// Get opcode variant name.
+///
+/// # Arguments
+///
+/// * `opcode_enum` - Opcode enum.
+///
+/// # Returns
+///
+/// * Variant name.
+///
+pub fn get_opcode_name(opcode_enum: &OpCode) -> &'static str {
+    match opcode_enum {
+        OpCode::SYS(..) => "SYS",
+        OpCode::CLS => "CLS",
+        OpCode::RET => "RET",
+        OpCode::JP(..) => "JP",
+        OpCode::CALL(..) => "CALL",
+        OpCode::SEByte(..) => "SEByte",
+        OpCode::SNEByte(..) => "SNEByte",
+        OpCode::SE(..) => "SE",
+        OpCode::LDByte(..) => "LDByte",
+        OpCode::ADDByte(..) => "ADDByte",
+        OpCode::LD(..) => "LD",
+        OpCode::OR(..) => "OR",
+        OpCode::AND(..) => "AND",
+        OpCode::XOR(..) => "XOR",
+        OpCode::ADD(..) => "ADD",
+        OpCode::SUB(..) => "SUB",
+        OpCode::SHR(..) => "SHR",
+        OpCode::SUBN(..) => "SUBN",
+        OpCode::SHL(..) => "SHL",
+        OpCode::SNE(..) => "SNE",
+        OpCode::LDI(..) => "LDI",
+        OpCode::JP0(..) => "JP0",
+        OpCode::RND(..) => "RND",
+        OpCode::DRW(..) => "DRW",
+        OpCode::SKP(..) => "SKP",
+        OpCode::SKNP(..) => "SKNP",
+        OpCode::LDGetDelayTimer(..) => "LDGetDelayTimer",
+        OpCode::LDGetKey(..) => "LDGetKey",
+        OpCode::LDSetDelayTimer(..) => "LDSetDelayTimer",
+        OpCode::LDSetSoundTimer(..) => "LDSetSoundTimer",
+        OpCode::ADDI(..) => "ADDI",
+        OpCode::LDSprite(..) => "LDSprite",
+        OpCode::LDBCD(..) => "LDBCD",
+        OpCode::LDS(..) => "LDS",
+        OpCode::LDR(..) => "LDR",
+        OpCode::SCRD(..) => "SCRD",
+        OpCode::SCRR => "SCRR",
+        OpCode::SCRL => "SCRL",
+        OpCode::EXIT => "EXIT",
+        OpCode::LOW => "LOW",
+        OpCode::HIGH => "HIGH",
+        OpCode::DRWX(..) => "DRWX",
+        OpCode::LDXSprite(..) => "LDXSprite",
+        OpCode::LDXS(..) => "LDXS",
+        OpCode::LDXR(..) => "LDXR",
+        OpCode::LongI(..) => "LongI",
+        OpCode::StoreRange(..) => "StoreRange",
+        OpCode::LoadRange(..) => "LoadRange",
+        OpCode::SCRU(..) => "SCRU",
+        OpCode::EMPTY => "EMPTY",
+        OpCode::DATA(..) => "DATA",
+    }
+}
+
 /// Get string output for an opcode.
 /// Return a tuple: (assembly, verbose).
 ///
@@ -547,11 +751,11 @@ pub fn get_opcode_str(opcode_enum: &OpCode) -> (String, String) {
         OpCode::OR(reg1, reg2) => (format!("OR V{:X}, V{:X}", reg1, reg2), format!("set V{:X} = V{:X} OR V{:X}", reg1, reg1, reg2)),
         OpCode::AND(reg1, reg2) => (format!("AND V{:X}, V{:X}", reg1, reg2), format!("set V{:X} = V{:X} AND V{:X}", reg1, reg1, reg2)),
         OpCode::XOR(reg1, reg2) => (format!("XOR V{:X}, V{:X}", reg1, reg2), format!("set V{:X} = V{:X} XOR V{:X}", reg1, reg1, reg2)),
-        OpCode::ADD(reg1, reg2) => (format!("AND V{:X}, V{:X}", reg1, reg2), format!("set V{:X} = V{:X} + V{:X}, set VF = carry", reg1, reg1, reg2)),
+        OpCode::ADD(reg1, reg2) => (format!("ADD V{:X}, V{:X}", reg1, reg2), format!("set V{:X} = V{:X} + V{:X}, set VF = carry", reg1, reg1, reg2)),
         OpCode::SUB(reg1, reg2) => (format!("SUB V{:X}, V{:X}", reg1, reg2), format!("set V{:X} = V{:X} - V{:X}, set VF = NOT borrow", reg1, reg1, reg2)),
-        OpCode::SHR(reg, _) => (format!("SHR V{:X}", reg), format!("set V{:X} = V{:X} SHR 1", reg, reg)),
+        OpCode::SHR(reg1, reg2) => (format!("SHR V{:X}, V{:X}", reg1, reg2), format!("set V{:X} = V{:X} SHR 1", reg1, reg1)),
         OpCode::SUBN(reg1, reg2) => (format!("SUBN V{:X}, V{:X}", reg1, reg2), format!("set V{:X} = V{:X} - V{:X}, set VF = NOT borrow", reg1, reg2, reg1)),
-        OpCode::SHL(reg, _) => (format!("SHL V{:X}", reg), format!("set V{:X} = V{:X} SHL 1", reg, reg)),
+        OpCode::SHL(reg1, reg2) => (format!("SHL V{:X}, V{:X}", reg1, reg2), format!("set V{:X} = V{:X} SHL 1", reg1, reg1)),
         OpCode::SNE(reg1, reg2) => (format!("SNE V{:X}, V{:X}", reg1, reg2), format!("skip next instruction if V{:X} != V{:X}", reg1, reg2)),
         OpCode::LDI(addr) => (format!("LD I, {:04X}", addr), format!("set I = {:04X}", addr)),
         OpCode::JP0(addr) => (format!("JP V0, {:04X}", addr), format!("jump to location {:04X} + V0", addr)),
@@ -584,6 +788,12 @@ pub fn get_opcode_str(opcode_enum: &OpCode) -> (String, String) {
         OpCode::LDXS(reg) => (format!("LDX [I], V{:X}", reg), format!("store V0..V{:X} in RPL user flags", reg)),
         OpCode::LDXR(reg) => (format!("LDX V{:X}, [I]", reg), format!("read V0..V{:X} from RPL user flags", reg)),
 
+        // XO-CHIP.
+        OpCode::LongI(addr) => (format!("LDI.L {:04X}", addr), format!("set I = {:04X}", addr)),
+        OpCode::StoreRange(reg1, reg2) => (format!("LD [I], V{:X}-V{:X}", reg1, reg2), format!("store V{:X}..V{:X} in memory starting at I", reg1, reg2)),
+        OpCode::LoadRange(reg1, reg2) => (format!("LD V{:X}-V{:X}, [I]", reg1, reg2), format!("read V{:X}..V{:X} from memory starting at I", reg1, reg2)),
+        OpCode::SCRU(byte) => (format!("SCRU {:X}", byte), format!("scroll display {} lines up", byte)),
+
         OpCode::EMPTY => ("EMPTY".into(), "- empty".into()),
         OpCode::DATA(opcode) => (format!("DATA {:04X}", opcode), format!("- data ({:04X})", opcode))
     }
@@ -636,3 +846,87 @@ pub fn is_opcode_schip(opcode: C8Addr) -> bool {
             | OpCode::LDXR(_)
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_all() {
+        let data = [0x00, 0xE0, 0xF0, 0x00, 0xAB, 0xCD, 0x12, 0x00, 0x7F];
+
+        assert_eq!(
+            decode_all(&data).collect::<Vec<_>>(),
+            vec![
+                (0x0200, OpCode::CLS),
+                (0x0202, OpCode::LongI(0xABCD)),
+                (0x0206, OpCode::JP(0x0200)),
+                (0x0208, OpCode::DATA(0x007F)),
+            ]
+        );
+        assert_eq!(decode_all(&data[..8]).count(), 3);
+        assert_eq!(decode_all(&[]).next(), None);
+
+        // Truncated long I.
+        assert_eq!(
+            decode_all(&[0x00, 0xE0, 0xF0, 0x00, 0xAB]).collect::<Vec<_>>(),
+            vec![
+                (0x0200, OpCode::CLS),
+                (0x0202, OpCode::DATA(0x00F0)),
+                (0x0203, OpCode::DATA(0x0000)),
+                (0x0204, OpCode::DATA(0x00AB)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_long_i_decode() {
+        let data = [0xF0, 0x00, 0xAB, 0xCD, 0x00, 0xE0];
+
+        let opcode = get_opcode_enum_from_array(&data, 0);
+        assert_eq!(opcode, OpCode::LongI(0xABCD));
+        assert_eq!(get_opcode_size(&opcode), 4);
+
+        let opcode = get_opcode_enum_from_array(&data, 4);
+        assert_eq!(opcode, OpCode::CLS);
+        assert_eq!(get_opcode_size(&opcode), 2);
+    }
+
+    #[test]
+    fn test_decode_fields() {
+        let fields = decode_fields(0xD123);
+        assert_eq!(fields.x, 0x1);
+        assert_eq!(fields.y, 0x2);
+        assert_eq!(fields.n, 0x3);
+        assert_eq!(fields.kk, 0x23);
+
+        assert_eq!(decode_fields(0xA456).nnn, 0x456);
+    }
+
+    #[test]
+    fn test_register_compare_low_nibble() {
+        assert_eq!(get_opcode_enum(0x5120), OpCode::SE(0x1, 0x2));
+        assert_eq!(get_opcode_enum(0x9120), OpCode::SNE(0x1, 0x2));
+
+        for n in 0x1..=0xF {
+            if n != 0x2 && n != 0x3 {
+                assert_eq!(get_opcode_enum(0x5120 | n), OpCode::DATA(0x5120 | n));
+            }
+            assert_eq!(get_opcode_enum(0x9120 | n), OpCode::DATA(0x9120 | n));
+        }
+    }
+
+    #[test]
+    fn test_range_decode() {
+        assert_eq!(get_opcode_enum(0x5252), OpCode::StoreRange(0x2, 0x5));
+        assert_eq!(get_opcode_enum(0x5523), OpCode::LoadRange(0x5, 0x2));
+    }
+
+    #[test]
+    fn test_scroll_up_decode() {
+        assert_eq!(get_opcode_enum(0x00D3), OpCode::SCRU(0x3));
+        assert_eq!(get_opcode_enum(0x00DF), OpCode::SCRU(0xF));
+        assert_eq!(get_opcode_enum(0x00C3), OpCode::SCRD(0x3));
+        assert_eq!(get_opcode_str(&OpCode::SCRU(0x3)).0, "SCRU 3");
+    }
+}
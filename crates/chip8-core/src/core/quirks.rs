@@ -0,0 +1,106 @@
+//! Interpreter quirks.
+
+use crate::peripherals::memory::{EXTENDED_MEMORY_SIZE, MEMORY_SIZE};
+
+/// Compatibility mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompatMode {
+    /// Original CHIP-8.
+    Chip8,
+    /// SUPER-CHIP.
+    SChip,
+    /// XO-CHIP.
+    XOChip,
+}
+
+impl Default for CompatMode {
+    fn default() -> Self {
+        Self::Chip8
+    }
+}
+
+impl CompatMode {
+    /// Get memory size.
+    ///
+    /// # Returns
+    ///
+    /// * Memory size in bytes.
+    ///
+    pub fn memory_size(&self) -> usize {
+        match self {
+            Self::Chip8 | Self::SChip => MEMORY_SIZE,
+            Self::XOChip => EXTENDED_MEMORY_SIZE,
+        }
+    }
+
+    /// Get stack depth.
+    ///
+    /// Nesting deeper stops the CPU with `ExitReason::StackOverflow`.
+    ///
+    /// # Returns
+    ///
+    /// * Maximum subroutine nesting.
+    ///
+    pub fn stack_depth(&self) -> usize {
+        match self {
+            Self::Chip8 => 12,
+            Self::SChip | Self::XOChip => 16,
+        }
+    }
+
+    /// Get quirk set.
+    ///
+    /// # Returns
+    ///
+    /// * Quirk set.
+    ///
+    pub fn quirks(&self) -> QuirkSet {
+        match self {
+            Self::Chip8 | Self::XOChip => QuirkSet::chip8(),
+            Self::SChip => QuirkSet::schip(),
+        }
+    }
+}
+
+/// Quirk set.
+///
+/// Toggles for behaviours that differ between CHIP-8 interpreters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QuirkSet {
+    /// In extended mode, DRWX sets VF to the number of sprite rows that collided.
+    pub schip_row_collisions: bool,
+    /// ADD I, Vx sets VF when I goes past 0x0FFF (Amiga interpreter).
+    pub addi_overflow: bool,
+    /// JP0 is read as `Bxnn`, jumping to `xnn + Vx` instead of `nnn + V0`.
+    pub jump_vx: bool,
+    /// SKP/SKNP read the key state latched at the start of the tick, instead of the live state.
+    pub latched_input: bool,
+    /// In low resolution, SCRD/SCRU wait for the next 60Hz timer tick before scrolling.
+    pub lores_scroll_sync: bool,
+}
+
+impl QuirkSet {
+    /// CHIP-8 quirk set.
+    ///
+    /// # Returns
+    ///
+    /// * Quirk set.
+    ///
+    pub fn chip8() -> Self {
+        Self::default()
+    }
+
+    /// SUPER-CHIP quirk set.
+    ///
+    /// # Returns
+    ///
+    /// * Quirk set.
+    ///
+    pub fn schip() -> Self {
+        Self {
+            schip_row_collisions: true,
+            jump_vx: true,
+            ..Self::default()
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Random number sources.
+
+use quad_rand::gen_range;
+
+use super::types::C8Byte;
+
+/// Random byte source, used by `RND`.
+pub trait RandSource: Send {
+    /// Get next random byte.
+    ///
+    /// # Returns
+    ///
+    /// * Random byte.
+    ///
+    fn next_byte(&mut self) -> C8Byte;
+}
+
+/// Uniform random source.
+#[derive(Debug, Default)]
+pub struct UniformRandSource;
+
+impl UniformRandSource {
+    /// Create uniform random source.
+    ///
+    /// # Returns
+    ///
+    /// * Random source.
+    ///
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl RandSource for UniformRandSource {
+    fn next_byte(&mut self) -> C8Byte {
+        gen_range(0, C8Byte::MAX)
+    }
+}
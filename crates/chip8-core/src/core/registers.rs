@@ -65,6 +65,31 @@ impl Registers {
         &self.data
     }
 
+    /// Get a copy of V registers.
+    ///
+    /// # Returns
+    ///
+    /// * V0 to VF values.
+    ///
+    pub fn snapshot(&self) -> [C8Byte; REGISTER_COUNT] {
+        let mut output = [0; REGISTER_COUNT];
+        for (dst, src) in output.iter_mut().zip(&self.data) {
+            *dst = *src;
+        }
+
+        output
+    }
+
+    /// Get a copy of all registers.
+    ///
+    /// # Returns
+    ///
+    /// * V0 to VF values, and I register value.
+    ///
+    pub fn all(&self) -> ([C8Byte; REGISTER_COUNT], C8Addr) {
+        (self.snapshot(), self.i)
+    }
+
     /// Set register value.
     ///
     /// # Arguments
@@ -129,3 +154,25 @@ impl fmt::Debug for Registers {
         writeln!(f, "    I: {:02X}", self.i)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot() {
+        let mut registers = Registers::new();
+        registers.set_register(0x0, 0x12);
+        registers.set_register(0x7, 0x34);
+        registers.set_carry_register(0x01);
+        registers.set_i_register(0x0345);
+
+        let mut expected = [0; REGISTER_COUNT];
+        expected[0x0] = 0x12;
+        expected[0x7] = 0x34;
+        expected[0xF] = 0x01;
+
+        assert_eq!(registers.snapshot(), expected);
+        assert_eq!(registers.all(), (expected, 0x0345));
+    }
+}
@@ -0,0 +1,103 @@
+//! RPL user flags.
+
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::Path,
+};
+
+use super::types::{C8Byte, C8RegIdx};
+use crate::errors::CResult;
+
+/// RPL flag count.
+pub const RPL_FLAG_COUNT: usize = 8;
+
+/// RPL user flags.
+///
+/// HP48 'flags' registers, written by `LDX [I], Vx` and read by `LDX Vx, [I]`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RplFlags {
+    data: [C8Byte; RPL_FLAG_COUNT],
+}
+
+impl RplFlags {
+    /// Create RPL flags.
+    ///
+    /// # Returns
+    ///
+    /// * RPL flags instance.
+    ///
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Store registers in flags.
+    ///
+    /// Only the first `RPL_FLAG_COUNT` values are stored.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - Register values, starting from V0.
+    ///
+    pub fn store(&mut self, values: &[C8Byte]) {
+        let count = values.len().min(RPL_FLAG_COUNT);
+        self.data[..count].copy_from_slice(&values[..count]);
+    }
+
+    /// Get flag value.
+    ///
+    /// # Arguments
+    ///
+    /// * `idx` - Flag index.
+    ///
+    /// # Returns
+    ///
+    /// * Flag value.
+    ///
+    pub fn get_flag(&self, idx: C8RegIdx) -> C8Byte {
+        self.data[idx as usize % RPL_FLAG_COUNT]
+    }
+
+    /// Get flags data.
+    ///
+    /// # Returns
+    ///
+    /// * Flags data.
+    ///
+    pub fn get_data(&self) -> &[C8Byte] {
+        &self.data
+    }
+
+    /// Load flags from path.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path.
+    ///
+    /// # Returns
+    ///
+    /// * RPL flags result.
+    ///
+    pub fn load_from_path<P: AsRef<Path>>(path: P) -> CResult<Self> {
+        let mut file = File::open(path.as_ref())?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+
+        let mut flags = Self::new();
+        flags.store(&contents);
+        Ok(flags)
+    }
+
+    /// Save flags to path.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path.
+    ///
+    pub fn save_to_path<P: AsRef<Path>>(&self, path: P) -> CResult {
+        let mut file = File::create(path.as_ref())?;
+
+        file.write_all(&self.data)?;
+        Ok(())
+    }
+}
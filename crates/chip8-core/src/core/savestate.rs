@@ -8,10 +8,19 @@ use std::{
     path::Path,
 };
 
-use nanoserde::{DeBin, SerBin};
+use nanoserde::{DeBin, DeJson, SerBin, SerJson};
 
-use super::{cpu::CPU, registers::Registers, stack::Stack, timer::Timer};
-use crate::peripherals::{input::InputState, memory::Memory, screen::ScreenData};
+use super::{
+    cpu::CPU,
+    registers::Registers,
+    stack::Stack,
+    timer::Timer,
+    types::{C8Addr, C8Byte},
+};
+use crate::{
+    errors::CResult,
+    peripherals::{input::InputState, memory::Memory, screen::ScreenData},
+};
 
 /// Missing save state.
 #[derive(Debug)]
@@ -84,12 +93,28 @@ impl SaveState {
     /// * `path` - Path to file.
     ///
     pub fn write_to_file(&self, path: &str) {
-        let state_bin = SerBin::serialize_bin(self);
-        let mut file = File::create(path).expect("Could not create savestate file.");
-        file.write_all(&state_bin)
+        self.save_to_path(path)
             .expect("Error when writing savestate.");
     }
 
+    /// Save state to path.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to file.
+    ///
+    /// # Returns
+    ///
+    /// * Result.
+    ///
+    pub fn save_to_path<P: AsRef<Path>>(&self, path: P) -> CResult {
+        let state_bin = SerBin::serialize_bin(self);
+        let mut file = File::create(path.as_ref())?;
+        file.write_all(&state_bin)?;
+
+        Ok(())
+    }
+
     /// Read save state from file.
     ///
     /// # Arguments
@@ -114,3 +139,145 @@ impl SaveState {
         Some(DeBin::deserialize_bin(&data).unwrap())
     }
 }
+
+/// Machine state.
+///
+/// Plain export of the machine, for post-mortem analysis with external tools.
+#[derive(Clone, Debug, SerJson, DeJson)]
+pub struct MachineState {
+    /// Program counter.
+    pub pc: C8Addr,
+    /// I register.
+    pub i: C8Addr,
+    /// V registers.
+    pub registers: Vec<C8Byte>,
+    /// Stack pointer.
+    pub sp: C8Byte,
+    /// Stack.
+    pub stack: Vec<C8Addr>,
+    /// Delay timer.
+    pub delay_timer: C8Byte,
+    /// Sound timer.
+    pub sound_timer: C8Byte,
+    /// Instruction count.
+    pub instruction_count: usize,
+    /// Memory.
+    pub memory: Vec<C8Byte>,
+    /// Screen width.
+    pub screen_width: usize,
+    /// Screen height.
+    pub screen_height: usize,
+    /// Screen pixels, row by row.
+    pub screen: Vec<C8Byte>,
+    /// Exit reason name (see `ExitReason`), empty if the CPU is running.
+    pub exit_reason: String,
+}
+
+impl MachineState {
+    /// Create machine state from CPU.
+    ///
+    /// # Arguments
+    ///
+    /// * `cpu` - CPU.
+    ///
+    /// # Returns
+    ///
+    /// * Machine state.
+    ///
+    pub fn from_cpu(cpu: &CPU) -> Self {
+        let (registers, i) = cpu.registers.all();
+        let screen = cpu.peripherals.screen.snapshot();
+        let (screen_width, screen_height) = screen.get_dimensions();
+
+        Self {
+            pc: cpu.peripherals.memory.get_pointer(),
+            i,
+            registers: registers.to_vec(),
+            sp: cpu.stack.get_pointer(),
+            stack: cpu.stack.get_data().to_vec(),
+            delay_timer: cpu.delay_timer.get_value(),
+            sound_timer: cpu.sound_timer.get_value(),
+            instruction_count: cpu.instruction_count,
+            memory: cpu.peripherals.memory.get_data().to_vec(),
+            screen_width,
+            screen_height,
+            screen: screen.get_pixels().to_vec(),
+            exit_reason: cpu
+                .exit_reason
+                .map(|reason| format!("{:?}", reason))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Export to JSON.
+    ///
+    /// # Returns
+    ///
+    /// * JSON string.
+    ///
+    pub fn to_json(&self) -> String {
+        SerJson::serialize_json(self)
+    }
+
+    /// Import from JSON.
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - JSON string.
+    ///
+    /// # Returns
+    ///
+    /// * Machine state result.
+    ///
+    pub fn from_json(json: &str) -> CResult<Self> {
+        DeJson::deserialize_json(json).map_err(|e| format!("bad machine state: {:?}", e).into())
+    }
+
+    /// Save machine state to path, as JSON.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to file.
+    ///
+    /// # Returns
+    ///
+    /// * Result.
+    ///
+    pub fn save_to_path<P: AsRef<Path>>(&self, path: P) -> CResult {
+        let mut file = File::create(path.as_ref())?;
+        file.write_all(self.to_json().as_bytes())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::cpu::ExitReason;
+
+    #[test]
+    fn test_machine_state_json() {
+        let mut cpu = CPU::new();
+        cpu.registers.set_register(0xA, 0x42);
+        cpu.peripherals.memory.set_pointer(0x0234);
+
+        let state = MachineState::from_cpu(&cpu);
+        let json = state.to_json();
+        assert!(json.contains("\"pc\":564"));
+
+        let state = MachineState::from_json(&json).unwrap();
+        assert_eq!(state.pc, 0x0234);
+        assert_eq!(state.registers[0xA], 0x42);
+        assert_eq!(state.screen.len(), state.screen_width * state.screen_height);
+
+        assert_eq!(state.exit_reason, "");
+
+        cpu.exit_reason = Some(ExitReason::StackOverflow);
+        let json = MachineState::from_cpu(&cpu).to_json();
+        let state = MachineState::from_json(&json).unwrap();
+        assert_eq!(state.exit_reason, "StackOverflow");
+
+        assert!(MachineState::from_json("{").is_err());
+    }
+}
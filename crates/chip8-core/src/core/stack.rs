@@ -5,9 +5,10 @@ use std::fmt;
 use nanoserde::{DeBin, SerBin};
 
 use super::types::{C8Addr, C8Byte};
+use crate::errors::Chip8Error;
 
-/// CPU stack depth.
-const STACK_DEPTH: usize = 16;
+/// Default CPU stack depth.
+pub const STACK_DEPTH: usize = 16;
 
 /// CPU stack struct.
 #[derive(Clone, DeBin, SerBin, Default)]
@@ -24,25 +25,54 @@ impl Stack {
     /// * Stack instance.
     ///
     pub fn new() -> Self {
+        Self::with_capacity(STACK_DEPTH)
+    }
+
+    /// Create new stack with a specific depth.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - Maximum nesting depth.
+    ///
+    /// # Returns
+    ///
+    /// * Stack instance.
+    ///
+    pub fn with_capacity(capacity: usize) -> Self {
         Stack {
-            data: vec![0; STACK_DEPTH],
+            data: vec![0; capacity],
             pointer: 0,
         }
     }
 
-    /// Store address in stack.
+    /// Get capacity.
+    ///
+    /// # Returns
+    ///
+    /// * Maximum nesting depth.
+    ///
+    pub fn get_capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Try to store address in stack.
     ///
     /// # Arguments
     ///
     /// * `addr` - Address to store.
     ///
-    pub fn push(&mut self, addr: C8Addr) {
-        if self.pointer as usize >= STACK_DEPTH {
-            panic!("CPU stack is full ! (limit: {})", STACK_DEPTH);
+    /// # Returns
+    ///
+    /// * Result, failing if the stack is full.
+    ///
+    pub fn try_push(&mut self, addr: C8Addr) -> Result<(), Chip8Error> {
+        if self.is_full() {
+            return Err(Chip8Error::StackOverflow(self.get_capacity()));
         }
 
         self.data[self.pointer as usize] = addr;
-        self.pointer += 1
+        self.pointer += 1;
+        Ok(())
     }
 
     /// Get data.
@@ -67,6 +97,14 @@ impl Stack {
 
     /// Peek value in stack.
     pub fn peek(&self, idx: usize) -> C8Addr {
+        if idx >= self.get_capacity() {
+            panic!(
+                "CPU stack index out of range ! ({} >= {})",
+                idx,
+                self.get_capacity()
+            );
+        }
+
         self.data[idx]
     }
 
@@ -96,9 +134,22 @@ impl Stack {
         self.pointer == 0
     }
 
+    /// Check if full.
+    ///
+    /// # Returns
+    ///
+    /// * `true` if full.
+    /// * `false` if not.
+    ///
+    pub fn is_full(&self) -> bool {
+        self.pointer as usize >= self.get_capacity()
+    }
+
     /// Reset stack.
+    ///
+    /// Capacity is kept.
     pub fn reset(&mut self) {
-        self.data = vec![0; STACK_DEPTH];
+        self.data = vec![0; self.get_capacity()];
         self.pointer = 0;
     }
 
@@ -123,3 +174,41 @@ impl fmt::Debug for Stack {
         writeln!(f, "    SP: {:02X}", self.pointer)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_capacity() {
+        let mut stack = Stack::with_capacity(4);
+        assert_eq!(stack.get_capacity(), 4);
+
+        for addr in 0..4 {
+            assert!(!stack.is_full());
+            stack.try_push(0x200 + addr).unwrap();
+        }
+        assert!(stack.is_full());
+        assert_eq!(stack.peek(3), 0x203);
+
+        assert_eq!(stack.pop(), 0x203);
+        assert!(!stack.is_full());
+
+        stack.reset();
+        assert!(stack.empty());
+        assert_eq!(stack.get_capacity(), 4);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut stack = Stack::with_capacity(4);
+        for addr in 0..4 {
+            stack.try_push(0x200 + addr).unwrap();
+        }
+
+        let err = stack.try_push(0x204).unwrap_err();
+        assert!(matches!(err, Chip8Error::StackOverflow(4)));
+        assert_eq!(err.to_string(), "CPU stack is full ! (limit: 4)");
+        assert_eq!(stack.get_pointer(), 4);
+    }
+}
@@ -1,5 +1,10 @@
 //! Core types.
 
+use std::{
+    fmt,
+    ops::{Add, Sub},
+};
+
 /// CHIP-8 byte type.
 pub type C8Byte = u8;
 
@@ -9,38 +14,197 @@ pub type C8Addr = u16;
 /// CHIP-8 register index.
 pub type C8RegIdx = u8;
 
+/// Typed CHIP-8 address.
+///
+/// Newtype over `C8Addr`, to avoid mixing addresses and bytes.
+/// Code can migrate to it gradually, using the explicit conversions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Addr(pub C8Addr);
+
+/// Typed CHIP-8 byte.
+///
+/// Newtype over `C8Byte`, to avoid mixing addresses and bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Byte(pub C8Byte);
+
+impl Addr {
+    /// Get raw address.
+    ///
+    /// # Returns
+    ///
+    /// * Raw address.
+    ///
+    pub fn get(self) -> C8Addr {
+        self.0
+    }
+
+    /// Get address as memory index.
+    ///
+    /// # Returns
+    ///
+    /// * Memory index.
+    ///
+    pub fn as_index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// Offset address, wrapping around.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Offset.
+    ///
+    /// # Returns
+    ///
+    /// * New address.
+    ///
+    pub fn wrapping_offset(self, offset: C8Addr) -> Self {
+        Self(self.0.wrapping_add(offset))
+    }
+}
+
+impl Byte {
+    /// Get raw byte.
+    ///
+    /// # Returns
+    ///
+    /// * Raw byte.
+    ///
+    pub fn get(self) -> C8Byte {
+        self.0
+    }
+}
+
+impl From<C8Addr> for Addr {
+    fn from(value: C8Addr) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Addr> for C8Addr {
+    fn from(value: Addr) -> Self {
+        value.0
+    }
+}
+
+impl From<C8Byte> for Byte {
+    fn from(value: C8Byte) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Byte> for C8Byte {
+    fn from(value: Byte) -> Self {
+        value.0
+    }
+}
+
+impl From<Byte> for Addr {
+    fn from(value: Byte) -> Self {
+        Self(C8Addr::from(value.0))
+    }
+}
+
+impl Add<C8Addr> for Addr {
+    type Output = Addr;
+
+    fn add(self, rhs: C8Addr) -> Self::Output {
+        Self(self.0.wrapping_add(rhs))
+    }
+}
+
+impl Sub<Addr> for Addr {
+    type Output = C8Addr;
+
+    fn sub(self, rhs: Addr) -> Self::Output {
+        self.0.wrapping_sub(rhs.0)
+    }
+}
+
+impl fmt::Display for Addr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04X}", self.0)
+    }
+}
+
+impl fmt::Display for Byte {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02X}", self.0)
+    }
+}
+
 /// Convert hexadecimal address.
 ///
 /// # Arguments
 ///
-/// * `s` - Input string.
+/// * `s` - Input string, with optional `0x` prefix.
 ///
 /// # Returns
 ///
 /// * Address option.
 ///
-pub fn convert_hex_addr(s: &str) -> Option<C8Addr> {
-    if s.len() >= 2 && &s[2..] == "0x" {
-        _convert_hex_addr(&s[2..])
-    } else {
-        _convert_hex_addr(s)
-    }
-}
-
-fn _convert_hex_addr(s: &str) -> Option<C8Addr> {
-    C8Addr::from_str_radix(s, 16).ok()
+pub fn convert_hex_addr(s: &str) -> Option<Addr> {
+    C8Addr::from_str_radix(strip_hex_prefix(s), 16)
+        .ok()
+        .map(Addr)
 }
 
 /// Convert hexadecimal byte.
 ///
 /// # Arguments
 ///
-/// * `s` - Input string.
+/// * `s` - Input string, with optional `0x` prefix.
 ///
 /// # Returns
 ///
 /// * Byte option.
 ///
-pub fn convert_hex_byte(s: &str) -> Option<C8Byte> {
-    C8Byte::from_str_radix(s, 16).ok()
+pub fn convert_hex_byte(s: &str) -> Option<Byte> {
+    C8Byte::from_str_radix(strip_hex_prefix(s), 16)
+        .ok()
+        .map(Byte)
+}
+
+fn strip_hex_prefix(s: &str) -> &str {
+    s.strip_prefix("0x").unwrap_or(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_hex() {
+        assert_eq!(convert_hex_addr("0200"), Some(Addr(0x200)));
+        assert_eq!(convert_hex_addr("0x0ABC"), Some(Addr(0xABC)));
+        assert_eq!(convert_hex_addr("ZZ"), None);
+        assert_eq!(convert_hex_byte("FF"), Some(Byte(0xFF)));
+        assert_eq!(convert_hex_byte("0x1A"), Some(Byte(0x1A)));
+        assert_eq!(convert_hex_byte("100"), None);
+    }
+
+    #[test]
+    fn test_conversions() {
+        let addr = Addr::from(0x0300);
+        assert_eq!(C8Addr::from(addr), 0x0300);
+        assert_eq!(addr.as_index(), 0x300_usize);
+
+        let byte = Byte::from(0x12);
+        assert_eq!(C8Byte::from(byte), 0x12);
+        assert_eq!(Addr::from(byte), Addr(0x0012));
+
+        assert_eq!(addr.to_string(), "0300");
+        assert_eq!(byte.to_string(), "12");
+    }
+
+    #[test]
+    fn test_addr_arithmetic() {
+        let addr = Addr(0x0200);
+        assert_eq!(addr + 2, Addr(0x0202));
+        assert_eq!(Addr(0xFFFF) + 2, Addr(0x0001));
+        assert_eq!(Addr(0x0210) - addr, 0x10);
+        assert_eq!(Addr(0) - Addr(1), 0xFFFF);
+        assert_eq!(Addr(0xFFFF).wrapping_offset(2), Addr(0x0001));
+        assert!(addr < addr + 1);
+    }
 }
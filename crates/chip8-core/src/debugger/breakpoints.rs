@@ -75,6 +75,16 @@ impl Breakpoints {
     pub fn check_breakpoint(&self, addr: C8Addr) -> bool {
         self.get_breakpoint(addr).is_some()
     }
+
+    /// Get breakpoint addresses.
+    ///
+    /// # Returns
+    ///
+    /// * Addresses, in registration order.
+    ///
+    pub fn addresses(&self) -> Vec<C8Addr> {
+        self.0.clone()
+    }
 }
 
 impl fmt::Debug for Breakpoints {
@@ -109,5 +119,9 @@ mod tests {
         bps.unregister(0x1234);
         assert!(!bps.check_breakpoint(0x1234));
         bps.unregister(0x1234);
+
+        bps.register(0x0206);
+        bps.register(0x0202);
+        assert_eq!(bps.addresses(), vec![0x0206, 0x0202]);
     }
 }
@@ -1,14 +1,43 @@
 //! Debugger context.
 
+use std::{
+    env,
+    fs::File,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
 use rustyline::Editor;
 
 use super::errors::BadBreakpoint;
 use crate::{
-    core::types::{convert_hex_addr, C8Addr},
+    core::{
+        cpu::CPU,
+        opcodes::{get_opcode_str, OpCode},
+        registers::Registers,
+        savestate::SaveState,
+        types::{convert_hex_addr, C8Addr},
+    },
     debugger::Breakpoints,
     errors::CResult,
+    peripherals::memory::Memory,
 };
 
+/// Default history file name, in the home directory.
+pub const HISTORY_FILE_NAME: &str = ".chip8_history";
+
+/// Get default history file path.
+///
+/// # Returns
+///
+/// * `~/.chip8_history`, or `None` if the home directory is unknown.
+///
+pub fn default_history_file() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(HISTORY_FILE_NAME))
+}
+
 /// Debugger mode.
 pub enum DebuggerMode {
     /// Interactive.
@@ -17,6 +46,34 @@ pub enum DebuggerMode {
     Manual,
 }
 
+/// CPU snapshot used for step diffing.
+#[derive(Clone, Debug)]
+pub struct StepSnapshot {
+    /// Registers.
+    pub registers: Registers,
+    /// Memory.
+    pub memory: Memory,
+}
+
+impl StepSnapshot {
+    /// Create snapshot from CPU.
+    ///
+    /// # Arguments
+    ///
+    /// * `cpu` - CPU.
+    ///
+    /// # Returns
+    ///
+    /// * Step snapshot.
+    ///
+    pub fn from_cpu(cpu: &CPU) -> Self {
+        Self {
+            registers: cpu.registers.clone(),
+            memory: cpu.peripherals.memory.clone(),
+        }
+    }
+}
+
 /// Debugger context.
 pub struct DebuggerContext {
     /// Running.
@@ -39,6 +96,20 @@ pub struct DebuggerContext {
     pub mode: DebuggerMode,
     /// Breakpoints.
     pub breakpoints: Breakpoints,
+    /// Breakpoints file, updated when breakpoints change.
+    pub breakpoints_file: Option<PathBuf>,
+    /// Command history file, saved on quit.
+    pub history_file: Option<PathBuf>,
+    /// Opcode breakpoints (mnemonics).
+    pub opcode_breakpoints: Vec<String>,
+    /// Temporary breakpoint, removed when hit.
+    pub temporary_breakpoint: Option<C8Addr>,
+    /// Save state to load on next step.
+    pub pending_savestate: Option<SaveState>,
+    /// Show register and memory diff after each step.
+    pub diff_enabled: bool,
+    /// Snapshot taken before the last step.
+    pub diff_snapshot: Option<StepSnapshot>,
 }
 
 impl Default for DebuggerContext {
@@ -54,6 +125,13 @@ impl Default for DebuggerContext {
             editor: Editor::<()>::new(),
             mode: DebuggerMode::Interactive,
             breakpoints: Breakpoints::new(),
+            breakpoints_file: None,
+            history_file: None,
+            opcode_breakpoints: Vec::new(),
+            temporary_breakpoint: None,
+            pending_savestate: None,
+            diff_enabled: false,
+            diff_snapshot: None,
         }
     }
 }
@@ -126,6 +204,37 @@ impl DebuggerContext {
         self.breakpoints.unregister(addr);
     }
 
+    /// Register opcode breakpoint.
+    ///
+    /// # Arguments
+    ///
+    /// * `mnemonic` - Opcode mnemonic (e.g. `DRW`).
+    ///
+    pub fn register_opcode_breakpoint(&mut self, mnemonic: &str) {
+        let mnemonic = mnemonic.to_uppercase();
+        if !self.opcode_breakpoints.contains(&mnemonic) {
+            self.opcode_breakpoints.push(mnemonic);
+        }
+    }
+
+    /// Check opcode breakpoint.
+    ///
+    /// # Arguments
+    ///
+    /// * `opcode` - Opcode.
+    ///
+    /// # Returns
+    ///
+    /// * `true` if a breakpoint matches the opcode mnemonic.
+    /// * `false` if not.
+    ///
+    pub fn check_opcode_breakpoint(&self, opcode: &OpCode) -> bool {
+        let (assembly, _) = get_opcode_str(opcode);
+        let mnemonic = assembly.split(' ').next().unwrap_or_default();
+
+        self.opcode_breakpoints.iter().any(|x| x == mnemonic)
+    }
+
     /// Register breakpoint as string.
     ///
     /// # Arguments
@@ -138,10 +247,143 @@ impl DebuggerContext {
     ///
     pub fn register_breakpoint_str(&mut self, addr: &str) -> CResult {
         if let Some(addr) = convert_hex_addr(addr) {
-            self.breakpoints.register(addr);
+            self.breakpoints.register(addr.get());
             Ok(())
         } else {
             Err(Box::new(BadBreakpoint(String::from(addr))))
         }
     }
+
+    /// Save breakpoints to path.
+    ///
+    /// One hexadecimal address per line.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path.
+    ///
+    /// # Returns
+    ///
+    /// * Result.
+    ///
+    pub fn save_breakpoints<P: AsRef<Path>>(&self, path: P) -> CResult {
+        let mut file = File::create(path.as_ref())?;
+
+        for addr in &self.breakpoints.0 {
+            writeln!(file, "{:04X}", addr)?;
+        }
+
+        Ok(())
+    }
+
+    /// Load breakpoints from path.
+    ///
+    /// Loaded breakpoints are added to the current ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path.
+    ///
+    /// # Returns
+    ///
+    /// * Result.
+    ///
+    pub fn load_breakpoints<P: AsRef<Path>>(&mut self, path: P) -> CResult {
+        let mut file = File::open(path.as_ref())?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+
+        for line in contents.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            self.register_breakpoint_str(line)?;
+        }
+
+        Ok(())
+    }
+
+    /// Save breakpoints to the breakpoints file, if any.
+    ///
+    /// # Returns
+    ///
+    /// * Result.
+    ///
+    pub fn sync_breakpoints_file(&self) -> CResult {
+        if let Some(path) = &self.breakpoints_file {
+            self.save_breakpoints(path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Load command history from the history file, if any.
+    ///
+    /// A missing history file is not an error.
+    ///
+    /// # Returns
+    ///
+    /// * Result.
+    ///
+    pub fn load_history(&mut self) -> CResult {
+        if let Some(path) = &self.history_file {
+            if path.exists() {
+                self.editor.load_history(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Save command history to the history file, if any.
+    ///
+    /// # Returns
+    ///
+    /// * Result.
+    ///
+    pub fn save_history(&mut self) -> CResult {
+        if let Some(path) = &self.history_file {
+            self.editor.save_history(path)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breakpoints_round_trip() {
+        let path = std::env::temp_dir().join(format!("chip8-{}.bp", std::process::id()));
+
+        let mut ctx = DebuggerContext::new();
+        ctx.register_breakpoint(0x0204);
+        ctx.register_breakpoint(0x0ABC);
+        ctx.save_breakpoints(&path).unwrap();
+
+        let mut ctx = DebuggerContext::new();
+        ctx.load_breakpoints(&path).unwrap();
+        assert_eq!(ctx.breakpoints.0, vec![0x0204, 0x0ABC]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_history_round_trip() {
+        let path = std::env::temp_dir().join(format!("chip8-{}.history", std::process::id()));
+
+        let mut ctx = DebuggerContext::new();
+        ctx.history_file = Some(path.clone());
+        ctx.load_history().unwrap();
+        ctx.editor.add_history_entry("b 0204");
+        ctx.editor.add_history_entry("c");
+        ctx.save_history().unwrap();
+
+        let mut ctx = DebuggerContext::new();
+        ctx.history_file = Some(path.clone());
+        ctx.load_history().unwrap();
+        let history: Vec<&str> = ctx.editor.history().iter().map(String::as_str).collect();
+        assert_eq!(history, vec!["b 0204", "c"]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -6,19 +6,22 @@ mod errors;
 mod stream;
 
 pub use breakpoints::Breakpoints;
-pub use context::DebuggerContext;
 use context::DebuggerMode;
+pub use context::{default_history_file, DebuggerContext, StepSnapshot};
+pub use errors::BadBreakpoint;
 use rustyline::error::ReadlineError;
-pub use stream::DebuggerStream;
+pub use stream::{DebuggerStream, DebuggerStreamLine};
 
 use crate::{
     core::{
         cpu::CPU,
-        opcodes::{get_opcode_enum, get_opcode_str},
-        types::{convert_hex_addr, C8Addr, C8RegIdx},
+        opcodes::{
+            get_opcode_enum, get_opcode_enum_from_array, get_opcode_size, get_opcode_str, OpCode,
+        },
+        savestate::SaveState,
+        types::{convert_hex_addr, convert_hex_byte, C8Addr, C8Byte, C8RegIdx},
     },
     emulator::{EmulationState, Emulator, EmulatorContext},
-    peripherals::memory::INITIAL_MEMORY_POINTER,
 };
 
 /// Debugger.
@@ -59,20 +62,38 @@ pub enum Command {
     List(u16),
     /// Complete source.
     LongList,
+    /// Disassemble live memory (start address, instruction count).
+    DisassembleLive(C8Addr, u16),
+    /// Preview sprite in memory (start address, rows, 16 pixels wide).
+    Sprite(C8Addr, u16, bool),
     /// Dump CPU.
     Dump(String),
     /// Read register.
     ReadRegister(RegisterKind),
     /// Read memory at offset.
     ReadMemory(C8Addr, C8Addr),
+    /// Dump memory region to path (start address, count, path).
+    DumpMemory(C8Addr, C8Addr, String),
+    /// Find byte pattern in memory.
+    Find(Vec<C8Byte>),
     /// Step instruction.
     Step,
+    /// Step instruction, stepping over subroutine calls.
+    Next,
     /// Add breakpoint.
     AddBreakpoint(C8Addr),
     /// Remove breakpoint.
     RemoveBreakpoint(C8Addr),
     /// List breakpoints.
     ListBreakpoints,
+    /// Break when an opcode mnemonic is about to execute.
+    BreakOnOpcode(String),
+    /// Save state to path.
+    SaveState(String),
+    /// Toggle step diffing.
+    Diff(bool),
+    /// Load state from path.
+    LoadState(String),
     /// Show help.
     Help,
     /// Empty.
@@ -113,32 +134,73 @@ impl Debugger {
     ) -> EmulationState {
         // Should quit?
         if debug_ctx.should_quit {
+            if let Err(e) = debug_ctx.save_history() {
+                stream.writeln_stderr(format!("could not save history: {}", e));
+            }
             return EmulationState::Quit;
         }
 
         // Emulator step result
         let mut emulator_step_result = EmulationState::Normal;
+        let was_paused = debug_ctx.is_paused();
+
+        // Load pending save state.
+        if let Some(state) = debug_ctx.pending_savestate.take() {
+            emulator.cpu.load_savestate(state);
+            debug_ctx.set_address(emulator.cpu.peripherals.memory.get_pointer());
+            debug_ctx.has_moved = true;
+        }
 
         // Check for breakpoint.
         if debug_ctx.is_continuing && !debug_ctx.breakpoint_hit {
             let pointer = emulator.cpu.peripherals.memory.get_pointer();
-            if debug_ctx.breakpoints.check_breakpoint(pointer) {
+            if debug_ctx.temporary_breakpoint == Some(pointer) {
+                debug_ctx.temporary_breakpoint = None;
+                debug_ctx.breakpoint_hit = true;
+                debug_ctx.has_moved = true;
+                debug_ctx.pause();
+            } else if debug_ctx.breakpoints.check_breakpoint(pointer) {
                 debug_ctx.breakpoint_hit = true;
                 debug_ctx.has_moved = true;
                 debug_ctx.pause();
+            } else if !debug_ctx.opcode_breakpoints.is_empty() {
+                let opcode = get_opcode_enum_from_array(
+                    emulator.cpu.peripherals.memory.get_data(),
+                    pointer as usize,
+                );
+
+                if debug_ctx.check_opcode_breakpoint(&opcode) {
+                    debug_ctx.breakpoint_hit = true;
+                    debug_ctx.has_moved = true;
+                    debug_ctx.pause();
+                }
             }
         }
 
         // Step.
         if debug_ctx.is_stepping || debug_ctx.is_continuing {
+            // Only diff single steps, not continuous execution.
+            debug_ctx.diff_snapshot = if debug_ctx.diff_enabled && debug_ctx.is_stepping {
+                Some(StepSnapshot::from_cpu(&emulator.cpu))
+            } else {
+                None
+            };
+
             emulator_step_result = emulator.step(emulator_ctx);
 
+            if let Some(snapshot) = &debug_ctx.diff_snapshot {
+                self.show_diff(&emulator.cpu, snapshot, stream);
+            }
+
             // Just moved.
             debug_ctx.has_moved = true;
             // Change debugger address.
             debug_ctx.set_address(emulator.cpu.peripherals.memory.get_pointer());
 
-            if debug_ctx.is_stepping {
+            // Keep stepping until the awaited key is received, without
+            // blocking on the prompt.
+            let waiting = matches!(emulator_step_result, EmulationState::WaitForInput);
+            if debug_ctx.is_stepping && !waiting {
                 debug_ctx.is_stepping = false;
             }
 
@@ -147,9 +209,14 @@ impl Debugger {
             }
         }
 
+        // Timers are not updated while paused: do not let the beep hang.
+        if debug_ctx.is_paused() && !was_paused {
+            emulator.cpu.stop_sound();
+        }
+
         // Interactive mode.
         if let DebuggerMode::Interactive = debug_ctx.mode {
-            if debug_ctx.is_paused() {
+            if debug_ctx.is_paused() && !debug_ctx.is_stepping {
                 if debug_ctx.has_moved {
                     self.show_line_context(&emulator.cpu, debug_ctx, stream, 1, 1);
                     debug_ctx.has_moved = false;
@@ -225,6 +292,7 @@ impl Debugger {
                     stream.writeln_stdout("    - registers");
                     stream.writeln_stdout("    - timers");
                     stream.writeln_stdout("    - video");
+                    stream.writeln_stdout("    - screen");
                     None
                 }
             }
@@ -242,7 +310,53 @@ impl Debugger {
                 }
             }
             "longlist" | "ll" => Some(Command::LongList),
-            "step" | "s" | "next" | "n" => Some(Command::Step),
+            "disasm-live" | "dl" => {
+                if cmd_split.len() == 3 {
+                    let addr = match convert_hex_addr(cmd_split[1]) {
+                        Some(addr) => addr.get(),
+                        None => {
+                            stream.writeln_stderr(format!("error: bad address {}", cmd_split[1]));
+                            return None;
+                        }
+                    };
+
+                    match cmd_split[2].parse::<u16>() {
+                        Ok(count) => Some(Command::DisassembleLive(addr, count)),
+                        Err(_) => {
+                            stream.writeln_stderr(format!("error: bad count {}", cmd_split[2]));
+                            None
+                        }
+                    }
+                } else {
+                    stream.writeln_stdout("usage: disasm-live addr count");
+                    None
+                }
+            }
+            "sprite" | "sp" | "sprite16" | "sp16" => {
+                if cmd_split.len() == 3 {
+                    let addr = match convert_hex_addr(cmd_split[1]) {
+                        Some(addr) => addr.get(),
+                        None => {
+                            stream.writeln_stderr(format!("error: bad address {}", cmd_split[1]));
+                            return None;
+                        }
+                    };
+
+                    let wide = command.ends_with("16");
+                    match cmd_split[2].parse::<u16>() {
+                        Ok(rows) => Some(Command::Sprite(addr, rows, wide)),
+                        Err(_) => {
+                            stream.writeln_stderr(format!("error: bad rows {}", cmd_split[2]));
+                            None
+                        }
+                    }
+                } else {
+                    stream.writeln_stdout(format!("usage: {} addr rows", command));
+                    None
+                }
+            }
+            "step" | "s" => Some(Command::Step),
+            "next" | "n" => Some(Command::Next),
             "help" | "h" => Some(Command::Help),
             "read-reg" | "rreg" => {
                 if cmd_split.len() == 2 {
@@ -318,7 +432,7 @@ impl Debugger {
                 if cmd_split.len() == 3 {
                     if let Some(addr) = convert_hex_addr(cmd_split[1]) {
                         Some(Command::ReadMemory(
-                            addr,
+                            addr.get(),
                             cmd_split[2].parse::<C8Addr>().unwrap(),
                         ))
                     } else {
@@ -330,10 +444,53 @@ impl Debugger {
                     None
                 }
             }
+            "dump-mem" | "dmem" => {
+                if cmd_split.len() == 4 {
+                    let addr = match convert_hex_addr(cmd_split[1]) {
+                        Some(addr) => addr.get(),
+                        None => {
+                            stream.writeln_stderr(format!("error: bad address {}", cmd_split[1]));
+                            return None;
+                        }
+                    };
+
+                    match cmd_split[2].parse::<C8Addr>() {
+                        Ok(count) => {
+                            Some(Command::DumpMemory(addr, count, cmd_split[3].to_string()))
+                        }
+                        Err(_) => {
+                            stream.writeln_stderr(format!("error: bad count {}", cmd_split[2]));
+                            None
+                        }
+                    }
+                } else {
+                    stream.writeln_stdout("usage: dump-mem addr count path");
+                    None
+                }
+            }
+            "find" | "f" => {
+                if cmd_split.len() >= 2 {
+                    let mut pattern = Vec::with_capacity(cmd_split.len() - 1);
+                    for arg in &cmd_split[1..] {
+                        match convert_hex_byte(arg) {
+                            Some(byte) => pattern.push(byte.get()),
+                            None => {
+                                stream.writeln_stderr(format!("error: bad byte {}", arg));
+                                return None;
+                            }
+                        }
+                    }
+
+                    Some(Command::Find(pattern))
+                } else {
+                    stream.writeln_stdout("usage: find byte [byte...]");
+                    None
+                }
+            }
             "add-bp" | "b" => {
                 if cmd_split.len() == 2 {
                     if let Some(addr) = convert_hex_addr(cmd_split[1]) {
-                        Some(Command::AddBreakpoint(addr))
+                        Some(Command::AddBreakpoint(addr.get()))
                     } else {
                         stream.writeln_stderr(format!("error: bad address {}", cmd_split[1]));
                         None
@@ -346,7 +503,7 @@ impl Debugger {
             "rem-bp" | "rb" => {
                 if cmd_split.len() == 2 {
                     if let Some(addr) = convert_hex_addr(cmd_split[1]) {
-                        Some(Command::RemoveBreakpoint(addr))
+                        Some(Command::RemoveBreakpoint(addr.get()))
                     } else {
                         stream.writeln_stderr(format!("error: bad address {}", cmd_split[1]));
                         None
@@ -357,6 +514,45 @@ impl Debugger {
                 }
             }
             "list-bp" | "lb" => Some(Command::ListBreakpoints),
+            "break-op" | "bo" => {
+                if cmd_split.len() == 2 {
+                    Some(Command::BreakOnOpcode(cmd_split[1].to_uppercase()))
+                } else {
+                    stream.writeln_stdout("usage: break-op mnemonic");
+                    None
+                }
+            }
+            "save-state" | "sst" => {
+                if cmd_split.len() == 2 {
+                    Some(Command::SaveState(cmd_split[1].to_string()))
+                } else {
+                    stream.writeln_stdout("usage: save-state path");
+                    None
+                }
+            }
+            "load-state" | "lst" => {
+                if cmd_split.len() == 2 {
+                    Some(Command::LoadState(cmd_split[1].to_string()))
+                } else {
+                    stream.writeln_stdout("usage: load-state path");
+                    None
+                }
+            }
+            "diff" => {
+                if cmd_split.len() == 2 {
+                    match cmd_split[1] {
+                        "on" => Some(Command::Diff(true)),
+                        "off" => Some(Command::Diff(false)),
+                        _ => {
+                            stream.writeln_stdout("usage: diff on|off");
+                            None
+                        }
+                    }
+                } else {
+                    stream.writeln_stdout("usage: diff on|off");
+                    None
+                }
+            }
             "" => Some(Command::Empty),
             c => {
                 stream.writeln_stderr(format!("unknown command: {}", c));
@@ -385,6 +581,11 @@ impl Debugger {
             Command::Dump(ref device) => match &device[..] {
                 "memory" | "m" => stream.writeln_stdout(format!("{:?}", cpu.peripherals.memory)),
                 "video" | "v" => stream.writeln_stdout(format!("{:?}", cpu.peripherals.screen)),
+                "screen" | "scr" => {
+                    for line in cpu.peripherals.screen.to_ascii().lines() {
+                        stream.writeln_stdout(line);
+                    }
+                }
                 "input" | "i" => stream.writeln_stdout(format!("{:?}", cpu.peripherals.input)),
                 "registers" | "r" => stream.writeln_stdout(format!("{:?}", cpu.registers)),
                 "stack" | "s" => stream.writeln_stdout(format!("{:?}", cpu.stack)),
@@ -406,11 +607,19 @@ impl Debugger {
                     stream.writeln_stdout(format!("I = {:X}", cpu.registers.get_i_register()));
                 }
                 RegisterKind::Stack(reg_idx) => {
-                    stream.writeln_stdout(format!(
-                        "S{:X} = {:X}",
-                        reg_idx,
-                        cpu.stack.peek(reg_idx as usize)
-                    ));
+                    if (reg_idx as usize) < cpu.stack.get_capacity() {
+                        stream.writeln_stdout(format!(
+                            "S{:X} = {:X}",
+                            reg_idx,
+                            cpu.stack.peek(reg_idx as usize)
+                        ));
+                    } else {
+                        stream.writeln_stderr(format!(
+                            "S{:X} is out of range (stack depth: {})",
+                            reg_idx,
+                            cpu.stack.get_capacity()
+                        ));
+                    }
                 }
                 RegisterKind::StackPointer => {
                     stream.writeln_stdout(format!("SP = {:X}", cpu.stack.get_pointer()));
@@ -445,21 +654,131 @@ impl Debugger {
                     cpu.peripherals.memory.read_data_at_offset(addr, count)
                 ));
             }
+            Command::DumpMemory(addr, count, path) => {
+                let data = cpu.peripherals.memory.read_data_at_offset(addr, count);
+                match std::fs::write(&path, &data) {
+                    Ok(()) => stream.writeln_stdout(format!(
+                        "{} bytes from 0x{:04X} written to {}",
+                        data.len(),
+                        addr,
+                        path
+                    )),
+                    Err(e) => stream.writeln_stderr(format!("error: could not dump memory: {}", e)),
+                }
+            }
+            Command::Find(pattern) => {
+                let addresses = cpu.peripherals.memory.find_pattern(&pattern);
+                if addresses.is_empty() {
+                    stream.writeln_stdout("pattern not found");
+                } else {
+                    for addr in addresses {
+                        stream.writeln_stdout(format!("pattern found at 0x{:04X}", addr));
+                    }
+                }
+            }
             Command::Step => ctx.is_stepping = true,
+            Command::Next => {
+                let opcode = cpu.peripherals.memory.read_opcode_at_address(ctx.address);
+                if let OpCode::CALL(_) = get_opcode_enum(opcode) {
+                    // Continue until the return address.
+                    ctx.temporary_breakpoint = Some(ctx.address + 2);
+                    ctx.is_continuing = true;
+                } else {
+                    ctx.is_stepping = true;
+                }
+            }
             Command::Continue => ctx.is_continuing = true,
-            Command::Where => self.show_line(cpu, ctx, stream, ctx.address),
+            Command::Where => {
+                self.show_line(cpu, ctx, stream, ctx.address);
+            }
             Command::List(sz) => self.show_line_context(cpu, ctx, stream, sz, sz),
             Command::LongList => self.show_source(cpu, ctx, stream),
+            Command::DisassembleLive(addr, count) => {
+                let memory_end = cpu.peripherals.memory.get_data().len();
+                let mut addr = addr as usize;
+
+                for _ in 0..count {
+                    if addr + 1 >= memory_end {
+                        break;
+                    }
+                    addr += self.show_line(cpu, ctx, stream, addr as C8Addr) as usize;
+                }
+            }
+            Command::Sprite(addr, rows, wide) => {
+                let memory = cpu.peripherals.memory.get_data();
+                let start = addr as usize;
+                let row_size = if wide { 2 } else { 1 };
+                let end = start
+                    .saturating_add((rows as usize).saturating_mul(row_size))
+                    .min(memory.len());
+
+                if start >= memory.len() {
+                    stream.writeln_stderr(format!(
+                        "error: address 0x{:04X} is past memory end (0x{:04X})",
+                        addr,
+                        memory.len() - 1
+                    ));
+                } else {
+                    for row in memory[start..end].chunks_exact(row_size) {
+                        let line: String = row
+                            .iter()
+                            .flat_map(|byte| (0..8).rev().map(move |shift| (byte >> shift) & 1))
+                            .map(|bit| if bit == 1 { '#' } else { '.' })
+                            .collect();
+                        stream.writeln_stdout(line);
+                    }
+                }
+            }
             Command::Help => self.show_help(stream),
             Command::AddBreakpoint(addr) => {
                 ctx.register_breakpoint(addr);
                 stream.writeln_stdout(format!("breakpoint added to address 0x{:04X}", addr));
+                self.sync_breakpoints_file(ctx, stream);
             }
             Command::RemoveBreakpoint(addr) => {
                 ctx.unregister_breakpoint(addr);
                 stream.writeln_stdout(format!("breakpoint removed from address 0x{:04X}", addr));
+                self.sync_breakpoints_file(ctx, stream);
+            }
+            Command::ListBreakpoints => {
+                stream.writeln_stdout("breakpoints:");
+                let addresses = ctx.breakpoints.addresses();
+                if addresses.is_empty() && ctx.opcode_breakpoints.is_empty() {
+                    stream.writeln_stdout("  none");
+                }
+
+                for addr in addresses {
+                    let opcode = get_opcode_enum_from_array(
+                        cpu.peripherals.memory.get_data(),
+                        addr as usize,
+                    );
+                    let (asm, txt) = get_opcode_str(&opcode);
+                    stream.writeln_stdout(format!("  - {:04X}: {:20} ; {}", addr, asm, txt));
+                }
+                for mnemonic in &ctx.opcode_breakpoints {
+                    stream.writeln_stdout(format!("  - opcode {}", mnemonic));
+                }
+            }
+            Command::BreakOnOpcode(mnemonic) => {
+                ctx.register_opcode_breakpoint(&mnemonic);
+                stream.writeln_stdout(format!("breakpoint added on opcode {}", mnemonic));
+            }
+            Command::SaveState(path) => match SaveState::save_from_cpu(cpu).save_to_path(&path) {
+                Ok(()) => stream.writeln_stdout(format!("state saved to {}", path)),
+                Err(e) => stream.writeln_stderr(format!("error: could not save state: {}", e)),
+            },
+            Command::LoadState(path) => match SaveState::read_from_file(&path) {
+                Some(state) => {
+                    // Applied on next debugger step, which owns the emulator.
+                    ctx.pending_savestate = Some(state);
+                    stream.writeln_stdout(format!("state loaded from {}", path));
+                }
+                None => stream.writeln_stderr(format!("error: missing state file {}", path)),
+            },
+            Command::Diff(enabled) => {
+                ctx.diff_enabled = enabled;
+                ctx.diff_snapshot = None;
             }
-            Command::ListBreakpoints => stream.writeln_stdout(format!("{:?}", ctx.breakpoints)),
             Command::Quit => ctx.should_quit = true,
             Command::Empty => (),
         }
@@ -474,14 +793,15 @@ impl Debugger {
         ctx: &DebuggerContext,
         stream: &mut DebuggerStream,
         addr: C8Addr,
-    ) {
-        let opcode = cpu.peripherals.memory.read_opcode_at_address(addr);
-        let opcode_enum = get_opcode_enum(opcode);
+    ) -> C8Addr {
+        let opcode_enum =
+            get_opcode_enum_from_array(cpu.peripherals.memory.get_data(), addr as usize);
         let (asm, txt) = get_opcode_str(&opcode_enum);
 
         let cursor = if ctx.address == addr { "-->" } else { "" };
 
         stream.writeln_stdout(format!("{:04X}| {:3} {:20} ; {}", addr, cursor, asm, txt));
+        get_opcode_size(&opcode_enum)
     }
 
     fn show_line_context(
@@ -494,19 +814,74 @@ impl Debugger {
     ) {
         let base_addr = ctx.address;
 
-        // Limit = INITIAL_MEMORY_POINTER
-        let min_limit = std::cmp::max(base_addr - prev_size * 2, INITIAL_MEMORY_POINTER);
+        // Limit = entry point
+        let min_limit = std::cmp::max(
+            base_addr.saturating_sub(prev_size * 2),
+            cpu.peripherals.memory.get_initial_pointer(),
+        );
         let max_limit = base_addr + next_size * 2;
 
-        for addr in (min_limit..=max_limit).step_by(2) {
-            self.show_line(cpu, ctx, stream, addr);
-        }
+        self.show_lines(cpu, ctx, stream, min_limit, max_limit);
     }
 
     fn show_source(&self, cpu: &CPU, ctx: &DebuggerContext, stream: &mut DebuggerStream) {
         let code_end_pointer = cpu.peripherals.memory.get_end_pointer();
-        for addr in (INITIAL_MEMORY_POINTER..=code_end_pointer).step_by(2) {
-            self.show_line(cpu, ctx, stream, addr);
+        let initial_pointer = cpu.peripherals.memory.get_initial_pointer();
+        self.show_lines(cpu, ctx, stream, initial_pointer, code_end_pointer);
+    }
+
+    fn show_lines(
+        &self,
+        cpu: &CPU,
+        ctx: &DebuggerContext,
+        stream: &mut DebuggerStream,
+        start: C8Addr,
+        end: C8Addr,
+    ) {
+        // Step by instruction size, so long instructions stay on one line.
+        let mut addr = Some(start);
+        while let Some(current) = addr.filter(|a| *a <= end) {
+            let size = self.show_line(cpu, ctx, stream, current);
+            addr = current.checked_add(size);
+        }
+    }
+
+    fn show_diff(&self, cpu: &CPU, snapshot: &StepSnapshot, stream: &mut DebuggerStream) {
+        let prev_regs = snapshot.registers.get_registers();
+        let regs = cpu.registers.get_registers();
+        for (idx, (prev, curr)) in prev_regs.iter().zip(regs.iter()).enumerate() {
+            if prev != curr {
+                stream.writeln_stdout(format!("  V{:X}: {:02X} -> {:02X}", idx, prev, curr));
+            }
+        }
+
+        let prev_i = snapshot.registers.get_i_register();
+        let i = cpu.registers.get_i_register();
+        if prev_i != i {
+            stream.writeln_stdout(format!("  I: {:04X} -> {:04X}", prev_i, i));
+        }
+
+        let prev_pc = snapshot.memory.get_pointer();
+        let pc = cpu.peripherals.memory.get_pointer();
+        stream.writeln_stdout(format!(
+            "  PC: {:04X} -> {:04X} ({:+})",
+            prev_pc,
+            pc,
+            i32::from(pc) - i32::from(prev_pc)
+        ));
+
+        let prev_mem = snapshot.memory.get_data();
+        let mem = cpu.peripherals.memory.get_data();
+        for (addr, (prev, curr)) in prev_mem.iter().zip(mem.iter()).enumerate() {
+            if prev != curr {
+                stream.writeln_stdout(format!("  {:04X}: {:02X} -> {:02X}", addr, prev, curr));
+            }
+        }
+    }
+
+    fn sync_breakpoints_file(&self, ctx: &DebuggerContext, stream: &mut DebuggerStream) {
+        if let Err(e) = ctx.sync_breakpoints_file() {
+            stream.writeln_stderr(format!("error: could not save breakpoints: {}", e));
         }
     }
 
@@ -517,13 +892,486 @@ impl Debugger {
         stream.writeln_stdout("  where|w         - show current line");
         stream.writeln_stdout("  list|l          - show current line with context");
         stream.writeln_stdout("  longlist|ll     - show complete source");
-        stream.writeln_stdout("  step|s|next|n   - step");
+        stream.writeln_stdout("  disasm-live|dl  - disassemble live memory range");
+        stream.writeln_stdout("  sprite|sp       - preview 8x<rows> sprite at address");
+        stream.writeln_stdout("  sprite16|sp16   - preview 16x<rows> sprite at address");
+        stream.writeln_stdout("  step|s          - step into");
+        stream.writeln_stdout("  next|n          - step over subroutine calls");
         stream.writeln_stdout("  add-bp|b        - add breakpoint at address");
         stream.writeln_stdout("  rem-bp|rb       - remove breakpoint at address");
         stream.writeln_stdout("  list-bp|lb      - list breakpoints");
+        stream.writeln_stdout("  break-op|bo     - break on opcode mnemonic");
+        stream.writeln_stdout("  save-state|sst  - save state to path");
+        stream.writeln_stdout("  load-state|lst  - load state from path");
         stream.writeln_stdout("  read-reg|rreg   - read register");
         stream.writeln_stdout("  read-mem|rmem   - read memory at offset");
+        stream.writeln_stdout("  dump-mem|dmem   - dump memory region to file");
+        stream.writeln_stdout("  find|f          - find byte pattern in memory");
+        stream.writeln_stdout("  diff on|off     - show changes after each step");
         stream.writeln_stdout("  quit|q          - quit program");
         stream.writeln_stdout("  help|h          - show this help");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::assembler::Assembler, peripherals::memory::INITIAL_MEMORY_POINTER};
+
+    fn prepare_emulator() -> Emulator {
+        prepare_emulator_from_source("CALL 0206\nLD V0, 01\nJP 0204\nLD V1, 02\nRET")
+    }
+
+    fn prepare_emulator_from_source(source: &str) -> Emulator {
+        let cartridge = Assembler::from_string(source).assemble_cartridge().unwrap();
+
+        let mut emulator = Emulator::new();
+        emulator.load_game(&cartridge).unwrap();
+        emulator
+    }
+
+    fn prepare_context() -> DebuggerContext {
+        let mut ctx = DebuggerContext::new();
+        ctx.set_manual();
+        ctx.set_address(INITIAL_MEMORY_POINTER);
+        ctx
+    }
+
+    fn run_until_paused(
+        debugger: &Debugger,
+        emulator: &mut Emulator,
+        ctx: &mut DebuggerContext,
+        stream: &mut DebuggerStream,
+    ) {
+        let mut emulator_ctx = EmulatorContext::new();
+
+        for _ in 0..100 {
+            debugger.step(emulator, &mut emulator_ctx, ctx, stream);
+            if ctx.is_paused() && !ctx.is_stepping {
+                return;
+            }
+        }
+
+        panic!("debugger did not pause");
+    }
+
+    #[test]
+    fn test_dump_screen() {
+        let debugger = Debugger::new();
+        let mut stream = DebuggerStream::new();
+        let mut ctx = prepare_context();
+        let mut emulator = prepare_emulator();
+        emulator
+            .cpu
+            .peripherals
+            .screen
+            .draw_sprite(0, 1, &[0b1100_0000, 0b0100_0000]);
+
+        let command = debugger.read_command("dump scr", &mut stream).unwrap();
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, command);
+
+        let lines: Vec<_> = stream.get_lines().iter().map(|l| &l.content).collect();
+        assert_eq!(lines.len(), 32);
+        assert_eq!(&lines[0][..4], "....");
+        assert_eq!(&lines[1][..4], "##..");
+        assert_eq!(&lines[2][..4], ".#..");
+    }
+
+    #[test]
+    fn test_save_and_load_state() {
+        let path = std::env::temp_dir().join(format!("chip8-debugger-{}.sav", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let debugger = Debugger::new();
+        let mut stream = DebuggerStream::new();
+        let mut emulator = prepare_emulator();
+        let mut ctx = prepare_context();
+
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, Command::Step);
+        run_until_paused(&debugger, &mut emulator, &mut ctx, &mut stream);
+
+        let command = debugger
+            .read_command(&format!("save-state {}", path), &mut stream)
+            .unwrap();
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, command);
+
+        for _ in 0..2 {
+            debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, Command::Step);
+            run_until_paused(&debugger, &mut emulator, &mut ctx, &mut stream);
+        }
+        assert_eq!(emulator.cpu.peripherals.memory.get_pointer(), 0x202);
+        assert_eq!(emulator.cpu.registers.get_register(1), 0x02);
+
+        let command = debugger
+            .read_command(&format!("load-state {}", path), &mut stream)
+            .unwrap();
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, command);
+        run_until_paused(&debugger, &mut emulator, &mut ctx, &mut stream);
+
+        assert_eq!(emulator.cpu.peripherals.memory.get_pointer(), 0x206);
+        assert_eq!(emulator.cpu.registers.get_register(1), 0x00);
+        assert_eq!(ctx.address, 0x206);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_pause_stops_sound() {
+        use crate::drivers::AudioInterface;
+        use std::sync::{Arc, Mutex};
+
+        struct RecordingAudioDriver(Arc<Mutex<Vec<&'static str>>>);
+
+        impl AudioInterface for RecordingAudioDriver {
+            fn play_beep(&mut self) {
+                self.0.lock().unwrap().push("play");
+            }
+
+            fn stop_beep(&mut self) {
+                self.0.lock().unwrap().push("stop");
+            }
+        }
+
+        let debugger = Debugger::new();
+        let mut stream = DebuggerStream::new();
+        let mut emulator = prepare_emulator_from_source("LD V0, 01\nLD V1, 02\nJP 0204");
+        let mut ctx = prepare_context();
+
+        let events = Arc::new(Mutex::new(vec![]));
+        emulator
+            .cpu
+            .drivers
+            .set_audio_driver(Box::new(RecordingAudioDriver(events.clone())));
+        emulator.cpu.sound_timer.reset(10);
+
+        ctx.register_breakpoint(0x0202);
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, Command::Continue);
+        run_until_paused(&debugger, &mut emulator, &mut ctx, &mut stream);
+        assert_eq!(events.lock().unwrap().last(), Some(&"stop"));
+
+        // Resuming with an expired sound timer stays silent.
+        events.lock().unwrap().clear();
+        emulator.cpu.sound_timer.reset(0);
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, Command::Continue);
+        let mut emulator_ctx = EmulatorContext::new();
+        for _ in 0..100 {
+            debugger.step(&mut emulator, &mut emulator_ctx, &mut ctx, &mut stream);
+        }
+        assert!(events.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_step_waits_for_input() {
+        let debugger = Debugger::new();
+        let mut stream = DebuggerStream::new();
+        let mut emulator = prepare_emulator_from_source("LD V0, K\nLD V1, 01\nJP 0204");
+        let mut emulator_ctx = EmulatorContext::new();
+        let mut ctx = prepare_context();
+
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, Command::Step);
+        debugger.step(&mut emulator, &mut emulator_ctx, &mut ctx, &mut stream);
+        assert!(!ctx.is_stepping);
+
+        // Step on a locked input: the step completes once a key is pressed.
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, Command::Step);
+        for _ in 0..3 {
+            let state = debugger.step(&mut emulator, &mut emulator_ctx, &mut ctx, &mut stream);
+            assert!(matches!(state, EmulationState::WaitForInput));
+            assert!(ctx.is_stepping);
+        }
+
+        emulator.cpu.peripherals.input.press(0x7);
+        debugger.step(&mut emulator, &mut emulator_ctx, &mut ctx, &mut stream);
+        assert!(!ctx.is_stepping);
+        assert_eq!(emulator.cpu.registers.get_register(0), 0x7);
+        assert_eq!(emulator.cpu.registers.get_register(1), 0x1);
+    }
+
+    #[test]
+    fn test_break_on_opcode() {
+        let debugger = Debugger::new();
+        let mut stream = DebuggerStream::new();
+        let mut emulator =
+            prepare_emulator_from_source("LD V0, 01\nLD V1, 02\nCALL 0208\nJP 0206\nRET");
+        let mut ctx = prepare_context();
+
+        let command = debugger.read_command("break-op call", &mut stream).unwrap();
+        assert_eq!(command, Command::BreakOnOpcode("CALL".into()));
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, command);
+
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, Command::Continue);
+        run_until_paused(&debugger, &mut emulator, &mut ctx, &mut stream);
+        assert_eq!(emulator.cpu.peripherals.memory.get_pointer(), 0x204);
+    }
+
+    #[test]
+    fn test_list_breakpoints() {
+        let debugger = Debugger::new();
+        let mut stream = DebuggerStream::new();
+        let mut ctx = prepare_context();
+        let emulator = prepare_emulator();
+
+        debugger.handle_command(
+            &emulator.cpu,
+            &mut ctx,
+            &mut stream,
+            Command::ListBreakpoints,
+        );
+        assert_eq!(stream.get_lines()[1].content, "  none");
+
+        let mut stream = DebuggerStream::new();
+        ctx.register_breakpoint(0x0202);
+        ctx.register_breakpoint(0x0206);
+        let command = debugger.read_command("list-bp", &mut stream).unwrap();
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, command);
+
+        let lines: Vec<_> = stream
+            .get_lines()
+            .iter()
+            .map(|l| l.content.clone())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "breakpoints:");
+        assert!(lines[1].starts_with("  - 0202: LD V0, 01"));
+        assert!(lines[2].starts_with("  - 0206: LD V1, 02"));
+    }
+
+    #[test]
+    fn test_dump_memory() {
+        let debugger = Debugger::new();
+        let mut stream = DebuggerStream::new();
+        let mut ctx = prepare_context();
+        let mut emulator = prepare_emulator();
+        emulator
+            .cpu
+            .peripherals
+            .memory
+            .write_data_at_offset(0x0300, &[0xF0, 0x90, 0x90, 0x90, 0xF0])
+            .unwrap();
+
+        let path = std::env::temp_dir().join(format!("chip8-{}.bin", std::process::id()));
+        let path_str = path.to_string_lossy().to_string();
+        let command = debugger
+            .read_command(&format!("dump-mem 0300 5 {}", path_str), &mut stream)
+            .unwrap();
+        assert_eq!(command, Command::DumpMemory(0x0300, 5, path_str));
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, command);
+
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            *emulator
+                .cpu
+                .peripherals
+                .memory
+                .read_data_at_offset(0x0300, 5)
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(debugger.read_command("dump-mem 0300 5", &mut stream), None);
+        assert_eq!(
+            debugger.read_command("dump-mem 0300 x out.bin", &mut stream),
+            None
+        );
+    }
+
+    #[test]
+    fn test_find() {
+        let debugger = Debugger::new();
+        let mut stream = DebuggerStream::new();
+        let mut ctx = prepare_context();
+        let mut emulator = prepare_emulator();
+        emulator
+            .cpu
+            .peripherals
+            .memory
+            .write_data_at_offset(0x0ABC, &[0xDE, 0xAD, 0xBE, 0xEF])
+            .unwrap();
+
+        let command = debugger.read_command("find DE AD BE", &mut stream).unwrap();
+        assert_eq!(command, Command::Find(vec![0xDE, 0xAD, 0xBE]));
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, command);
+        assert_eq!(stream.get_lines()[0].content, "pattern found at 0x0ABC");
+
+        let command = debugger.read_command("find EF DE", &mut stream).unwrap();
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, command);
+        assert_eq!(stream.get_lines()[1].content, "pattern not found");
+
+        assert_eq!(debugger.read_command("find ZZ", &mut stream), None);
+    }
+
+    #[test]
+    fn test_disasm_live() {
+        let debugger = Debugger::new();
+        let mut stream = DebuggerStream::new();
+        let mut ctx = prepare_context();
+        let mut emulator = prepare_emulator();
+
+        // Patch `LD V0, 01` at runtime.
+        emulator
+            .cpu
+            .peripherals
+            .memory
+            .write_data_at_offset(0x0204, &[0x6A, 0x42])
+            .unwrap();
+
+        let command = debugger
+            .read_command("disasm-live 0202 2", &mut stream)
+            .unwrap();
+        assert_eq!(command, Command::DisassembleLive(0x0202, 2));
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, command);
+
+        let lines = stream.get_lines();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].content.starts_with("0202|"));
+        assert!(lines[1].content.starts_with("0204|"));
+        assert!(lines[1].content.contains("LD VA, 42"));
+
+        assert_eq!(debugger.read_command("disasm-live 0202", &mut stream), None);
+        assert_eq!(debugger.read_command("disasm-live ZZ 2", &mut stream), None);
+
+        // Stops at the end of memory.
+        let mut stream = DebuggerStream::new();
+        debugger.handle_command(
+            &emulator.cpu,
+            &mut ctx,
+            &mut stream,
+            Command::DisassembleLive(0x0FFC, 10),
+        );
+        assert_eq!(stream.get_lines().len(), 2);
+
+        // Long instructions take one line, with their immediate word.
+        emulator
+            .cpu
+            .peripherals
+            .memory
+            .write_data_at_offset(0x0202, &[0xF0, 0x00, 0xAB, 0xCD])
+            .unwrap();
+        let mut stream = DebuggerStream::new();
+        debugger.handle_command(
+            &emulator.cpu,
+            &mut ctx,
+            &mut stream,
+            Command::DisassembleLive(0x0202, 2),
+        );
+
+        let lines = stream.get_lines();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].content.starts_with("0202|"));
+        assert!(lines[0].content.contains("LDI.L ABCD"));
+        assert!(lines[1].content.starts_with("0206|"));
+    }
+
+    #[test]
+    fn test_sprite() {
+        let debugger = Debugger::new();
+        let mut stream = DebuggerStream::new();
+        let mut ctx = prepare_context();
+        let mut emulator = prepare_emulator();
+
+        // Digit `0`.
+        emulator
+            .cpu
+            .peripherals
+            .memory
+            .write_data_at_offset(0x0300, &[0xF0, 0x90, 0x90, 0x90, 0xF0])
+            .unwrap();
+
+        let command = debugger.read_command("sprite 0300 5", &mut stream).unwrap();
+        assert_eq!(command, Command::Sprite(0x0300, 5, false));
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, command);
+
+        let lines: Vec<_> = stream
+            .get_lines()
+            .iter()
+            .map(|l| l.content.clone())
+            .collect();
+        assert_eq!(
+            lines,
+            vec!["####....", "#..#....", "#..#....", "#..#....", "####...."]
+        );
+
+        // Super sprite rows are two bytes wide.
+        let mut stream = DebuggerStream::new();
+        let command = debugger.read_command("sp16 0300 2", &mut stream).unwrap();
+        assert_eq!(command, Command::Sprite(0x0300, 2, true));
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, command);
+        let lines: Vec<_> = stream
+            .get_lines()
+            .iter()
+            .map(|l| l.content.clone())
+            .collect();
+        assert_eq!(lines, vec!["####....#..#....", "#..#....#..#...."]);
+
+        // Clamped to memory end.
+        let mut stream = DebuggerStream::new();
+        debugger.handle_command(
+            &emulator.cpu,
+            &mut ctx,
+            &mut stream,
+            Command::Sprite(0x0FFE, 0xFFFF, true),
+        );
+        assert_eq!(stream.get_lines().len(), 1);
+
+        let mut stream = DebuggerStream::new();
+        debugger.handle_command(
+            &emulator.cpu,
+            &mut ctx,
+            &mut stream,
+            Command::Sprite(0x1000, 1, false),
+        );
+        let lines = stream.get_lines();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].content.starts_with("error: address 0x1000"));
+
+        assert_eq!(debugger.read_command("sprite 0300", &mut stream), None);
+        assert_eq!(debugger.read_command("sprite 0300 x", &mut stream), None);
+    }
+
+    #[test]
+    fn test_diff() {
+        let debugger = Debugger::new();
+        let mut stream = DebuggerStream::new();
+        let mut ctx = prepare_context();
+        let mut emulator = prepare_emulator_from_source("LD V0, 05\nJP 0202");
+
+        let command = debugger.read_command("diff on", &mut stream).unwrap();
+        assert_eq!(command, Command::Diff(true));
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, command);
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, Command::Step);
+        run_until_paused(&debugger, &mut emulator, &mut ctx, &mut stream);
+
+        let lines: Vec<_> = stream
+            .get_lines()
+            .iter()
+            .map(|l| l.content.clone())
+            .collect();
+        assert_eq!(lines, vec!["  V0: 00 -> 05", "  PC: 0200 -> 0202 (+2)"]);
+
+        assert_eq!(debugger.read_command("diff maybe", &mut stream), None);
+    }
+
+    #[test]
+    fn test_step_and_next() {
+        let debugger = Debugger::new();
+        let mut stream = DebuggerStream::new();
+
+        assert_eq!(debugger.read_command("s", &mut stream), Some(Command::Step));
+        assert_eq!(debugger.read_command("n", &mut stream), Some(Command::Next));
+
+        // Step into the subroutine.
+        let mut emulator = prepare_emulator();
+        let mut ctx = prepare_context();
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, Command::Step);
+        run_until_paused(&debugger, &mut emulator, &mut ctx, &mut stream);
+        assert_eq!(emulator.cpu.peripherals.memory.get_pointer(), 0x206);
+
+        // Step over the subroutine.
+        let mut emulator = prepare_emulator();
+        let mut ctx = prepare_context();
+        debugger.handle_command(&emulator.cpu, &mut ctx, &mut stream, Command::Next);
+        run_until_paused(&debugger, &mut emulator, &mut ctx, &mut stream);
+        assert_eq!(emulator.cpu.peripherals.memory.get_pointer(), 0x202);
+        assert_eq!(emulator.cpu.registers.get_register(1), 0x02);
+        assert_eq!(ctx.temporary_breakpoint, None);
+    }
+}
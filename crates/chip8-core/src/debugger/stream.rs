@@ -2,7 +2,9 @@
 
 /// Debugger stream line.
 pub struct DebuggerStreamLine {
+    /// Is error.
     pub error: bool,
+    /// Content.
     pub content: String,
 }
 
@@ -21,14 +21,18 @@ pub const WINDOW_TITLE: &str = "CHIP-8 Emulator GUI";
 /// Window interface.
 pub trait WindowInterface {
     /// Run emulator.
+    ///
+    /// Returns the emulator on exit, to inspect its final state.
     fn run_emulator(
         &mut self,
         emulator: Emulator,
         emulator_ctx: EmulatorContext,
         cartridge: Cartridge,
-    ) -> CResult;
+    ) -> CResult<Emulator>;
 
     /// Run debugger.
+    ///
+    /// Returns the emulator on exit, to inspect its final state.
     fn run_debugger(
         &mut self,
         debugger: Debugger,
@@ -36,7 +40,7 @@ pub trait WindowInterface {
         emulator: Emulator,
         emulator_ctx: EmulatorContext,
         cartridge: Cartridge,
-    ) -> CResult;
+    ) -> CResult<Emulator>;
 }
 
 /// Input interface.
@@ -62,9 +66,14 @@ pub trait RenderInterface {
 }
 
 /// Audio interface.
-pub trait AudioInterface {
-    /// Play beep.
+pub trait AudioInterface: Send {
+    /// Play beep, until `stop_beep` is called.
+    ///
+    /// Called once per sustained beep, not on each timer decrement.
     fn play_beep(&mut self);
+
+    /// Stop beep.
+    fn stop_beep(&mut self);
 }
 
 /// Drivers.
@@ -72,6 +81,8 @@ pub trait AudioInterface {
 pub struct Drivers {
     /// Audio driver
     pub audio: Option<Box<dyn AudioInterface>>,
+    /// Audio disabled: audio drivers are ignored.
+    pub audio_disabled: bool,
 }
 
 impl Drivers {
@@ -81,7 +92,33 @@ impl Drivers {
     }
 
     /// Set audio driver.
+    ///
+    /// Ignored when audio is disabled.
     pub fn set_audio_driver(&mut self, audio_driver: Box<dyn AudioInterface>) {
-        self.audio = Some(audio_driver);
+        if !self.audio_disabled {
+            self.audio = Some(audio_driver);
+        }
+    }
+
+    /// Disable audio.
+    ///
+    /// Removes the current audio driver, and ignores the next ones:
+    /// beeps become no-ops.
+    pub fn disable_audio(&mut self) {
+        self.audio = None;
+        self.audio_disabled = true;
+    }
+
+    /// Check if audio is enabled.
+    ///
+    /// Window drivers should not open an audio device when disabled.
+    ///
+    /// # Returns
+    ///
+    /// * `true` if enabled.
+    /// * `false` if not.
+    ///
+    pub fn is_audio_enabled(&self) -> bool {
+        !self.audio_disabled
     }
 }
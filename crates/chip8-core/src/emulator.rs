@@ -1,31 +1,284 @@
 //! CHIP-8 emulator.
 
 use std::{
-    fs::{File, OpenOptions},
+    collections::{HashSet, VecDeque},
+    fs::{self, File, OpenOptions},
     io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
 };
 
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use super::{
     core::{
-        cpu::CPU,
-        opcodes,
-        savestate::{MissingSaveState, SaveState},
+        cpu::{ExitReason, CPU},
+        opcodes::{self, OpCode},
+        quirks::CompatMode,
+        rpl::RplFlags,
+        savestate::{MachineState, MissingSaveState, SaveState},
+        types::{C8Addr, C8Byte},
+    },
+    errors::{CResult, Chip8Error},
+    peripherals::{
+        cartridge::Cartridge,
+        memory::{MemoryFill, INITIAL_MEMORY_POINTER},
+        screen::{ScreenMode, ScreenScrollDirection},
     },
-    errors::CResult,
-    peripherals::cartridge::Cartridge,
     trace_exec,
 };
 
 const TIMER_FRAME_LIMIT: u64 = 16;
 const CPU_FRAME_LIMIT: u64 = 0;
+/// Nominal frame rate, for untimed frames.
+const FRAME_RATE: f64 = 60.0;
+/// Speed measurement window.
+const SPEED_WINDOW: Duration = Duration::from_secs(1);
+/// Worker threads used by `run_suite`.
+const SUITE_WORKERS: usize = 4;
+
+/// Step observer, called after each executed instruction.
+pub type StepObserver = Box<dyn FnMut(&CPU, &OpCode) + Send>;
 
 /// CHIP-8 emulator.
 #[derive(Default)]
 pub struct Emulator {
     /// CPU handle.
     pub cpu: CPU,
+
+    persistence_dir: Option<PathBuf>,
+    cartridge_hash: Option<String>,
+    coverage: HashSet<&'static str>,
+    on_unknown_opcode: Option<Box<dyn Fn(C8Addr, C8Addr) + Send>>,
+    step_observer: Option<StepObserver>,
+    recent_trace: VecDeque<String>,
+    tick_ctx: EmulatorContext,
+    speed: SpeedMeter,
+}
+
+/// Emulation statistics, e.g. for HUD overlays.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmulatorStats {
+    /// Effective instructions per second, measured on timed frames.
+    pub ips: f64,
+    /// Target instructions per second.
+    pub target_ips: f64,
+    /// Sound timer is active.
+    pub sound_active: bool,
+    /// Program counter.
+    pub pc: C8Addr,
+    /// Screen mode.
+    pub mode: ScreenMode,
+}
+
+/// Instructions per second meter, averaged over `SPEED_WINDOW`.
+#[derive(Default)]
+struct SpeedMeter {
+    instructions: usize,
+    elapsed: Duration,
+    ips: f64,
+    target_ips: f64,
+}
+
+impl SpeedMeter {
+    /// Record a timed frame.
+    fn record(&mut self, instructions: usize, delta: Duration, target_ips: f64) {
+        self.instructions += instructions;
+        self.elapsed += delta;
+        self.target_ips = target_ips;
+
+        if self.elapsed > Duration::new(0, 0) {
+            self.ips = self.instructions as f64 / self.elapsed.as_secs_f64();
+        }
+
+        if self.elapsed >= SPEED_WINDOW {
+            self.instructions = 0;
+            self.elapsed = Duration::new(0, 0);
+        }
+    }
+}
+
+/// Emulator builder.
+///
+/// Prepares an emulator and its context, without any window or driver.
+///
+/// ```
+/// use chip8_core::emulator::EmulatorBuilder;
+///
+/// // CLS, LD V0 00, LD F V0, DRW V0 V0 5, JP 0208.
+/// let rom = [0x00, 0xE0, 0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05, 0x12, 0x08];
+/// let (mut emulator, mut ctx) = EmulatorBuilder::new()
+///     .from_bytes(&rom)
+///     .unwrap()
+///     .build()
+///     .unwrap();
+///
+/// emulator.step_frame(&mut ctx);
+///
+/// let snapshot = emulator.snapshot();
+/// let lit = snapshot.screen_data.get_pixels().iter().filter(|p| **p != 0).count();
+/// assert_eq!(lit, 14);
+/// ```
+#[derive(Default)]
+pub struct EmulatorBuilder {
+    cartridge: Option<Cartridge>,
+    compat_mode: CompatMode,
+    persistence_dir: Option<PathBuf>,
+    warmup_instructions: usize,
+    entry_point: Option<C8Addr>,
+    initial_screen_mode: Option<ScreenMode>,
+}
+
+impl EmulatorBuilder {
+    /// Create new builder.
+    ///
+    /// # Returns
+    ///
+    /// * Emulator builder.
+    ///
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Load game from raw bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Cartridge data.
+    ///
+    /// # Returns
+    ///
+    /// * Emulator builder result, failing if data exceeds `CARTRIDGE_MAX_SIZE`.
+    ///
+    pub fn from_bytes(self, bytes: &[C8Byte]) -> Result<Self, Chip8Error> {
+        Ok(self.with_cartridge(Cartridge::from_bytes(bytes)?))
+    }
+
+    /// Load game from cartridge.
+    ///
+    /// # Arguments
+    ///
+    /// * `cartridge` - Cartridge.
+    ///
+    /// # Returns
+    ///
+    /// * Emulator builder.
+    ///
+    pub fn with_cartridge(mut self, cartridge: Cartridge) -> Self {
+        self.cartridge = Some(cartridge);
+        self
+    }
+
+    /// Set compatibility mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - Compatibility mode.
+    ///
+    /// # Returns
+    ///
+    /// * Emulator builder.
+    ///
+    pub fn with_compat_mode(mut self, mode: CompatMode) -> Self {
+        self.compat_mode = mode;
+        self
+    }
+
+    /// Set persistence directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - Directory path.
+    ///
+    /// # Returns
+    ///
+    /// * Emulator builder.
+    ///
+    pub fn with_persistence_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.persistence_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set warmup instructions.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Instruction count.
+    ///
+    /// # Returns
+    ///
+    /// * Emulator builder.
+    ///
+    pub fn with_warmup_instructions(mut self, count: usize) -> Self {
+        self.warmup_instructions = count;
+        self
+    }
+
+    /// Set entry point.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address.
+    ///
+    /// # Returns
+    ///
+    /// * Emulator builder.
+    ///
+    pub fn with_entry_point(mut self, addr: C8Addr) -> Self {
+        self.entry_point = Some(addr);
+        self
+    }
+
+    /// Set initial screen mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - Screen mode.
+    ///
+    /// # Returns
+    ///
+    /// * Emulator builder.
+    ///
+    pub fn with_initial_screen_mode(mut self, mode: ScreenMode) -> Self {
+        self.initial_screen_mode = Some(mode);
+        self
+    }
+
+    /// Build emulator.
+    ///
+    /// # Returns
+    ///
+    /// * Emulator and its context result, failing if the cartridge does not
+    ///   fit past the entry point.
+    ///
+    pub fn build(self) -> Result<(Emulator, EmulatorContext), Chip8Error> {
+        let mut emulator = Emulator::new();
+        let mut ctx = EmulatorContext::new();
+        ctx.set_warmup_instructions(self.warmup_instructions);
+
+        emulator.cpu.set_compat_mode(self.compat_mode);
+        if let Some(addr) = self.entry_point {
+            ctx.set_entry_point(addr);
+            emulator.cpu.peripherals.memory.set_initial_pointer(addr);
+        }
+        if let Some(dir) = &self.persistence_dir {
+            emulator.set_persistence_dir(dir);
+        }
+        if let Some(mode) = self.initial_screen_mode {
+            ctx.set_initial_screen_mode(mode);
+            emulator.apply_initial_screen_mode(&ctx);
+        }
+        if let Some(cartridge) = &self.cartridge {
+            emulator.load_game(cartridge)?;
+        }
+
+        Ok((emulator, ctx))
+    }
 }
 
 /// Emulation state.
@@ -33,6 +286,8 @@ pub struct Emulator {
 pub enum EmulationState {
     /// Quit.
     Quit,
+    /// Runtime error (see `CPU::exit_reason`).
+    Error,
     /// Reset.
     Reset,
     /// Normal.
@@ -52,12 +307,61 @@ pub enum TracefileHandle {
     Stdout,
 }
 
+/// Tracefile mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TracefileMode {
+    /// Truncate existing tracefile.
+    Truncate,
+    /// Append to existing tracefile.
+    Append,
+}
+
+impl Default for TracefileMode {
+    fn default() -> Self {
+        Self::Truncate
+    }
+}
+
+/// Action when waiting for input times out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputTimeoutAction {
+    /// Quit emulation.
+    Quit,
+    /// Act as if the key was pressed.
+    Key(C8Byte),
+}
+
+/// Action when the watchdog triggers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchdogAction {
+    /// Log a warning and keep running.
+    Warn,
+    /// Log a warning and quit emulation.
+    Quit,
+}
+
 /// Emulator context.
 #[derive(Default)]
 pub struct EmulatorContext {
     tracefile_handle: Option<TracefileHandle>,
+    tracefile_mode: TracefileMode,
+    track_coverage: bool,
     timer_frametime: u64,
     cpu_frametime: u64,
+    warmup_instructions: usize,
+    entry_point: Option<C8Addr>,
+    halt_on_self_jump: bool,
+    cpu_hz: Option<f64>,
+    cycle_accumulator: f64,
+    recent_trace_capacity: usize,
+    recent_trace_path: Option<PathBuf>,
+    memory_fill: MemoryFill,
+    randomize_screen: bool,
+    input_timeout: Option<(usize, InputTimeoutAction)>,
+    input_wait_steps: usize,
+    initial_screen_mode: Option<ScreenMode>,
+    watchdog: Option<(usize, WatchdogAction)>,
+    watchdog_steps: usize,
 }
 
 impl EmulatorContext {
@@ -71,6 +375,234 @@ impl EmulatorContext {
         Default::default()
     }
 
+    /// Set warmup instructions.
+    ///
+    /// Instructions to execute headless before the first rendered frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Instruction count.
+    ///
+    pub fn set_warmup_instructions(&mut self, count: usize) {
+        self.warmup_instructions = count;
+    }
+
+    /// Get warmup instructions.
+    ///
+    /// # Returns
+    ///
+    /// * Instruction count.
+    ///
+    pub fn get_warmup_instructions(&self) -> usize {
+        self.warmup_instructions
+    }
+
+    /// Set entry point.
+    ///
+    /// Address where execution starts, applied on reset.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address.
+    ///
+    pub fn set_entry_point(&mut self, addr: C8Addr) {
+        self.entry_point = Some(addr);
+    }
+
+    /// Get entry point.
+    ///
+    /// # Returns
+    ///
+    /// * Entry point (defaults to `INITIAL_MEMORY_POINTER`).
+    ///
+    pub fn get_entry_point(&self) -> C8Addr {
+        self.entry_point.unwrap_or(INITIAL_MEMORY_POINTER)
+    }
+
+    /// Halt on self jump.
+    ///
+    /// Quit when a `JP` targets its own address, as games usually end
+    /// this way.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value.
+    ///
+    pub fn set_halt_on_self_jump(&mut self, value: bool) {
+        self.halt_on_self_jump = value;
+    }
+
+    /// Set CPU frequency.
+    ///
+    /// Used by `step_frame_timed` to derive the instruction budget of a
+    /// frame from its duration.
+    ///
+    /// # Arguments
+    ///
+    /// * `hz` - Instructions per second.
+    ///
+    pub fn set_cpu_hz(&mut self, hz: f64) {
+        self.cpu_hz = Some(hz);
+        self.cycle_accumulator = 0.0;
+    }
+
+    /// Get CPU frequency.
+    ///
+    /// # Returns
+    ///
+    /// * Instructions per second, if set.
+    ///
+    pub fn get_cpu_hz(&self) -> Option<f64> {
+        self.cpu_hz
+    }
+
+    /// Keep the last executed instructions in memory.
+    ///
+    /// Cheaper than a tracefile: the trace is only written to `path` on
+    /// runtime error.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - Number of instructions to keep (0 to disable).
+    /// * `path` - Dump path.
+    ///
+    pub fn set_recent_trace<P: AsRef<Path>>(&mut self, capacity: usize, path: P) {
+        self.recent_trace_capacity = capacity;
+        self.recent_trace_path = Some(path.as_ref().to_path_buf());
+    }
+
+    /// Set power-on memory pattern.
+    ///
+    /// Fills memory above the loaded game on reset, as real hardware
+    /// powers on with indeterminate RAM.
+    ///
+    /// # Arguments
+    ///
+    /// * `fill` - Fill pattern.
+    ///
+    pub fn set_memory_fill(&mut self, fill: MemoryFill) {
+        self.memory_fill = fill;
+    }
+
+    /// Get power-on memory pattern.
+    ///
+    /// # Returns
+    ///
+    /// * Fill pattern.
+    ///
+    pub fn get_memory_fill(&self) -> MemoryFill {
+        self.memory_fill
+    }
+
+    /// Randomize screen on reset, instead of clearing it.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value.
+    ///
+    pub fn set_randomize_screen(&mut self, value: bool) {
+        self.randomize_screen = value;
+    }
+
+    /// Set initial screen mode.
+    ///
+    /// Applied on build and reset, for games starting in high resolution
+    /// without issuing `HIGH`.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - Screen mode.
+    ///
+    pub fn set_initial_screen_mode(&mut self, mode: ScreenMode) {
+        self.initial_screen_mode = Some(mode);
+    }
+
+    /// Get initial screen mode.
+    ///
+    /// # Returns
+    ///
+    /// * Screen mode, if set.
+    ///
+    pub fn get_initial_screen_mode(&self) -> Option<ScreenMode> {
+        self.initial_screen_mode.clone()
+    }
+
+    /// Set a timeout when waiting for input (`LD Vx, K`).
+    ///
+    /// # Arguments
+    ///
+    /// * `steps` - Steps to wait before timing out.
+    /// * `action` - Action on timeout.
+    ///
+    pub fn set_input_timeout(&mut self, steps: usize, action: InputTimeoutAction) {
+        self.input_timeout = Some((steps, action));
+    }
+
+    /// Get input timeout.
+    ///
+    /// # Returns
+    ///
+    /// * Steps and action, if any.
+    ///
+    pub fn get_input_timeout(&self) -> Option<(usize, InputTimeoutAction)> {
+        self.input_timeout
+    }
+
+    /// Set a watchdog on runaway loops.
+    ///
+    /// Triggers when more than `instructions` execute without drawing nor
+    /// counting down a running timer, e.g. on a `JP` to itself.
+    ///
+    /// # Arguments
+    ///
+    /// * `instructions` - Instruction threshold.
+    /// * `action` - Action on trigger.
+    ///
+    pub fn set_watchdog(&mut self, instructions: usize, action: WatchdogAction) {
+        self.watchdog = Some((instructions, action));
+        self.watchdog_steps = 0;
+    }
+
+    /// Get watchdog.
+    ///
+    /// # Returns
+    ///
+    /// * Instruction threshold and action, if any.
+    ///
+    pub fn get_watchdog(&self) -> Option<(usize, WatchdogAction)> {
+        self.watchdog
+    }
+
+    /// Set tracefile mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - Tracefile mode.
+    ///
+    pub fn set_tracefile_mode(&mut self, mode: TracefileMode) {
+        self.tracefile_mode = mode;
+    }
+
+    /// Get tracefile mode.
+    ///
+    /// # Returns
+    ///
+    /// * Tracefile mode.
+    ///
+    pub fn get_tracefile_mode(&self) -> TracefileMode {
+        self.tracefile_mode
+    }
+
+    /// Enable opcode coverage tracking.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value.
+    ///
+    pub fn set_coverage_tracking(&mut self, value: bool) {
+        self.track_coverage = value;
+    }
+
     /// Prepare tracefile
     ///
     /// # Arguments
@@ -81,17 +613,26 @@ impl EmulatorContext {
         self.tracefile_handle = match tracefile {
             Some(ref path) => {
                 if path == "-" {
-                    info!(message = "Tracefile defined.", output = "stdout");
+                    info!(
+                        target: "chip8::emulator",
+                        message = "Tracefile defined.",
+                        output = "stdout"
+                    );
                     Some(TracefileHandle::Stdout)
                 } else {
                     info!(
+                        target: "chip8::emulator",
                         message = "Tracefile defined.",
-                        output = %path
+                        output = %path,
+                        mode = ?self.tracefile_mode
                     );
+                    let append = self.tracefile_mode == TracefileMode::Append;
                     Some(TracefileHandle::File(
                         OpenOptions::new()
                             .write(true)
                             .create(true)
+                            .truncate(!append)
+                            .append(append)
                             .open(path)
                             .unwrap(),
                     ))
@@ -123,29 +664,133 @@ impl Emulator {
         self.cpu.tracefile(tracefile);
     }
 
-    /// Load game.
+    /// Set unknown opcode hook.
+    ///
+    /// Called with the pointer and the raw opcode each time an opcode which
+    /// does not decode to an instruction gets executed, including XO-CHIP
+    /// only instructions outside XO-CHIP mode.
     ///
     /// # Arguments
     ///
-    /// * `cartridge` - Cartridge.
+    /// * `hook` - Hook.
     ///
-    pub fn load_game(&mut self, cartridge: &Cartridge) {
-        self.cpu.load_font_in_memory();
-        self.cpu.load_cartridge_data(cartridge);
+    pub fn set_on_unknown_opcode<F: Fn(C8Addr, C8Addr) + Send + 'static>(&mut self, hook: F) {
+        self.on_unknown_opcode = Some(Box::new(hook));
     }
 
-    /// Save state.
+    /// Set step observer.
+    ///
+    /// Called with the CPU state and the opcode after each executed instruction.
     ///
     /// # Arguments
     ///
-    /// * `name` - Game name.
+    /// * `observer` - Observer.
     ///
-    pub fn save_state(&self, name: &str) {
-        let savestate = SaveState::save_from_cpu(&self.cpu);
-        let path = format!("{}.sav", name);
+    pub fn set_step_observer(&mut self, observer: StepObserver) {
+        self.step_observer = Some(observer);
+    }
+
+    /// Set persistence directory.
+    ///
+    /// RPL user flags are loaded from `{dir}/{hash}.rpl` when a game is loaded,
+    /// and saved back when the emulator is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Directory path.
+    ///
+    pub fn set_persistence_dir<P: AsRef<Path>>(&mut self, path: P) {
+        self.persistence_dir = Some(path.as_ref().to_owned());
+    }
+
+    /// Load game.
+    ///
+    /// # Arguments
+    ///
+    /// * `cartridge` - Cartridge.
+    ///
+    /// # Returns
+    ///
+    /// * Result, failing if the cartridge does not fit past the entry point.
+    ///
+    pub fn load_game(&mut self, cartridge: &Cartridge) -> Result<(), Chip8Error> {
+        self.cpu.load_font_in_memory();
+        self.cpu.load_cartridge_data(cartridge)?;
+
+        self.cartridge_hash = Some(cartridge.get_hash());
+        self.load_rpl_flags();
+        Ok(())
+    }
+
+    /// Apply initial screen mode from context, as `LOW`/`HIGH` would.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - Emulator context.
+    ///
+    fn apply_initial_screen_mode(&mut self, ctx: &EmulatorContext) {
+        if let Some(mode) = ctx.get_initial_screen_mode() {
+            self.cpu.speed_multiplicator = match mode {
+                ScreenMode::Standard => 8,
+                ScreenMode::Extended => 16,
+            };
+            self.cpu.schip_mode = true;
+            self.cpu.peripherals.screen.reload_screen_for_mode(mode);
+        }
+    }
+
+    fn get_rpl_flags_path(&self) -> Option<PathBuf> {
+        match (&self.persistence_dir, &self.cartridge_hash) {
+            (Some(dir), Some(hash)) => Some(dir.join(format!("{}.rpl", hash))),
+            _ => None,
+        }
+    }
+
+    fn load_rpl_flags(&mut self) {
+        if let Some(path) = self.get_rpl_flags_path() {
+            if let Ok(flags) = RplFlags::load_from_path(&path) {
+                self.cpu.rpl_flags = flags;
+
+                info!(
+                    target: "chip8::emulator",
+                    message = "RPL flags loaded.",
+                    path = %path.display(),
+                );
+            }
+        }
+    }
+
+    fn save_rpl_flags(&self) -> CResult {
+        if let Some(path) = self.get_rpl_flags_path() {
+            if let Some(dir) = &self.persistence_dir {
+                fs::create_dir_all(dir)?;
+            }
+
+            self.cpu.rpl_flags.save_to_path(&path)?;
+
+            info!(
+                target: "chip8::emulator",
+                message = "RPL flags saved.",
+                path = %path.display(),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Save state.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Game name.
+    ///
+    pub fn save_state(&self, name: &str) {
+        let savestate = SaveState::save_from_cpu(&self.cpu);
+        let path = format!("{}.sav", name);
         savestate.write_to_file(&path);
 
         info!(
+            target: "chip8::emulator",
             message = "Game state saved.",
             title = %name,
             path = %path,
@@ -168,6 +813,7 @@ impl Emulator {
         match savestate {
             None => {
                 error!(
+                    target: "chip8::emulator",
                     message = "Game state loading error.",
                     title = %name,
                     path = %filename,
@@ -177,6 +823,7 @@ impl Emulator {
             Some(ss) => {
                 self.cpu.load_savestate(ss);
                 info!(
+                    target: "chip8::emulator",
                     message = "Game state loaded.",
                     title = %name,
                     path = %filename,
@@ -193,19 +840,302 @@ impl Emulator {
     /// * `cartridge` - Cartridge.
     /// * `ctx` - Emulator context.
     ///
-    pub fn reset(&mut self, cartridge: &Cartridge, ctx: &mut EmulatorContext) {
+    /// # Returns
+    ///
+    /// * Result, failing if the cartridge does not fit past the entry point.
+    ///
+    pub fn reset(
+        &mut self,
+        cartridge: &Cartridge,
+        ctx: &mut EmulatorContext,
+    ) -> Result<(), Chip8Error> {
         // Reset CPU.
+        self.cpu
+            .peripherals
+            .memory
+            .set_initial_pointer(ctx.get_entry_point());
         self.cpu.reset();
 
         // Reload data.
         self.cpu.load_font_in_memory();
-        self.cpu.load_cartridge_data(cartridge);
+        self.cpu.load_cartridge_data(cartridge)?;
+
+        // Power-on state.
+        let rom_end = ctx.get_entry_point() as usize + cartridge.get_data().len();
+        self.cpu
+            .peripherals
+            .memory
+            .fill_from(rom_end, ctx.memory_fill);
+        self.apply_initial_screen_mode(ctx);
+        if ctx.randomize_screen {
+            self.cpu.peripherals.screen.randomize();
+        }
 
         // Reset vars.
         ctx.timer_frametime = 0;
         ctx.cpu_frametime = 0;
+        ctx.watchdog_steps = 0;
+        self.tick_ctx.timer_frametime = 0;
+        self.tick_ctx.cpu_frametime = 0;
+        self.speed = SpeedMeter::default();
+
+        info!(target: "chip8::emulator", message = "Emulator reset.");
+        Ok(())
+    }
+
+    /// Warm up emulation.
+    ///
+    /// Execute the context warmup instructions without rendering.
+    /// Stops early on quit or when waiting for input.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - Emulator context.
+    ///
+    /// # Returns
+    ///
+    /// * Emulation state.
+    ///
+    pub fn warmup(&mut self, ctx: &mut EmulatorContext) -> EmulationState {
+        let target = self.cpu.instruction_count + ctx.warmup_instructions;
+
+        while self.cpu.instruction_count < target {
+            match self.step(ctx) {
+                EmulationState::Normal => (),
+                state => return state,
+            }
+        }
 
-        info!(message = "Emulator reset.")
+        if ctx.warmup_instructions > 0 {
+            info!(
+                target: "chip8::emulator",
+                message = "Warmup done.",
+                instructions = ctx.warmup_instructions,
+            );
+        }
+
+        EmulationState::Normal
+    }
+
+    /// Step emulation for one frame.
+    ///
+    /// Execute `speed_multiplicator` steps, then present the screen.
+    /// Stops early on quit or when waiting for input.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - Emulator context.
+    ///
+    /// # Returns
+    ///
+    /// * Emulation state.
+    ///
+    pub fn step_frame(&mut self, ctx: &mut EmulatorContext) -> EmulationState {
+        let mut state = EmulationState::Normal;
+
+        for _ in 0..self.cpu.speed_multiplicator {
+            state = self.step(ctx);
+            if matches!(
+                state,
+                EmulationState::Quit | EmulationState::Error | EmulationState::WaitForInput
+            ) {
+                break;
+            }
+        }
+
+        self.cpu.peripherals.screen.present();
+        state
+    }
+
+    /// Run frames and capture the final screen.
+    ///
+    /// Frames use a fixed timestep (see `step_frame`) and a default context,
+    /// so captures are deterministic for games without input nor randomness.
+    ///
+    /// # Arguments
+    ///
+    /// * `frames` - Frame count, stopping early on quit or error.
+    ///
+    /// # Returns
+    ///
+    /// * Presented pixels, row by row.
+    ///
+    pub fn run_and_capture(&mut self, frames: usize) -> Vec<u8> {
+        let mut ctx = EmulatorContext::new();
+
+        for _ in 0..frames {
+            if let EmulationState::Quit | EmulationState::Error = self.step_frame(&mut ctx) {
+                break;
+            }
+        }
+
+        self.cpu.peripherals.screen.present();
+        self.cpu.peripherals.screen.snapshot().get_pixels().to_vec()
+    }
+
+    /// Step emulation for a frame of a given duration.
+    ///
+    /// Execute `cpu_hz * delta` steps, then present the screen. The fractional
+    /// part of the budget is carried to the next frame, so that uneven frame
+    /// durations do not drift.
+    /// Without CPU frequency, behaves like `step_frame`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - Emulator context.
+    /// * `delta` - Frame duration.
+    ///
+    /// # Returns
+    ///
+    /// * Emulation state.
+    ///
+    pub fn step_frame_timed(
+        &mut self,
+        ctx: &mut EmulatorContext,
+        delta: Duration,
+    ) -> EmulationState {
+        let start_count = self.cpu.instruction_count;
+        let target_ips = ctx
+            .cpu_hz
+            .unwrap_or(f64::from(self.cpu.speed_multiplicator) * FRAME_RATE);
+
+        let state = self.run_timed_frame(ctx, delta);
+
+        let executed = self.cpu.instruction_count - start_count;
+        self.speed.record(executed, delta, target_ips);
+        state
+    }
+
+    fn run_timed_frame(&mut self, ctx: &mut EmulatorContext, delta: Duration) -> EmulationState {
+        let hz = match ctx.cpu_hz {
+            Some(hz) => hz,
+            None => return self.step_frame(ctx),
+        };
+
+        ctx.cycle_accumulator += hz * delta.as_secs_f64();
+        let budget = ctx.cycle_accumulator.floor();
+        ctx.cycle_accumulator -= budget;
+
+        let mut state = EmulationState::Normal;
+        for _ in 0..budget as u64 {
+            state = self.step(ctx);
+            if matches!(
+                state,
+                EmulationState::Quit | EmulationState::Error | EmulationState::WaitForInput
+            ) {
+                break;
+            }
+        }
+
+        self.cpu.peripherals.screen.present();
+        state
+    }
+
+    /// Dump recently executed instructions, oldest first.
+    ///
+    /// Only filled when a recent trace is enabled in the context.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Output path.
+    ///
+    /// # Returns
+    ///
+    /// * Result.
+    ///
+    pub fn dump_recent_trace<P: AsRef<Path>>(&self, path: P) -> CResult {
+        let mut file = File::create(path.as_ref())?;
+        for line in &self.recent_trace {
+            writeln!(file, "{}", line)?;
+        }
+
+        Ok(())
+    }
+
+    /// Get opcode coverage.
+    ///
+    /// Only filled when coverage tracking is enabled in the context.
+    ///
+    /// # Returns
+    ///
+    /// * Names of the opcode variants executed.
+    ///
+    pub fn coverage(&self) -> HashSet<&'static str> {
+        self.coverage.clone()
+    }
+
+    /// Take a snapshot of the emulator state.
+    ///
+    /// # Returns
+    ///
+    /// * Save state.
+    ///
+    pub fn snapshot(&self) -> SaveState {
+        SaveState::save_from_cpu(&self.cpu)
+    }
+
+    /// Get emulation statistics.
+    ///
+    /// Speed is measured on `step_frame_timed` calls. Without CPU frequency,
+    /// the target is `speed_multiplicator` instructions per 60 Hz frame.
+    ///
+    /// # Returns
+    ///
+    /// * Emulator stats.
+    ///
+    pub fn stats(&self) -> EmulatorStats {
+        EmulatorStats {
+            ips: self.speed.ips,
+            target_ips: self.speed.target_ips,
+            sound_active: self.cpu.sound_timer.get_value() > 0,
+            pc: self.cpu.peripherals.memory.get_pointer(),
+            mode: self.cpu.peripherals.screen.get_mode(),
+        }
+    }
+
+    /// Render the presented frame to an RGBA buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `scale` - Size of a CHIP-8 pixel, in output pixels.
+    /// * `fg` - Color of lit pixels.
+    /// * `bg` - Color of unlit pixels.
+    ///
+    /// # Returns
+    ///
+    /// * Tightly-packed RGBA buffer, row by row.
+    ///
+    pub fn render_to_rgba(&self, scale: usize, fg: [u8; 4], bg: [u8; 4]) -> Vec<u8> {
+        let frame = self.cpu.peripherals.screen.snapshot();
+        let (width, height) = frame.get_dimensions();
+        let pixels = frame.get_pixels();
+        let mut output = Vec::with_capacity(width * scale * height * scale * 4);
+
+        for y in 0..height * scale {
+            for x in 0..width * scale {
+                let pixel = pixels[x / scale + (y / scale) * width];
+                output.extend_from_slice(if pixel == 0 { &bg } else { &fg });
+            }
+        }
+
+        output
+    }
+
+    /// Execute one instruction.
+    ///
+    /// Same as `step`, using an internal default context: timers are
+    /// decremented every `TIMER_FRAME_LIMIT` ticks, without any driver.
+    ///
+    /// # Returns
+    ///
+    /// * Emulation state.
+    ///
+    pub fn tick(&mut self) -> EmulationState {
+        let mut ctx = std::mem::take(&mut self.tick_ctx);
+        let state = self.step(&mut ctx);
+        self.tick_ctx = ctx;
+
+        state
     }
 
     /// Step emulation.
@@ -225,6 +1155,10 @@ impl Emulator {
             CPU_FRAME_LIMIT
         };
 
+        if self.cpu.quirks.latched_input {
+            self.cpu.latch_input();
+        }
+
         // Handle input lock.
         if self.cpu.peripherals.input.is_locked() {
             if self.cpu.peripherals.input.is_lock_key_set() {
@@ -236,14 +1170,37 @@ impl Emulator {
 
                 // Unlock.
                 self.cpu.peripherals.input.unlock();
+                ctx.input_wait_steps = 0;
             } else {
-                // Wait for key.
-                return EmulationState::WaitForInput;
+                match ctx.input_timeout {
+                    Some((steps, action)) if ctx.input_wait_steps >= steps => {
+                        info!(
+                            target: "chip8::emulator",
+                            message = "Input wait timed out.",
+                            action = ?action
+                        );
+                        ctx.input_wait_steps = 0;
+
+                        match action {
+                            InputTimeoutAction::Quit => return EmulationState::Quit,
+                            InputTimeoutAction::Key(key) => {
+                                let reg = self.cpu.peripherals.input.get_lock_register();
+                                self.cpu.registers.set_register(reg, key);
+                                self.cpu.peripherals.input.unlock();
+                            }
+                        }
+                    }
+                    _ => {
+                        // Wait for key.
+                        ctx.input_wait_steps += 1;
+                        return EmulationState::WaitForInput;
+                    }
+                }
             }
         }
 
         // Handle scroll.
-        if self.cpu.peripherals.screen.is_scrolling() {
+        if self.cpu.peripherals.screen.is_scrolling() && !self.scroll_waits_for_sync() {
             self.cpu.peripherals.screen.apply_scroll();
         }
 
@@ -259,17 +1216,104 @@ impl Emulator {
             );
 
             // Trace.
-            let opcode_enum = opcodes::get_opcode_enum(opcode);
+            let opcode_enum = opcodes::get_opcode_enum_from_array(
+                self.cpu.peripherals.memory.get_data(),
+                self.cpu.peripherals.memory.get_pointer() as usize,
+            );
             let (assembly, verbose) = opcodes::get_opcode_str(&opcode_enum);
             trace_exec!(ctx.tracefile_handle, "  - {:20} ; {}", assembly, verbose);
 
+            if ctx.recent_trace_capacity > 0 {
+                if self.recent_trace.len() >= ctx.recent_trace_capacity {
+                    self.recent_trace.pop_front();
+                }
+
+                self.recent_trace.push_back(format!(
+                    "[{:08X}] {:04X} - {:20} ; {}",
+                    self.cpu.instruction_count,
+                    self.cpu.peripherals.memory.get_pointer(),
+                    assembly,
+                    verbose
+                ));
+            }
+
+            if ctx.track_coverage {
+                self.coverage.insert(opcodes::get_opcode_name(&opcode_enum));
+            }
+
+            if let Some(hook) = &self.on_unknown_opcode {
+                if self.cpu.is_unknown_opcode(&opcode_enum) {
+                    let memory = &self.cpu.peripherals.memory;
+                    hook(memory.get_pointer(), memory.read_opcode());
+                }
+            }
+
+            if ctx.halt_on_self_jump {
+                if let OpCode::JP(addr) = opcode_enum {
+                    if addr == self.cpu.peripherals.memory.get_pointer() {
+                        info!(
+                            target: "chip8::emulator",
+                            message = "Self jump detected, halting.",
+                            pointer = addr
+                        );
+                        self.cpu.exit_reason = Some(ExitReason::Halt);
+                        return EmulationState::Quit;
+                    }
+                }
+            }
+
             // Execute instruction.
-            if self.cpu.execute_instruction(&opcode_enum) {
+            let exit_reason = self.cpu.execute(&opcode_enum);
+            if let Some(observer) = self.step_observer.as_mut() {
+                observer(&self.cpu, &opcode_enum);
+            }
+
+            if let Some(reason) = exit_reason {
+                if let ExitReason::StackUnderflow
+                | ExitReason::StackOverflow
+                | ExitReason::MemoryTrap = reason
+                {
+                    if let Some(path) = &ctx.recent_trace_path {
+                        if let Err(e) = self.dump_recent_trace(path) {
+                            error!(
+                                target: "chip8::emulator",
+                                message = "Could not dump recent trace.",
+                                error = %e
+                            );
+                        }
+                    }
+
+                    return EmulationState::Error;
+                }
+
                 return EmulationState::Quit;
             }
 
             self.cpu.instruction_count += 1;
 
+            // Watchdog.
+            if let OpCode::DRW(..) | OpCode::DRWX(..) = opcode_enum {
+                ctx.watchdog_steps = 0;
+            } else {
+                ctx.watchdog_steps += 1;
+            }
+
+            if let Some((instructions, action)) = ctx.watchdog {
+                if ctx.watchdog_steps > instructions {
+                    warn!(
+                        target: "chip8::emulator",
+                        message = "Watchdog triggered: no draw nor running timer.",
+                        instructions = ctx.watchdog_steps,
+                        pointer = self.cpu.peripherals.memory.get_pointer()
+                    );
+                    ctx.watchdog_steps = 0;
+
+                    if action == WatchdogAction::Quit {
+                        return EmulationState::Quit;
+                    }
+                }
+            }
+
             ctx.cpu_frametime = 0;
         } else {
             ctx.cpu_frametime += 1;
@@ -277,12 +1321,796 @@ impl Emulator {
 
         if ctx.timer_frametime >= TIMER_FRAME_LIMIT {
             // Handle timers.
+            if self.cpu.delay_timer.get_value() > 0 || self.cpu.sound_timer.get_value() > 0 {
+                ctx.watchdog_steps = 0;
+            }
             self.cpu.decrement_timers();
             ctx.timer_frametime = 0;
+
+            // Handle synchronized scroll.
+            if self.cpu.peripherals.screen.is_scrolling() && self.scroll_waits_for_sync() {
+                self.cpu.peripherals.screen.apply_scroll();
+            }
         } else {
             ctx.timer_frametime += 1;
         }
 
         EmulationState::Normal
     }
+
+    /// Check if the pending scroll waits for the next timer tick.
+    ///
+    /// In low resolution, SCRD/SCRU are synchronized on the 60Hz timer when
+    /// the `lores_scroll_sync` quirk is enabled.
+    fn scroll_waits_for_sync(&self) -> bool {
+        let screen = &self.cpu.peripherals.screen;
+
+        self.cpu.quirks.lores_scroll_sync
+            && screen.get_mode() == ScreenMode::Standard
+            && matches!(
+                screen.data.scroll.direction,
+                ScreenScrollDirection::Down | ScreenScrollDirection::Up
+            )
+    }
+}
+
+impl Drop for Emulator {
+    fn drop(&mut self) {
+        if let Err(e) = self.save_rpl_flags() {
+            error!(
+                target: "chip8::emulator",
+                message = "RPL flags saving error.",
+                error = %e,
+            );
+        }
+    }
+}
+
+/// Run cartridges in parallel, e.g. for ROM test suites.
+///
+/// Each cartridge runs headless in its own emulator (see `run_and_capture`),
+/// on a pool of `SUITE_WORKERS` threads. A failing run stops early, with its
+/// exit reason in the machine state.
+///
+/// # Arguments
+///
+/// * `roms` - Cartridges.
+/// * `frames` - Frame count per cartridge, stopping early on quit or error.
+///
+/// # Returns
+///
+/// * Final machine states, in cartridge order.
+///
+pub fn run_suite(roms: Vec<Cartridge>, frames: usize) -> Vec<MachineState> {
+    let count = roms.len();
+    let roms = Arc::new(roms);
+    let next = Arc::new(AtomicUsize::new(0));
+    let states = Arc::new(Mutex::new(vec![None; count]));
+
+    let workers: Vec<_> = (0..SUITE_WORKERS.min(count))
+        .map(|_| {
+            let roms = roms.clone();
+            let next = next.clone();
+            let states = states.clone();
+
+            thread::spawn(move || loop {
+                let idx = next.fetch_add(1, Ordering::Relaxed);
+                let cartridge = match roms.get(idx) {
+                    Some(cartridge) => cartridge,
+                    None => break,
+                };
+
+                let mut emulator = Emulator::new();
+                emulator
+                    .load_game(cartridge)
+                    .expect("cartridge fits at the default entry point");
+                emulator.run_and_capture(frames);
+                states.lock().unwrap()[idx] = Some(MachineState::from_cpu(&emulator.cpu));
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        worker.join().expect("suite worker panicked");
+    }
+
+    let states = states.lock().unwrap();
+    states
+        .iter()
+        .map(|state| state.clone().expect("every cartridge is run"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::core::assembler::Assembler;
+    use crate::peripherals::cartridge::CARTRIDGE_MAX_SIZE;
+    use crate::peripherals::screen::{VIDEO_MEMORY_HEIGHT, VIDEO_MEMORY_WIDTH};
+
+    #[test]
+    fn test_warmup() {
+        let cartridge = Assembler::from_string("LD V0, 01\nADD V1, 01\nJP 0202")
+            .assemble_cartridge()
+            .unwrap();
+
+        let mut emulator = Emulator::new();
+        let mut ctx = EmulatorContext::new();
+        ctx.set_warmup_instructions(100);
+        emulator.load_game(&cartridge).unwrap();
+
+        emulator.warmup(&mut ctx);
+        assert_eq!(emulator.cpu.instruction_count, 100);
+    }
+
+    #[test]
+    fn test_entry_point() {
+        let assembler = Assembler::from_string("LD V0, 01\nJP 0602");
+
+        let (mut emulator, mut ctx) = EmulatorBuilder::new()
+            .with_entry_point(0x600)
+            .with_warmup_instructions(1)
+            .with_cartridge(assembler.assemble_cartridge().unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(ctx.get_entry_point(), 0x600);
+        assert_eq!(emulator.cpu.peripherals.memory.get_pointer(), 0x600);
+        assert_eq!(emulator.cpu.peripherals.memory.read_opcode(), 0x6001);
+
+        emulator.warmup(&mut ctx);
+        assert_eq!(emulator.cpu.registers.get_register(0), 0x01);
+        assert_eq!(emulator.cpu.peripherals.memory.get_pointer(), 0x602);
+
+        // Reset goes back to the entry point.
+        emulator
+            .reset(&assembler.assemble_cartridge().unwrap(), &mut ctx)
+            .unwrap();
+        assert_eq!(emulator.cpu.peripherals.memory.get_pointer(), 0x600);
+        assert_eq!(emulator.cpu.peripherals.memory.read_opcode(), 0x6001);
+        assert_eq!(EmulatorContext::new().get_entry_point(), 0x200);
+
+        // A full-size cartridge does not fit past 0x600.
+        let cartridge = || Cartridge::from_bytes(&[0; CARTRIDGE_MAX_SIZE]).unwrap();
+        let result = EmulatorBuilder::new()
+            .with_entry_point(0x600)
+            .with_cartridge(cartridge())
+            .build();
+        assert!(matches!(
+            result,
+            Err(Chip8Error::CartridgeTooLarge(CARTRIDGE_MAX_SIZE, 0x0A00))
+        ));
+
+        ctx.set_entry_point(0x600);
+        assert!(matches!(
+            emulator.reset(&cartridge(), &mut ctx),
+            Err(Chip8Error::CartridgeTooLarge(..))
+        ));
+    }
+
+    #[test]
+    fn test_unknown_opcode_hook() {
+        use std::sync::{Arc, Mutex};
+
+        // LD V0, 01 / invalid 5124 / JP 0204.
+        let (mut emulator, mut ctx) = EmulatorBuilder::new()
+            .from_bytes(&[0x60, 0x01, 0x51, 0x24, 0x12, 0x04])
+            .unwrap()
+            .with_warmup_instructions(4)
+            .build()
+            .unwrap();
+
+        let calls = Arc::new(Mutex::new(vec![]));
+        let hook_calls = calls.clone();
+        emulator
+            .set_on_unknown_opcode(move |pc, opcode| hook_calls.lock().unwrap().push((pc, opcode)));
+
+        emulator.warmup(&mut ctx);
+        assert_eq!(*calls.lock().unwrap(), vec![(0x0202, 0x5124)]);
+
+        // XO-CHIP only: 5232 / F000 ABCD / LD V0, 01, then XO-CHIP mode.
+        let run = |mode: CompatMode| {
+            let (mut emulator, mut ctx) = EmulatorBuilder::new()
+                .from_bytes(&[0x52, 0x32, 0xF0, 0x00, 0xAB, 0xCD, 0x60, 0x01])
+                .unwrap()
+                .with_compat_mode(mode)
+                .with_warmup_instructions(3)
+                .build()
+                .unwrap();
+
+            let calls = Arc::new(Mutex::new(vec![]));
+            let hook_calls = calls.clone();
+            emulator.set_on_unknown_opcode(move |pc, opcode| {
+                hook_calls.lock().unwrap().push((pc, opcode))
+            });
+
+            emulator.warmup(&mut ctx);
+            assert_eq!(emulator.cpu.registers.get_register(0), 0x01);
+            let calls = calls.lock().unwrap().clone();
+            calls
+        };
+
+        assert_eq!(
+            run(CompatMode::Chip8),
+            vec![(0x0200, 0x5232), (0x0202, 0xF000)]
+        );
+        assert_eq!(run(CompatMode::XOChip), vec![]);
+    }
+
+    #[test]
+    fn test_halt_on_self_jump() {
+        let assembler = Assembler::from_string("LD V0, 01\nADD V0, 01\nJP 0204");
+
+        // Disabled by default.
+        let (mut emulator, mut ctx) = EmulatorBuilder::new()
+            .with_cartridge(assembler.assemble_cartridge().unwrap())
+            .with_warmup_instructions(100)
+            .build()
+            .unwrap();
+        assert!(matches!(emulator.warmup(&mut ctx), EmulationState::Normal));
+        assert_eq!(emulator.cpu.exit_reason, None);
+
+        let (mut emulator, mut ctx) = EmulatorBuilder::new()
+            .with_cartridge(assembler.assemble_cartridge().unwrap())
+            .with_warmup_instructions(100)
+            .build()
+            .unwrap();
+        ctx.set_halt_on_self_jump(true);
+        assert!(matches!(emulator.warmup(&mut ctx), EmulationState::Quit));
+        assert_eq!(emulator.cpu.instruction_count, 2);
+        assert_eq!(emulator.cpu.registers.get_register(0), 0x02);
+        assert_eq!(emulator.cpu.exit_reason, Some(ExitReason::Halt));
+    }
+
+    #[test]
+    fn test_run_suite() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Emulator>();
+        assert_send::<EmulatorContext>();
+        assert_send::<CPU>();
+
+        let roms = [
+            "LD V0, 01\nEXIT",
+            "LD V0, 02\nLD V1, 03\nEXIT",
+            "LD V2, 04\nJP 0202",
+            "CALL 0200",
+        ]
+        .iter()
+        .map(|src| Assembler::from_string(src).assemble_cartridge().unwrap())
+        .collect();
+
+        let states = run_suite(roms, 10);
+        assert_eq!(states.len(), 4);
+        assert_eq!(states[0].registers[..3], [0x01, 0x00, 0x00]);
+        assert_eq!(states[0].pc, 0x0202);
+        assert_eq!(states[1].registers[..3], [0x02, 0x03, 0x00]);
+        assert_eq!(states[1].pc, 0x0204);
+        assert_eq!(states[2].registers[..3], [0x00, 0x00, 0x04]);
+        assert_eq!(states[2].pc, 0x0202);
+        assert!(states[2].instruction_count > states[1].instruction_count);
+
+        // Stack overflow: only this run fails.
+        assert_eq!(states[0].exit_reason, "Exit");
+        assert_eq!(states[2].exit_reason, "");
+        assert_eq!(states[3].exit_reason, "StackOverflow");
+        assert_eq!(states[3].sp, 12);
+
+        assert!(run_suite(vec![], 10).is_empty());
+    }
+
+    #[test]
+    fn test_watchdog() {
+        let run = |source: &str, instructions: usize, action: WatchdogAction| {
+            let (mut emulator, mut ctx) = EmulatorBuilder::new()
+                .with_cartridge(Assembler::from_string(source).assemble_cartridge().unwrap())
+                .build()
+                .unwrap();
+            ctx.set_watchdog(instructions, action);
+
+            (0..200)
+                .position(|_| matches!(emulator.step(&mut ctx), EmulationState::Quit))
+                .map(|step| step + 1)
+        };
+
+        // Tight loop.
+        assert_eq!(run("JP 0200", 10, WatchdogAction::Quit), Some(11));
+        assert_eq!(run("JP 0200", 10, WatchdogAction::Warn), None);
+
+        // Drawing loop.
+        assert_eq!(
+            run("DRW V0, V0, 1\nJP 0200", 10, WatchdogAction::Quit),
+            None
+        );
+
+        // Waiting on the delay timer, ticking every 17 steps.
+        let wait = "LD V0, FF\nLD DT, V0\nJP 0204";
+        assert_eq!(run(wait, 20, WatchdogAction::Quit), None);
+        assert_eq!(run(wait, 10, WatchdogAction::Quit), Some(11));
+
+        assert_eq!(EmulatorContext::new().get_watchdog(), None);
+    }
+
+    #[test]
+    fn test_step_observer() {
+        use std::sync::{Arc, Mutex};
+
+        let (mut emulator, mut ctx) = EmulatorBuilder::new()
+            .with_cartridge(
+                Assembler::from_string("LD V0, 01\nADD V0, 02\nEXIT")
+                    .assemble_cartridge()
+                    .unwrap(),
+            )
+            .with_warmup_instructions(10)
+            .build()
+            .unwrap();
+
+        let executed = Arc::new(Mutex::new(vec![]));
+        let observed = executed.clone();
+        emulator.set_step_observer(Box::new(move |cpu, opcode| {
+            let (assembly, _) = opcodes::get_opcode_str(opcode);
+            observed
+                .lock()
+                .unwrap()
+                .push((assembly, cpu.registers.get_register(0)));
+        }));
+
+        assert!(matches!(emulator.warmup(&mut ctx), EmulationState::Quit));
+        assert_eq!(
+            *executed.lock().unwrap(),
+            vec![
+                ("LD V0, 01".to_string(), 0x01),
+                ("ADD V0, 02".to_string(), 0x03),
+                ("EXIT".to_string(), 0x03),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_power_on_memory_fill() {
+        let cartridge = Assembler::from_string("LD V0, 01\nJP 0202")
+            .assemble_cartridge()
+            .unwrap();
+        let (mut emulator, mut ctx) = EmulatorBuilder::new().build().unwrap();
+        ctx.set_memory_fill(MemoryFill::Ones);
+        ctx.set_randomize_screen(true);
+
+        emulator.reset(&cartridge, &mut ctx).unwrap();
+
+        let memory = &emulator.cpu.peripherals.memory;
+        assert_eq!(memory.region(0x0200, 4).unwrap(), &[0x60, 0x01, 0x12, 0x02]);
+        assert_eq!(memory.get_data()[0x0204], 0xFF);
+        assert_eq!(memory.get_data()[0x0F00], 0xFF);
+        // Font is kept.
+        assert_ne!(memory.get_data()[0x0000], 0xFF);
+
+        let screen = &emulator.cpu.peripherals.screen;
+        assert!(screen.data.get_pixels().iter().any(|&p| p != 0));
+    }
+
+    #[test]
+    fn test_initial_screen_mode() {
+        let assembler = Assembler::from_string("CLS\nEXIT");
+        let cartridge = assembler.assemble_cartridge().unwrap();
+        let (emulator, ctx) = EmulatorBuilder::new()
+            .with_cartridge(assembler.assemble_cartridge().unwrap())
+            .with_initial_screen_mode(ScreenMode::Extended)
+            .build()
+            .unwrap();
+
+        let check = |emulator: &Emulator| {
+            let screen = &emulator.cpu.peripherals.screen;
+            assert_eq!(screen.get_mode(), ScreenMode::Extended);
+            assert_eq!(screen.data.get_dimensions(), (128, 64));
+            assert_eq!(emulator.cpu.speed_multiplicator, 16);
+            assert!(emulator.cpu.schip_mode);
+            assert_eq!(emulator.cpu.instruction_count, 0);
+        };
+        check(&emulator);
+
+        // Reset falls back to standard mode, unless set in context.
+        let (mut emulator, mut ctx2) = EmulatorBuilder::new().build().unwrap();
+        emulator.reset(&cartridge, &mut ctx2).unwrap();
+        assert_eq!(
+            emulator.cpu.peripherals.screen.get_mode(),
+            ScreenMode::Standard
+        );
+        assert_eq!(ctx.get_initial_screen_mode(), Some(ScreenMode::Extended));
+        ctx2.set_initial_screen_mode(ScreenMode::Extended);
+        emulator.reset(&cartridge, &mut ctx2).unwrap();
+        check(&emulator);
+    }
+
+    #[test]
+    fn test_wait_for_input_handshake() {
+        let (mut emulator, mut ctx) = EmulatorBuilder::new()
+            .with_cartridge(
+                Assembler::from_string("LD V0, K\nLD V1, 01\nJP 0204")
+                    .assemble_cartridge()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+
+        assert!(matches!(emulator.step(&mut ctx), EmulationState::Normal));
+        for _ in 0..3 {
+            assert!(matches!(
+                emulator.step(&mut ctx),
+                EmulationState::WaitForInput
+            ));
+        }
+        assert_eq!(emulator.cpu.peripherals.memory.get_pointer(), 0x0202);
+
+        emulator.cpu.peripherals.input.press(0xA);
+        assert!(matches!(emulator.step(&mut ctx), EmulationState::Normal));
+        assert!(!emulator.cpu.peripherals.input.is_locked());
+        assert_eq!(emulator.cpu.registers.get_register(0), 0xA);
+        assert_eq!(emulator.cpu.registers.get_register(1), 0x1);
+        assert_eq!(emulator.cpu.peripherals.memory.get_pointer(), 0x0204);
+    }
+
+    #[test]
+    fn test_recent_trace_dump() {
+        let path = env::temp_dir().join(format!("chip8-recent-{}.trace", std::process::id()));
+        let (mut emulator, mut ctx) = EmulatorBuilder::new()
+            .with_cartridge(
+                Assembler::from_string("LD V0, 00\nADD V0, 01\nSE V0, 20\nJP 0202\nRET")
+                    .assemble_cartridge()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+        ctx.set_recent_trace(4, &path);
+
+        let mut steps = 0;
+        loop {
+            steps += 1;
+            match emulator.step(&mut ctx) {
+                EmulationState::Normal => (),
+                EmulationState::Error => break,
+                state => panic!("unexpected state {:?}", state),
+            }
+        }
+        assert!(steps > 4);
+
+        let trace = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lines: Vec<_> = trace
+            .lines()
+            .map(|l| l[11..].split(';').next().unwrap().trim_end())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "0206 - JP 0202",
+                "0202 - ADD V0, 01",
+                "0204 - SE V0, 20",
+                "0208 - RET"
+            ]
+        );
+
+        let counters: Vec<_> = trace
+            .lines()
+            .map(|l| usize::from_str_radix(&l[1..9], 16).unwrap())
+            .collect();
+        assert_eq!(counters[3], emulator.cpu.instruction_count);
+        assert!(counters.windows(2).all(|w| w[1] == w[0] + 1));
+    }
+
+    #[test]
+    fn test_step_frame_timed() {
+        let (mut emulator, mut ctx) = EmulatorBuilder::new()
+            .with_cartridge(
+                Assembler::from_string("JP 0200")
+                    .assemble_cartridge()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+        ctx.set_cpu_hz(700.0);
+
+        let deltas = [16_667, 17_000, 15_900, 33_333, 1_000, 8_250];
+        let mut total = Duration::new(0, 0);
+        for i in 0..600 {
+            let delta = Duration::from_micros(deltas[i % deltas.len()]);
+            total += delta;
+
+            emulator.step_frame_timed(&mut ctx, delta);
+
+            let expected = 700.0 * total.as_secs_f64();
+            let executed = emulator.cpu.instruction_count as f64;
+            assert!((expected - executed).abs() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let (mut emulator, mut ctx) = EmulatorBuilder::new()
+            .with_cartridge(
+                Assembler::from_string("LD V0, FF\nLD ST, V0\nHIGH\nJP 0206")
+                    .assemble_cartridge()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+
+        let stats = emulator.stats();
+        assert_eq!(stats.ips, 0.0);
+        assert_eq!(stats.mode, ScreenMode::Standard);
+        assert!(!stats.sound_active);
+
+        // Fixed timestep: 10 instructions per 1/60 s frame.
+        ctx.set_cpu_hz(600.0);
+        for _ in 0..90 {
+            emulator.step_frame_timed(&mut ctx, Duration::from_micros(16_667));
+        }
+
+        let stats = emulator.stats();
+        assert!((stats.ips - 600.0).abs() < 1.0);
+        assert_eq!(stats.target_ips, 600.0);
+        assert_eq!(stats.mode, ScreenMode::Extended);
+        assert!(stats.sound_active);
+        assert_eq!(stats.pc, 0x0206);
+
+        // Without CPU frequency, target follows the speed multiplicator.
+        let mut ctx = EmulatorContext::new();
+        emulator.step_frame_timed(&mut ctx, Duration::from_micros(16_667));
+        assert_eq!(
+            emulator.stats().target_ips,
+            f64::from(emulator.cpu.speed_multiplicator) * 60.0
+        );
+    }
+
+    #[test]
+    fn test_tick() {
+        let (mut emulator, _) = EmulatorBuilder::new()
+            .with_cartridge(
+                Assembler::from_string("LD V0, 01\nLD V1, 02\nJP 0200")
+                    .assemble_cartridge()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+        emulator.cpu.delay_timer.reset(2);
+
+        let mut pointers = vec![];
+        for _ in 0..6 {
+            assert!(matches!(emulator.tick(), EmulationState::Normal));
+            pointers.push(emulator.cpu.peripherals.memory.get_pointer());
+        }
+        assert_eq!(pointers, vec![0x202, 0x204, 0x200, 0x202, 0x204, 0x200]);
+        assert_eq!(emulator.cpu.instruction_count, 6);
+        assert_eq!(emulator.cpu.registers.get_register(1), 0x02);
+
+        for _ in 6..TIMER_FRAME_LIMIT {
+            emulator.tick();
+        }
+        assert_eq!(emulator.cpu.delay_timer.get_value(), 2);
+        emulator.tick();
+        assert_eq!(emulator.cpu.delay_timer.get_value(), 1);
+    }
+
+    #[test]
+    fn test_lores_scroll_sync() {
+        let build = |source: &str| {
+            let (mut emulator, ctx) = EmulatorBuilder::new()
+                .with_cartridge(Assembler::from_string(source).assemble_cartridge().unwrap())
+                .build()
+                .unwrap();
+            emulator.cpu.quirks.lores_scroll_sync = true;
+            (emulator, ctx)
+        };
+        let lit = |emulator: &Emulator, pos: usize| {
+            emulator.cpu.peripherals.screen.data.get_pixels()[pos] == 1
+        };
+
+        // Low resolution: applied on the next timer tick.
+        let (mut emulator, mut ctx) = build("SCRD 1\nJP 0202");
+        emulator.cpu.peripherals.screen.draw_sprite(8, 0, &[0x80]);
+        for _ in 0..TIMER_FRAME_LIMIT {
+            emulator.step(&mut ctx);
+        }
+        assert!(lit(&emulator, 8));
+
+        emulator.step(&mut ctx);
+        assert!(!lit(&emulator, 8));
+        assert!(lit(&emulator, 72));
+
+        // High resolution: applied immediately.
+        let (mut emulator, mut ctx) = build("HIGH\nSCRD 1\nJP 0204");
+        emulator.step(&mut ctx);
+        emulator.cpu.peripherals.screen.draw_sprite(8, 0, &[0x80]);
+        emulator.step(&mut ctx);
+        emulator.step(&mut ctx);
+        assert!(!lit(&emulator, 8));
+        assert!(lit(&emulator, 136));
+    }
+
+    #[test]
+    fn test_tick_latched_input() {
+        let (mut emulator, _) = EmulatorBuilder::new()
+            .with_cartridge(
+                Assembler::from_string("LD V0, 05\nSKP V0\nJP 0202\nJP 0206")
+                    .assemble_cartridge()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+        emulator.cpu.quirks.latched_input = true;
+        emulator.tick();
+
+        // Tap between ticks: released when the next tick latches.
+        emulator.cpu.peripherals.input.press(0x5);
+        emulator.cpu.peripherals.input.release(0x5);
+        emulator.tick();
+        assert_eq!(emulator.cpu.peripherals.memory.get_pointer(), 0x204);
+
+        emulator.tick();
+        emulator.cpu.peripherals.input.press(0x5);
+        emulator.tick();
+        assert_eq!(emulator.cpu.peripherals.memory.get_pointer(), 0x206);
+    }
+
+    #[test]
+    fn test_input_timeout() {
+        let build = || {
+            EmulatorBuilder::new()
+                .with_cartridge(
+                    Assembler::from_string("LD V0, K\nLD V1, 01\nJP 0204")
+                        .assemble_cartridge()
+                        .unwrap(),
+                )
+                .build()
+                .unwrap()
+        };
+
+        // Default: wait forever.
+        let (mut emulator, mut ctx) = build();
+        emulator.step(&mut ctx);
+        for _ in 0..100 {
+            assert!(matches!(
+                emulator.step(&mut ctx),
+                EmulationState::WaitForInput
+            ));
+        }
+
+        let (mut emulator, mut ctx) = build();
+        ctx.set_input_timeout(3, InputTimeoutAction::Quit);
+        emulator.step(&mut ctx);
+        for _ in 0..3 {
+            assert!(matches!(
+                emulator.step(&mut ctx),
+                EmulationState::WaitForInput
+            ));
+        }
+        assert!(matches!(emulator.step(&mut ctx), EmulationState::Quit));
+
+        let (mut emulator, mut ctx) = build();
+        ctx.set_input_timeout(3, InputTimeoutAction::Key(0xA));
+        for _ in 0..6 {
+            emulator.step(&mut ctx);
+        }
+        assert_eq!(emulator.cpu.registers.get_register(0), 0xA);
+        assert_eq!(emulator.cpu.registers.get_register(1), 0x01);
+        assert!(!emulator.cpu.peripherals.input.is_locked());
+    }
+
+    #[test]
+    fn test_render_to_rgba() {
+        let fg = [0xFF, 0x80, 0x00, 0xFF];
+        let bg = [0x00, 0x00, 0x00, 0xFF];
+        let scale = 3;
+
+        let mut emulator = Emulator::new();
+        emulator.cpu.peripherals.screen.toggle_pixel_xy(1, 0);
+        emulator.cpu.peripherals.screen.present();
+
+        let buffer = emulator.render_to_rgba(scale, fg, bg);
+        let width = VIDEO_MEMORY_WIDTH * scale;
+        assert_eq!(buffer.len(), width * VIDEO_MEMORY_HEIGHT * scale * 4);
+
+        let pixel_at = |x: usize, y: usize| &buffer[(x + y * width) * 4..(x + y * width) * 4 + 4];
+        assert_eq!(pixel_at(0, 0), bg);
+        assert_eq!(pixel_at(3, 0), fg);
+        assert_eq!(pixel_at(5, 2), fg);
+        assert_eq!(pixel_at(6, 0), bg);
+    }
+
+    #[test]
+    fn test_coverage() {
+        let rom = Assembler::from_string("LD V0, 01\nADD V0, 01\nSE V0, 00\nJP 0202\nCLS")
+            .assemble_cartridge()
+            .unwrap();
+
+        let (mut emulator, mut ctx) = EmulatorBuilder::new()
+            .with_cartridge(rom)
+            .with_warmup_instructions(50)
+            .build()
+            .unwrap();
+        ctx.set_coverage_tracking(true);
+        emulator.warmup(&mut ctx);
+
+        let expected: HashSet<_> = ["LDByte", "ADDByte", "SEByte", "JP"]
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(emulator.coverage(), expected);
+
+        // Disabled by default.
+        let (mut emulator, mut ctx) = EmulatorBuilder::new()
+            .with_cartridge(
+                Assembler::from_string("JP 0200")
+                    .assemble_cartridge()
+                    .unwrap(),
+            )
+            .with_warmup_instructions(10)
+            .build()
+            .unwrap();
+        emulator.warmup(&mut ctx);
+        assert!(emulator.coverage().is_empty());
+    }
+
+    #[test]
+    fn test_tracefile_mode() {
+        let path = env::temp_dir().join(format!("chip8-trace-{}", std::process::id()));
+        let tracefile = Some(path.to_string_lossy().to_string());
+        let cartridge = Assembler::from_string("LD V0, 01\nJP 0202")
+            .assemble_cartridge()
+            .unwrap();
+        fs::write(&path, "stale trace\n").unwrap();
+
+        let run_trace = |mode| {
+            let mut emulator = Emulator::new();
+            let mut ctx = EmulatorContext::new();
+            ctx.set_tracefile_mode(mode);
+            ctx.prepare_tracefile(&tracefile);
+            emulator.load_game(&cartridge).unwrap();
+            emulator.step(&mut ctx);
+            fs::read_to_string(&path).unwrap()
+        };
+
+        // Truncate by default.
+        assert_eq!(
+            EmulatorContext::new().get_tracefile_mode(),
+            TracefileMode::Truncate
+        );
+        let truncated = run_trace(TracefileMode::Truncate);
+        assert!(!truncated.contains("stale trace"));
+        assert!(truncated.contains("LD V0, 01"));
+
+        let appended = run_trace(TracefileMode::Append);
+        assert!(appended.starts_with(&truncated));
+        assert_eq!(appended.matches("LD V0, 01").count(), 2);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_rpl_flags_persistence() {
+        let dir = env::temp_dir().join(format!("chip8-rpl-{}", std::process::id()));
+        let cartridge = Assembler::from_string("LD V0, 12\nLD V1, 34\nLDX [I], V1\nEXIT")
+            .assemble_cartridge()
+            .unwrap();
+
+        {
+            let mut emulator = Emulator::new();
+            let mut ctx = EmulatorContext::new();
+            emulator.set_persistence_dir(&dir);
+            emulator.load_game(&cartridge).unwrap();
+
+            while !matches!(
+                emulator.step(&mut ctx),
+                EmulationState::Quit | EmulationState::Error
+            ) {}
+        }
+
+        let mut emulator = Emulator::new();
+        emulator.set_persistence_dir(&dir);
+        emulator.load_game(&cartridge).unwrap();
+        assert_eq!(&emulator.cpu.rpl_flags.get_data()[..3], &[0x12, 0x34, 0x00]);
+
+        drop(emulator);
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -1,6 +1,105 @@
 //! Error module.
 
-use std::error::Error;
+use std::{error::Error, fmt, io};
+
+use crate::{
+    core::opcodes::BadInstruction,
+    peripherals::{cartridge::PatchOutOfBounds, memory::MemoryOutOfBounds},
+};
 
 /// Common result.
 pub type CResult<T = ()> = Result<T, Box<dyn Error>>;
+
+/// CHIP-8 error.
+///
+/// Returned by cartridge loading and patching, stack pushes and memory
+/// region accesses. Other APIs still return `CResult`, which it converts
+/// into, so it can be propagated with `?`.
+#[derive(Debug)]
+pub enum Chip8Error {
+    /// Bad instruction.
+    BadInstruction(String),
+    /// I/O error.
+    Io(io::Error),
+    /// Cartridge too large, with its size and the maximum size (e.g.
+    /// `CARTRIDGE_MAX_SIZE`, or the memory left past the entry point).
+    CartridgeTooLarge(usize, usize),
+    /// CPU stack overflow, with the stack capacity.
+    StackOverflow(usize),
+    /// Bad address.
+    BadAddress(String),
+    /// Bad hexadecimal data.
+    BadHex(String),
+    /// Other error.
+    Other(Box<dyn Error>),
+}
+
+impl Error for Chip8Error {
+    fn description(&self) -> &str {
+        match self {
+            Self::BadInstruction(_) => "bad instruction",
+            Self::Io(_) => "i/o error",
+            Self::CartridgeTooLarge(..) => "cartridge too large",
+            Self::StackOverflow(_) => "stack overflow",
+            Self::BadAddress(_) => "bad address",
+            Self::BadHex(_) => "bad hexadecimal data",
+            Self::Other(_) => "other error",
+        }
+    }
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Other(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadInstruction(s) => write!(f, "bad instruction: {}.", s),
+            Self::Io(e) => write!(f, "i/o error: {}", e),
+            Self::CartridgeTooLarge(size, max) => {
+                write!(f, "cartridge is too large: {} bytes (max: {})", size, max)
+            }
+            Self::StackOverflow(capacity) => {
+                write!(f, "CPU stack is full ! (limit: {})", capacity)
+            }
+            Self::BadAddress(s) => write!(f, "bad address: {}", s),
+            Self::BadHex(s) => write!(f, "bad hexadecimal data: {}", s),
+            Self::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<io::Error> for Chip8Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<BadInstruction> for Chip8Error {
+    fn from(error: BadInstruction) -> Self {
+        Self::BadInstruction(error.0)
+    }
+}
+
+impl From<PatchOutOfBounds> for Chip8Error {
+    fn from(error: PatchOutOfBounds) -> Self {
+        Self::BadAddress(error.to_string())
+    }
+}
+
+impl From<MemoryOutOfBounds> for Chip8Error {
+    fn from(error: MemoryOutOfBounds) -> Self {
+        Self::BadAddress(error.to_string())
+    }
+}
+
+impl From<Box<dyn Error>> for Chip8Error {
+    fn from(error: Box<dyn Error>) -> Self {
+        Self::Other(error)
+    }
+}
@@ -1,10 +1,11 @@
 //! Cartridge.
 
 use std::{
+    collections::BTreeMap,
     env,
     error::Error,
     fmt,
-    fs::{metadata, File, OpenOptions},
+    fs::{File, OpenOptions},
     io,
     io::prelude::*,
     path::{Path, PathBuf},
@@ -15,12 +16,28 @@ use tracing::info;
 use super::memory::INITIAL_MEMORY_POINTER;
 use crate::{
     core::{
-        opcodes::{extract_opcode_from_array, get_opcode_enum, get_opcode_str, is_opcode_schip},
+        opcodes::{
+            extract_opcode_from_array, get_opcode_enum, get_opcode_enum_from_array,
+            get_opcode_size, get_opcode_str, is_opcode_schip, OpCode,
+        },
+        quirks::CompatMode,
         types::{C8Addr, C8Byte},
     },
-    errors::CResult,
+    errors::{CResult, Chip8Error},
 };
 
+/// Disassembly HTML style.
+const DISASSEMBLY_HTML_STYLE: &str = "\
+    body { background: #1e1e1e; color: #d4d4d4; font-family: monospace; }\n\
+    td { padding: 0 8px; }\n\
+    .addr, .code { color: #808080; }\n\
+    .comment { color: #6a9955; }\n\
+    .flow .mnemonic { color: #c586c0; }\n\
+    .arith .mnemonic { color: #4fc1ff; }\n\
+    .draw .mnemonic { color: #dcdcaa; }\n\
+    .schip .mnemonic { color: #ce9178; }\n\
+    .data .mnemonic { color: #808080; }\n";
+
 /// Cartridge max size.
 pub const CARTRIDGE_MAX_SIZE: usize = 4096 - 512;
 /// Empty game name.
@@ -31,6 +48,7 @@ pub struct Cartridge {
     title: String,
     path: String,
     data: Vec<C8Byte>,
+    compat_mode: Option<CompatMode>,
 }
 
 /// Missing cartridge error.
@@ -49,6 +67,52 @@ impl fmt::Display for MissingCartridgeError {
     }
 }
 
+/// Patch out of cartridge bounds.
+#[derive(Debug)]
+pub struct PatchOutOfBounds {
+    /// Patch address.
+    pub addr: C8Addr,
+    /// Patch length.
+    pub len: usize,
+    /// Cartridge size.
+    pub size: usize,
+}
+
+impl Error for PatchOutOfBounds {
+    fn description(&self) -> &str {
+        "patch out of bounds"
+    }
+}
+
+impl fmt::Display for PatchOutOfBounds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "patch 0x{:04X}+{} is out of cartridge bounds (0x{:04X}-0x{:04X})",
+            self.addr,
+            self.len,
+            INITIAL_MEMORY_POINTER,
+            INITIAL_MEMORY_POINTER as usize + self.size
+        )
+    }
+}
+
+/// Bad IPS patch.
+#[derive(Debug)]
+pub struct BadIpsPatch(pub String);
+
+impl Error for BadIpsPatch {
+    fn description(&self) -> &str {
+        "bad IPS patch"
+    }
+}
+
+impl fmt::Display for BadIpsPatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bad IPS patch: {}", self.0)
+    }
+}
+
 impl Cartridge {
     /// New empty cartridge.
     ///
@@ -61,9 +125,67 @@ impl Cartridge {
             title: String::from(EMPTY_GAME_NAME),
             path: String::from(""),
             data: vec![],
+            compat_mode: None,
         }
     }
 
+    /// New cartridge from raw bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Cartridge data.
+    ///
+    /// # Returns
+    ///
+    /// * Cartridge result, failing if data exceeds `CARTRIDGE_MAX_SIZE`.
+    ///
+    pub fn from_bytes(bytes: &[C8Byte]) -> Result<Self, Chip8Error> {
+        if bytes.len() > CARTRIDGE_MAX_SIZE {
+            return Err(Chip8Error::CartridgeTooLarge(
+                bytes.len(),
+                CARTRIDGE_MAX_SIZE,
+            ));
+        }
+
+        Ok(Self {
+            title: String::from(EMPTY_GAME_NAME),
+            path: String::from(""),
+            data: bytes.to_vec(),
+            compat_mode: None,
+        })
+    }
+
+    /// New cartridge from an hexadecimal string.
+    ///
+    /// Whitespace is ignored, e.g. `6005 7001` or `60057001`.
+    ///
+    /// # Arguments
+    ///
+    /// * `hex` - Hexadecimal string.
+    ///
+    /// # Returns
+    ///
+    /// * Cartridge result.
+    ///
+    pub fn from_hex_str(hex: &str) -> Result<Self, Chip8Error> {
+        let digits: Vec<char> = hex.chars().filter(|c| !c.is_whitespace()).collect();
+
+        let bytes = digits
+            .chunks(2)
+            .map(|pair| {
+                if pair.len() != 2 {
+                    return Err(Chip8Error::BadHex(format!("odd digit count in {:?}", hex)));
+                }
+
+                let pair: String = pair.iter().collect();
+                C8Byte::from_str_radix(&pair, 16)
+                    .map_err(|_| Chip8Error::BadHex(format!("invalid byte {:?}", pair)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Self::from_bytes(&bytes)
+    }
+
     /// Set data.
     ///
     /// # Arguments
@@ -74,6 +196,130 @@ impl Cartridge {
         self.data = data;
     }
 
+    /// Apply a byte patch.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Memory address, from the `0x200` load base.
+    /// * `bytes` - Bytes to write.
+    ///
+    /// # Returns
+    ///
+    /// * Result, failing if the patch does not fit in the cartridge data.
+    ///
+    pub fn apply_patch(&mut self, addr: C8Addr, bytes: &[C8Byte]) -> Result<(), Chip8Error> {
+        let out_of_bounds = || PatchOutOfBounds {
+            addr,
+            len: bytes.len(),
+            size: self.data.len(),
+        };
+
+        let start = addr
+            .checked_sub(INITIAL_MEMORY_POINTER)
+            .ok_or_else(out_of_bounds)? as usize;
+        let end = start + bytes.len();
+        if end > self.data.len() {
+            return Err(out_of_bounds().into());
+        }
+
+        self.data[start..end].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Apply an IPS patch.
+    ///
+    /// Offsets are relative to the ROM file. Records past the end of the
+    /// data extend the cartridge, up to `CARTRIDGE_MAX_SIZE`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ips` - IPS patch contents.
+    ///
+    /// # Returns
+    ///
+    /// * Result.
+    ///
+    pub fn apply_ips(&mut self, ips: &[u8]) -> CResult<()> {
+        const HEADER: &[u8] = b"PATCH";
+        const EOF: &[u8] = b"EOF";
+
+        fn take<'a>(ips: &'a [u8], pos: &mut usize, len: usize) -> CResult<&'a [u8]> {
+            let chunk = ips
+                .get(*pos..*pos + len)
+                .ok_or_else(|| BadIpsPatch(format!("unexpected end of patch at {}", *pos)))?;
+            *pos += len;
+            Ok(chunk)
+        }
+
+        if !ips.starts_with(HEADER) {
+            return Err(Box::new(BadIpsPatch("missing PATCH header".into())));
+        }
+
+        let mut data = self.data.clone();
+        let mut pos = HEADER.len();
+        loop {
+            let offset = take(ips, &mut pos, 3)?;
+            if offset == EOF {
+                break;
+            }
+
+            let offset = (usize::from(offset[0]) << 16)
+                | (usize::from(offset[1]) << 8)
+                | usize::from(offset[2]);
+            let size = take(ips, &mut pos, 2)?;
+            let size = (usize::from(size[0]) << 8) | usize::from(size[1]);
+
+            let bytes = if size == 0 {
+                // RLE record.
+                let run = take(ips, &mut pos, 2)?;
+                let run = (usize::from(run[0]) << 8) | usize::from(run[1]);
+                let value = take(ips, &mut pos, 1)?[0];
+                vec![value; run]
+            } else {
+                take(ips, &mut pos, size)?.to_vec()
+            };
+
+            let end = offset + bytes.len();
+            if end > CARTRIDGE_MAX_SIZE {
+                return Err(Box::new(BadIpsPatch(format!(
+                    "record 0x{:06X}+{} exceeds max cartridge size",
+                    offset,
+                    bytes.len()
+                ))));
+            }
+
+            if end > data.len() {
+                data.resize(end, 0);
+            }
+            data[offset..end].copy_from_slice(&bytes);
+        }
+
+        self.data = data;
+        Ok(())
+    }
+
+    /// Set target compatibility mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - Compatibility mode, if known.
+    ///
+    pub fn set_compat_mode(&mut self, mode: Option<CompatMode>) {
+        self.compat_mode = mode;
+    }
+
+    /// Get target compatibility mode.
+    ///
+    /// Only known for cartridges assembled from a source declaring it.
+    ///
+    /// # Returns
+    ///
+    /// * Compatibility mode option.
+    ///
+    pub fn get_compat_mode(&self) -> Option<CompatMode> {
+        self.compat_mode
+    }
+
     /// Get game name from path.
     ///
     /// # Arguments
@@ -121,25 +367,38 @@ impl Cartridge {
     /// * Game names.
     ///
     pub fn list_from_games_directory() -> Vec<String> {
+        Self::list_from_directory(&Self::get_games_directory())
+    }
+
+    /// List games from a specific directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `game_dir` - Games directory.
+    ///
+    /// # Returns
+    ///
+    /// * Game names relative to directory, sorted by name.
+    ///
+    pub fn list_from_directory(game_dir: &Path) -> Vec<String> {
         let mut res = vec![];
-        let game_dir = Self::get_games_directory();
 
-        for entry in walkdir::WalkDir::new(game_dir.to_str().unwrap())
+        for entry in walkdir::WalkDir::new(game_dir)
             .into_iter()
             .filter_map(Result::ok)
         {
-            // Remove game_dir from entry.
-            let mdata = metadata(entry.path()).unwrap();
-            if mdata.is_dir() {
+            if entry.file_type().is_dir() {
                 continue;
             }
 
-            let fname = entry.path().strip_prefix(&game_dir).unwrap();
+            // Remove game_dir from entry.
+            let fname = entry.path().strip_prefix(game_dir).unwrap();
             if Self::check_game_extension(fname) {
                 res.push(fname.to_string_lossy().into_owned());
             }
         }
 
+        res.sort();
         res
     }
 
@@ -153,7 +412,7 @@ impl Cartridge {
     ///
     /// * Cartridge result.
     ///
-    pub fn load_from_path<P: AsRef<Path>>(path: P) -> CResult<Cartridge> {
+    pub fn load_from_path<P: AsRef<Path>>(path: P) -> Result<Cartridge, Chip8Error> {
         let mut file = File::open(path.as_ref())?;
 
         let mut contents = Vec::with_capacity(CARTRIDGE_MAX_SIZE);
@@ -170,10 +429,22 @@ impl Cartridge {
     ///
     /// * `path` - Path to file.
     ///
-    pub fn save_to_path<P: AsRef<Path>>(&self, path: P) -> CResult<()> {
+    pub fn save_to_path<P: AsRef<Path>>(&self, path: P) -> Result<(), Chip8Error> {
         let mut file = File::create(path.as_ref())?;
 
-        file.write_all(&self.data)?;
+        self.save_to_writer(&mut file)
+    }
+
+    /// Save cartridge to writer.
+    ///
+    /// Counterpart of `from_bytes`, for in-memory outputs.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Writer.
+    ///
+    pub fn save_to_writer<W: Write>(&self, writer: &mut W) -> Result<(), Chip8Error> {
+        writer.write_all(&self.data)?;
         Ok(())
     }
 
@@ -187,13 +458,20 @@ impl Cartridge {
     ///
     /// # Returns
     ///
-    /// * Cartridge result.
+    /// * Cartridge result, failing if data exceeds `CARTRIDGE_MAX_SIZE`.
     ///
     pub fn load_from_string<P: AsRef<Path>>(
         title: &str,
         path: P,
         bytes: &[C8Byte],
-    ) -> CResult<Cartridge> {
+    ) -> Result<Cartridge, Chip8Error> {
+        if bytes.len() > CARTRIDGE_MAX_SIZE {
+            return Err(Chip8Error::CartridgeTooLarge(
+                bytes.len(),
+                CARTRIDGE_MAX_SIZE,
+            ));
+        }
+
         let title = title.to_string();
         let data = bytes.to_vec();
         let path = path.as_ref().to_str().unwrap().to_string();
@@ -202,7 +480,12 @@ impl Cartridge {
             title = %title
         );
 
-        Ok(Cartridge { title, data, path })
+        Ok(Cartridge {
+            title,
+            data,
+            path,
+            compat_mode: None,
+        })
     }
 
     /// Get games directory.
@@ -243,6 +526,25 @@ impl Cartridge {
         &self.path
     }
 
+    /// Get cartridge hash.
+    ///
+    /// Stable 64-bit FNV-1a hash of the cartridge data, as hexadecimal.
+    ///
+    /// # Returns
+    ///
+    /// * Hash string.
+    ///
+    pub fn get_hash(&self) -> String {
+        let hash = self
+            .data
+            .iter()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |acc, byte| {
+                (acc ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+            });
+
+        format!("{:016x}", hash)
+    }
+
     /// Get internal data.
     ///
     /// # Returns
@@ -266,20 +568,191 @@ impl Cartridge {
         let mut ptr = 0;
 
         while ptr < self.data.len() {
+            if ptr + 1 == self.data.len() {
+                // Lone trailing byte, not a full opcode.
+                let byte = self.data[ptr];
+                code_output.push(C8Addr::from(byte));
+                assembly_output.push(format!("DB {:02X}", byte));
+                verbose_output.push(format!("byte {:02X}", byte));
+                break;
+            }
+
             let opcode_value = extract_opcode_from_array(&self.data, ptr);
-            let opcode_enum = get_opcode_enum(opcode_value);
+            let opcode_enum = get_opcode_enum_from_array(&self.data, ptr);
 
             let (assembly, verbose) = get_opcode_str(&opcode_enum);
             code_output.push(opcode_value);
             assembly_output.push(assembly);
             verbose_output.push(verbose);
 
-            ptr += 2;
+            ptr += get_opcode_size(&opcode_enum) as usize;
         }
 
         (code_output, assembly_output, verbose_output)
     }
 
+    /// Get disassembly.
+    ///
+    /// # Returns
+    ///
+    /// * Tuples of (address, assembly, verbose), starting at `INITIAL_MEMORY_POINTER`.
+    ///
+    pub fn disassembly(&self) -> Vec<(C8Addr, String, String)> {
+        let (code, assembly, verbose) = self.disassemble();
+        let mut ptr_value = INITIAL_MEMORY_POINTER;
+        let mut output = Vec::with_capacity(code.len());
+
+        for ((code, assembly), verbose) in code.into_iter().zip(assembly).zip(verbose) {
+            output.push((ptr_value, assembly, verbose));
+            ptr_value += get_opcode_size(&get_opcode_enum(code));
+        }
+
+        output
+    }
+
+    /// Get disassembly preview.
+    ///
+    /// # Arguments
+    ///
+    /// * `lines` - Instruction count.
+    ///
+    /// # Returns
+    ///
+    /// * First instructions, as `address: assembly` strings.
+    ///
+    pub fn preview_disassembly(&self, lines: usize) -> Vec<String> {
+        self.disassembly()
+            .into_iter()
+            .take(lines)
+            .map(|(addr, assembly, _)| format!("{:04X}: {}", addr, assembly))
+            .collect()
+    }
+
+    /// Get sprite data hints.
+    ///
+    /// Track `LD I, addr` targets followed by a `DRW` instruction, the drawn
+    /// byte count giving the sprite size (32 bytes for `DRWX`).
+    ///
+    /// # Returns
+    ///
+    /// * Sprite sizes, by sprite address.
+    ///
+    pub fn sprite_hints(&self) -> BTreeMap<C8Addr, C8Byte> {
+        let end = INITIAL_MEMORY_POINTER as usize + self.data.len();
+        let mut hints = BTreeMap::new();
+        let mut last_i = None;
+
+        for (addr, _, _) in self.disassembly() {
+            let offset = (addr - INITIAL_MEMORY_POINTER) as usize;
+            if offset + 1 == self.data.len() {
+                break;
+            }
+
+            let size = match get_opcode_enum_from_array(&self.data, offset) {
+                OpCode::LDI(target) | OpCode::LongI(target) => {
+                    last_i = Some(target);
+                    continue;
+                }
+                OpCode::ADDI(_) | OpCode::LDSprite(_) | OpCode::LDXSprite(_) => {
+                    last_i = None;
+                    continue;
+                }
+                OpCode::DRW(_, _, n) if n > 0 => n,
+                OpCode::DRWX(_, _) => 32,
+                _ => continue,
+            };
+
+            if let Some(target) = last_i {
+                let in_cartridge =
+                    target >= INITIAL_MEMORY_POINTER && target as usize + size as usize <= end;
+                if in_cartridge {
+                    let hint = hints.entry(target).or_insert(size);
+                    *hint = size.max(*hint);
+                }
+            }
+        }
+
+        hints
+    }
+
+    /// Get annotated disassembly.
+    ///
+    /// Same as `disassembly`, but sprite data found by `sprite_hints` is
+    /// emitted as `DB` bytes with a pixel preview.
+    ///
+    /// # Returns
+    ///
+    /// * Tuples of (address, assembly, verbose), starting at `INITIAL_MEMORY_POINTER`.
+    ///
+    pub fn annotated_disassembly(&self) -> Vec<(C8Addr, String, String)> {
+        let hints = self.sprite_hints();
+        let is_sprite = |offset: usize| {
+            let addr = INITIAL_MEMORY_POINTER as usize + offset;
+            hints.iter().any(|(&start, &size)| {
+                let start = start as usize;
+                addr >= start && addr < start + size as usize
+            })
+        };
+
+        let mut output = Vec::with_capacity(self.data.len() / 2);
+        let mut ptr = 0;
+
+        while ptr < self.data.len() {
+            let addr = INITIAL_MEMORY_POINTER + ptr as C8Addr;
+
+            if ptr + 1 == self.data.len() || is_sprite(ptr) || is_sprite(ptr + 1) {
+                let byte = self.data[ptr];
+                let verbose = if is_sprite(ptr) {
+                    format!("sprite data {}", sprite_preview(byte))
+                } else {
+                    format!("byte {:02X}", byte)
+                };
+
+                output.push((addr, format!("DB {:02X}", byte), verbose));
+                ptr += 1;
+                continue;
+            }
+
+            let opcode_enum = get_opcode_enum_from_array(&self.data, ptr);
+            let (assembly, verbose) = get_opcode_str(&opcode_enum);
+            output.push((addr, assembly, verbose));
+
+            ptr += get_opcode_size(&opcode_enum) as usize;
+        }
+
+        output
+    }
+
+    /// Compare with another cartridge.
+    ///
+    /// When lengths differ, the tail is reported against `0x00`.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Other cartridge.
+    ///
+    /// # Returns
+    ///
+    /// * Tuples of (address, old byte, new byte), starting at `INITIAL_MEMORY_POINTER`.
+    ///
+    pub fn diff(&self, other: &Cartridge) -> Vec<(C8Addr, C8Byte, C8Byte)> {
+        let len = self.data.len().max(other.data.len());
+
+        (0..len)
+            .filter_map(|i| {
+                let old = self.data.get(i).copied().unwrap_or(0);
+                let new = other.data.get(i).copied().unwrap_or(0);
+                let in_both = i < self.data.len() && i < other.data.len();
+
+                if old != new || !in_both {
+                    Some((INITIAL_MEMORY_POINTER + i as C8Addr, old, new))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
     /// Write disassembly to file.
     ///
     /// If file is '-', print to console.
@@ -308,31 +781,141 @@ impl Cartridge {
 
     /// Write disassembly to stream.
     ///
+    /// Uses the annotated disassembly, with sprite data hints.
+    ///
     /// # Arguments
     ///
     /// * `output_stream` - Output stream.
     ///
     pub fn write_disassembly_to_stream<W: Write>(&self, output_stream: &mut W) {
-        let (code, assembly, verbose) = self.disassemble();
-        let mut ptr_value = INITIAL_MEMORY_POINTER;
+        let disassembly = self.annotated_disassembly();
+        let end = INITIAL_MEMORY_POINTER + self.data.len() as C8Addr;
 
-        for i in 0..assembly.len() {
-            let schip_chr = if is_opcode_schip(code[i]) { "*" } else { " " };
+        for (i, (addr, assembly, verbose)) in disassembly.iter().enumerate() {
+            let offset = (addr - INITIAL_MEMORY_POINTER) as usize;
+            let next = disassembly.get(i + 1).map_or(end, |row| row.0);
+            let is_byte = next - addr == 1;
+            let code = if is_byte {
+                C8Addr::from(self.data[offset])
+            } else {
+                extract_opcode_from_array(&self.data, offset)
+            };
+            let schip_chr = if !is_byte && is_opcode_schip(code) {
+                "*"
+            } else {
+                " "
+            };
 
             writeln!(
                 output_stream,
                 "{:04X}|{}({:04X})  {:20} ; {}",
-                ptr_value, schip_chr, code[i], assembly[i], verbose[i]
+                addr, schip_chr, code, assembly, verbose
             )
             .unwrap();
-            ptr_value += 2;
         }
     }
+
+    /// Disassemble cartridge to an HTML table.
+    ///
+    /// Rows are color-coded by instruction category, using the
+    /// `flow`, `arith`, `draw`, `schip`, `load` and `data` CSS classes.
+    ///
+    /// # Returns
+    ///
+    /// * HTML document.
+    ///
+    pub fn disassemble_to_html(&self) -> String {
+        let (code, assembly, verbose) = self.disassemble();
+        let mut ptr_value = INITIAL_MEMORY_POINTER;
+        let mut output = String::new();
+
+        output.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        output.push_str(&format!("<title>{}</title>\n", escape_html(&self.title)));
+        output.push_str(&format!("<style>\n{}</style>\n", DISASSEMBLY_HTML_STYLE));
+        output.push_str("</head>\n<body>\n<table>\n");
+
+        for i in 0..assembly.len() {
+            output.push_str(&format!(
+                "<tr class=\"{}\"><td class=\"addr\">{:04X}</td><td class=\"code\">{:04X}</td><td class=\"mnemonic\">{}</td><td class=\"comment\">{}</td></tr>\n",
+                get_opcode_category(code[i]),
+                ptr_value,
+                code[i],
+                escape_html(&assembly[i]),
+                escape_html(&verbose[i])
+            ));
+            ptr_value += get_opcode_size(&get_opcode_enum(code[i]));
+        }
+
+        output.push_str("</table>\n</body>\n</html>\n");
+        output
+    }
+}
+
+/// Get sprite row preview.
+///
+/// # Arguments
+///
+/// * `byte` - Sprite row.
+///
+/// # Returns
+///
+/// * Lit pixels as `#`, others as `.`.
+///
+fn sprite_preview(byte: C8Byte) -> String {
+    (0..8)
+        .rev()
+        .map(|shift| if byte & (1 << shift) != 0 { '#' } else { '.' })
+        .collect()
+}
+
+/// Get opcode category, used as CSS class.
+fn get_opcode_category(opcode: C8Addr) -> &'static str {
+    if is_opcode_schip(opcode) {
+        return "schip";
+    }
+
+    match get_opcode_enum(opcode) {
+        OpCode::SYS(_)
+        | OpCode::RET
+        | OpCode::JP(_)
+        | OpCode::CALL(_)
+        | OpCode::SEByte(_, _)
+        | OpCode::SNEByte(_, _)
+        | OpCode::SE(_, _)
+        | OpCode::SNE(_, _)
+        | OpCode::JP0(_)
+        | OpCode::SKP(_)
+        | OpCode::SKNP(_) => "flow",
+        OpCode::ADDByte(_, _)
+        | OpCode::OR(_, _)
+        | OpCode::AND(_, _)
+        | OpCode::XOR(_, _)
+        | OpCode::ADD(_, _)
+        | OpCode::SUB(_, _)
+        | OpCode::SHR(_, _)
+        | OpCode::SUBN(_, _)
+        | OpCode::SHL(_, _)
+        | OpCode::RND(_, _)
+        | OpCode::ADDI(_)
+        | OpCode::LDBCD(_) => "arith",
+        OpCode::CLS | OpCode::DRW(_, _, _) | OpCode::LDSprite(_) => "draw",
+        OpCode::EMPTY | OpCode::DATA(_) => "data",
+        _ => "load",
+    }
+}
+
+/// Escape HTML special characters.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::assembler::Assembler;
 
     #[test]
     fn test_load_from_string() {
@@ -356,12 +939,229 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_from_hex_str() {
+        let cartridge = Cartridge::from_hex_str("6005 7001\n00e0").unwrap();
+        assert_eq!(cartridge.get_data(), &[0x60, 0x05, 0x70, 0x01, 0x00, 0xE0]);
+
+        assert!(matches!(
+            Cartridge::from_hex_str("600"),
+            Err(Chip8Error::BadHex(_))
+        ));
+        assert!(matches!(
+            Cartridge::from_hex_str("60 0Z"),
+            Err(Chip8Error::BadHex(_))
+        ));
+    }
+
+    #[test]
+    fn test_preview_disassembly() {
+        let cartridge =
+            Cartridge::from_bytes(&[0x00, 0xE0, 0x60, 0x05, 0x12, 0x02, 0x70, 0x01]).unwrap();
+
+        assert_eq!(
+            cartridge.preview_disassembly(3),
+            vec!["0200: CLS", "0202: LD V0, 05", "0204: JP 0202"]
+        );
+        assert_eq!(cartridge.preview_disassembly(10).len(), 4);
+    }
+
+    #[test]
+    fn test_save_to_writer() {
+        let cartridge = Cartridge::from_bytes(&[0x60, 0x05, 0x70, 0x01]).unwrap();
+
+        let mut output = Vec::new();
+        cartridge.save_to_writer(&mut output).unwrap();
+        assert_eq!(output, cartridge.get_data());
+    }
+
+    #[test]
+    fn test_from_bytes_too_large() {
+        assert!(Cartridge::from_bytes(&[0; CARTRIDGE_MAX_SIZE]).is_ok());
+
+        match Cartridge::from_bytes(&[0; CARTRIDGE_MAX_SIZE + 1]) {
+            Err(Chip8Error::CartridgeTooLarge(size, max)) => {
+                assert_eq!(size, CARTRIDGE_MAX_SIZE + 1);
+                assert_eq!(max, CARTRIDGE_MAX_SIZE);
+            }
+            _ => panic!("expected CartridgeTooLarge"),
+        }
+
+        assert!(matches!(
+            Cartridge::load_from_path("does-not-exist.ch8"),
+            Err(Chip8Error::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_apply_patch() {
+        let mut cartridge = Cartridge::from_bytes(&[0x00, 0xE0, 0x63, 0x00, 0x12, 0x04]).unwrap();
+
+        cartridge.apply_patch(0x0203, &[0x2A, 0x12]).unwrap();
+        assert_eq!(cartridge.get_data(), &[0x00, 0xE0, 0x63, 0x2A, 0x12, 0x04]);
+
+        assert!(cartridge.apply_patch(0x0205, &[0x00, 0x00]).is_err());
+        assert!(cartridge.apply_patch(0x01FF, &[0x00]).is_err());
+        assert_eq!(cartridge.get_data(), &[0x00, 0xE0, 0x63, 0x2A, 0x12, 0x04]);
+    }
+
+    #[test]
+    fn test_apply_ips() {
+        let mut cartridge = Cartridge::from_bytes(&[0x00, 0xE0, 0x63, 0x00, 0x12, 0x04]).unwrap();
+
+        let mut ips = b"PATCH".to_vec();
+        // Record: 2 bytes at 0x000002.
+        ips.extend(&[0x00, 0x00, 0x02, 0x00, 0x02, 0x64, 0x01]);
+        // RLE record: 3 times 0xFF at 0x000005, extending the cartridge.
+        ips.extend(&[0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x03, 0xFF]);
+        ips.extend(b"EOF");
+
+        cartridge.apply_ips(&ips).unwrap();
+        assert_eq!(
+            cartridge.get_data(),
+            &[0x00, 0xE0, 0x64, 0x01, 0x12, 0xFF, 0xFF, 0xFF]
+        );
+
+        // Truncated patches are rejected and leave the cartridge untouched.
+        assert!(cartridge.apply_ips(b"PATCH\x00\x00\x00\x00\x01").is_err());
+        assert!(cartridge.apply_ips(b"NOPE").is_err());
+        assert_eq!(cartridge.get_data().len(), 8);
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = Cartridge::from_bytes(&[0x00, 0xE0, 0x63, 0x00, 0x12, 0x04]).unwrap();
+        let new = Cartridge::from_bytes(&[0x00, 0xE0, 0x63, 0x2A, 0x12, 0x04, 0x00, 0xFD]).unwrap();
+
+        assert_eq!(
+            old.diff(&new),
+            vec![
+                (0x0203, 0x00, 0x2A),
+                (0x0206, 0x00, 0x00),
+                (0x0207, 0x00, 0xFD)
+            ]
+        );
+        assert_eq!(new.diff(&new), vec![]);
+    }
+
+    #[test]
+    fn test_disassembly() {
+        let cartridge = Cartridge::from_bytes(&[0x00, 0xE0, 0x63, 0x00]).unwrap();
+
+        assert_eq!(
+            cartridge.disassembly(),
+            vec![
+                (0x0200, "CLS".to_string(), "clearing screen".to_string()),
+                (0x0202, "LD V3, 00".to_string(), "set V3 = 00".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_disassemble_odd_length() {
+        let cartridge = Cartridge::from_bytes(&[0x00, 0xE0, 0xAB]).unwrap();
+
+        let disassembly = cartridge.disassembly();
+        assert_eq!(disassembly.len(), 2);
+        assert_eq!(disassembly[1].0, 0x0202);
+        assert_eq!(disassembly[1].1, "DB AB");
+
+        let mut disasm_raw = Vec::new();
+        cartridge.write_disassembly_to_stream(&mut disasm_raw);
+        let disasm_str = String::from_utf8(disasm_raw).unwrap();
+        let last_line = disasm_str.lines().last().unwrap();
+        assert_eq!(last_line, "0202| (00AB)  DB AB                ; byte AB");
+
+        // Round trip.
+        let assembler = Assembler::from_string(&disasm_str);
+        assert_eq!(assembler.assemble_data().unwrap(), vec![0x00, 0xE0, 0xAB]);
+    }
+
+    #[test]
+    fn test_sprite_hints() {
+        let cartridge = Assembler::from_string(
+            "LD I, 0208\nLD V0, 00\nDRW V0, V0, 05\nJP 0206\nDB F0\nDB 90\nDB 90\nDB 90\nDB F0",
+        )
+        .assemble_cartridge()
+        .unwrap();
+
+        assert_eq!(
+            cartridge.sprite_hints().into_iter().collect::<Vec<_>>(),
+            vec![(0x0208, 5)]
+        );
+
+        let disassembly = cartridge.annotated_disassembly();
+        assert_eq!(disassembly.len(), 9);
+        assert_eq!(disassembly[3].1, "JP 0206");
+        assert_eq!(
+            disassembly[4],
+            (
+                0x0208,
+                "DB F0".to_string(),
+                "sprite data ####....".to_string()
+            )
+        );
+        assert_eq!(disassembly[5].2, "sprite data #..#....");
+        assert_eq!(disassembly[8].0, 0x020C);
+
+        let mut disasm_raw = Vec::new();
+        cartridge.write_disassembly_to_stream(&mut disasm_raw);
+        let disasm_str = String::from_utf8(disasm_raw).unwrap();
+        assert_eq!(
+            disasm_str.lines().nth(5).unwrap(),
+            "0209| (0090)  DB 90                ; sprite data #..#...."
+        );
+
+        // Round trip.
+        let assembler = Assembler::from_string(&disasm_str);
+        assert_eq!(assembler.assemble_data().unwrap(), cartridge.get_data());
+    }
+
+    #[test]
+    fn test_hash() {
+        let cartridge = Cartridge::load_from_string("Test", "", b"\x00\xE0").unwrap();
+        let same = Cartridge::load_from_string("Other", "", b"\x00\xE0").unwrap();
+        let other = Cartridge::load_from_string("Test", "", b"\x00\xEE").unwrap();
+
+        assert_eq!(cartridge.get_hash(), same.get_hash());
+        assert_ne!(cartridge.get_hash(), other.get_hash());
+        assert_eq!(Cartridge::new_empty().get_hash(), "cbf29ce484222325");
+    }
+
     #[test]
     fn test_game_list() {
         let game_list = Cartridge::list_from_games_directory();
         assert!(!game_list.is_empty());
     }
 
+    #[test]
+    fn test_list_from_directory() {
+        let dir = std::env::temp_dir().join(format!("chip8-roms-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in &["PONG.ch8", "BLITZ.CH8", "README.txt", "notes.md", "TETRIS"] {
+            std::fs::write(dir.join(name), [0x00, 0xE0]).unwrap();
+        }
+
+        let games = Cartridge::list_from_directory(&dir);
+        assert_eq!(games, vec!["BLITZ.CH8", "PONG.ch8", "TETRIS"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(Cartridge::list_from_directory(&dir).is_empty());
+    }
+
+    #[test]
+    fn test_disassemble_to_html() {
+        let cartridge = Cartridge::from_bytes(&[0x00, 0xE0, 0x12, 0x00, 0x00, 0xFF]).unwrap();
+        let html = cartridge.disassemble_to_html();
+
+        assert!(html.contains(
+            "<tr class=\"flow\"><td class=\"addr\">0202</td><td class=\"code\">1200</td><td class=\"mnemonic\">JP 0200</td>"
+        ));
+        assert!(html.contains("<tr class=\"draw\"><td class=\"addr\">0200</td>"));
+        assert!(html.contains("<tr class=\"schip\"><td class=\"addr\">0204</td>"));
+        assert_eq!(escape_html("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+    }
+
     #[test]
     fn test_game_name() {
         assert_eq!(
@@ -120,6 +120,10 @@ pub struct InputState {
     input_pressed: bool,
     /// Lock.
     lock: InputLock,
+    /// Sticky keys: releases are deferred until the key has been read.
+    sticky: bool,
+    /// Deferred releases.
+    pending_release: Vec<bool>,
 }
 
 impl Default for InputState {
@@ -135,6 +139,8 @@ impl Default for InputState {
                 register: INPUT_EMPTY_KEY,
                 key: INPUT_EMPTY_KEY,
             },
+            sticky: false,
+            pending_release: vec![false; INPUT_STATE_COUNT],
         }
     }
 }
@@ -150,6 +156,46 @@ impl InputState {
         Default::default()
     }
 
+    /// Enable sticky keys.
+    ///
+    /// A key pressed then released between two updates stays pressed
+    /// until it has been read once, or until the next update.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value.
+    ///
+    pub fn set_sticky_keys(&mut self, value: bool) {
+        self.sticky = value;
+
+        if !value {
+            self.apply_pending_releases();
+        }
+    }
+
+    /// Check if sticky keys are enabled.
+    ///
+    /// # Returns
+    ///
+    /// * `true` if enabled.
+    /// * `false` if not.
+    ///
+    pub fn has_sticky_keys(&self) -> bool {
+        self.sticky
+    }
+
+    /// Apply deferred releases.
+    ///
+    /// Should be called by input drivers before processing new events.
+    ///
+    pub fn apply_pending_releases(&mut self) {
+        for key in 0..INPUT_STATE_COUNT {
+            if self.pending_release[key] {
+                self.clear_key(key as C8RegIdx);
+            }
+        }
+    }
+
     /// Wait for input.
     ///
     /// # Arguments
@@ -173,6 +219,7 @@ impl InputState {
         }
 
         self.data[key as usize] = 1;
+        self.pending_release[key as usize] = false;
         self.last_pressed_key = key;
         self.input_pressed = true;
 
@@ -246,6 +293,17 @@ impl InputState {
             panic!("Key `{}` does not exist.", key);
         }
 
+        if self.sticky && self.data[key as usize] != 0 && !self.pending_release[key as usize] {
+            // Keep the key pressed until it gets read.
+            self.pending_release[key as usize] = true;
+            return;
+        }
+
+        self.clear_key(key);
+    }
+
+    fn clear_key(&mut self, key: C8RegIdx) {
+        self.pending_release[key as usize] = false;
         self.data[key as usize] = 0;
         self.last_pressed_key = INPUT_EMPTY_KEY;
         self.input_pressed = false;
@@ -269,6 +327,43 @@ impl InputState {
         self.data[key as usize]
     }
 
+    /// Poll input.
+    ///
+    /// Same as `get`, but consumes a deferred release (sticky keys).
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Input key.
+    ///
+    /// # Returns
+    ///
+    /// * Input value.
+    ///
+    pub fn poll(&mut self, key: C8RegIdx) -> C8Byte {
+        let value = self.get(key);
+
+        if self.pending_release[key as usize] {
+            self.clear_key(key);
+        }
+
+        value
+    }
+
+    /// Check if key is pressed.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Input key.
+    ///
+    /// # Returns
+    ///
+    /// * `true` if key is pressed.
+    /// * `false` if not.
+    ///
+    pub fn is_pressed(&self, key: C8RegIdx) -> bool {
+        self.get(key) != 0
+    }
+
     /// Get input data.
     ///
     /// # Returns
@@ -279,6 +374,18 @@ impl InputState {
         &self.data
     }
 
+    /// Take a snapshot of the key state.
+    ///
+    /// # Returns
+    ///
+    /// * Key values.
+    ///
+    pub fn snapshot(&self) -> [C8Byte; INPUT_STATE_COUNT] {
+        let mut snapshot = [0; INPUT_STATE_COUNT];
+        snapshot.copy_from_slice(&self.data);
+        snapshot
+    }
+
     /// Get last pressed key.
     ///
     /// # Returns
@@ -300,6 +407,7 @@ impl InputState {
         self.last_pressed_key = data.last_pressed_key;
         self.input_pressed = data.input_pressed;
         self.lock = data.lock;
+        self.pending_release = vec![false; INPUT_STATE_COUNT];
     }
 
     /// Reset.
@@ -307,6 +415,7 @@ impl InputState {
         self.data = vec![0; INPUT_STATE_COUNT];
         self.last_pressed_key = INPUT_EMPTY_KEY;
         self.input_pressed = false;
+        self.pending_release = vec![false; INPUT_STATE_COUNT];
         self.lock.reset();
     }
 }
@@ -320,3 +429,44 @@ impl fmt::Debug for InputState {
         writeln!(f, "    LK: {}", self.last_pressed_key)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_pressed() {
+        let mut input = InputState::new();
+        assert!(!input.is_pressed(0x5));
+
+        input.press(0x5);
+        assert!(input.is_pressed(0x5));
+        assert!(!input.is_pressed(0x6));
+
+        input.release(0x5);
+        assert!(!input.is_pressed(0x5));
+    }
+
+    #[test]
+    fn test_sticky_keys() {
+        let mut input = InputState::new();
+        input.set_sticky_keys(true);
+
+        // Press and release before any read.
+        input.press(0x5);
+        input.release(0x5);
+        assert!(input.is_pressed(0x5));
+        assert_eq!(input.poll(0x5), 1);
+        assert_eq!(input.poll(0x5), 0);
+
+        // Unread releases are applied on next update.
+        input.press(0x6);
+        input.release(0x6);
+        input.apply_pending_releases();
+        assert!(!input.is_pressed(0x6));
+
+        // Releasing an already released key does nothing.
+        input.release(0x7);
+        assert_eq!(input.poll(0x7), 0);
+    }
+}
@@ -1,30 +1,81 @@
 //! CPU memory.
 
-use std::fmt;
+use std::{borrow::Cow, error::Error, fmt};
 
 use nanoserde::{DeBin, SerBin};
-
-use crate::core::{
-    opcodes::extract_opcode_from_array,
-    types::{C8Addr, C8Byte},
+use quad_rand::gen_range;
+
+use crate::{
+    core::{
+        opcodes::extract_opcode_from_array,
+        types::{C8Addr, C8Byte},
+    },
+    errors::Chip8Error,
 };
 
 // CPU memory vars.
 
 /// Memory size.
 pub const MEMORY_SIZE: usize = 4096;
+/// Extended memory size (XO-CHIP, 16-bit addressing).
+pub const EXTENDED_MEMORY_SIZE: usize = 65536;
 /// Chunk size.
 pub const CHUNK_SIZE: usize = 64;
 
 /// Initial memory pointer.
 pub const INITIAL_MEMORY_POINTER: C8Addr = 0x200;
 
+/// Memory region out of bounds.
+#[derive(Debug)]
+pub struct MemoryOutOfBounds {
+    /// Region start.
+    pub start: usize,
+    /// Region length.
+    pub len: usize,
+    /// Memory size.
+    pub size: usize,
+}
+
+impl Error for MemoryOutOfBounds {
+    fn description(&self) -> &str {
+        "memory out of bounds"
+    }
+}
+
+impl fmt::Display for MemoryOutOfBounds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "memory region 0x{:04X}+{} is out of bounds (size: {} bytes)",
+            self.start, self.len, self.size
+        )
+    }
+}
+
+/// Power-on memory pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryFill {
+    /// Zeros.
+    Zero,
+    /// 0xFF bytes.
+    Ones,
+    /// Pseudo-random bytes.
+    Random,
+}
+
+impl Default for MemoryFill {
+    fn default() -> Self {
+        Self::Zero
+    }
+}
+
 /// CPU memory struct.
 #[derive(Clone, SerBin, DeBin, Default)]
 pub struct Memory {
     data: Vec<C8Byte>,
     pointer: C8Addr,
     code_end_pointer: C8Addr,
+    initial_pointer: C8Addr,
 }
 
 impl Memory {
@@ -35,13 +86,44 @@ impl Memory {
     /// * Memory instance.
     ///
     pub fn new() -> Self {
+        Self::with_size(MEMORY_SIZE)
+    }
+
+    /// Create new memory with a custom size.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Memory size, up to `EXTENDED_MEMORY_SIZE`.
+    ///
+    /// # Returns
+    ///
+    /// * Memory instance.
+    ///
+    pub fn with_size(bytes: usize) -> Self {
+        assert!(
+            bytes <= EXTENDED_MEMORY_SIZE,
+            "memory size should not exceed {} bytes",
+            EXTENDED_MEMORY_SIZE
+        );
+
         Memory {
-            data: vec![0; MEMORY_SIZE],
+            data: vec![0; bytes],
             pointer: INITIAL_MEMORY_POINTER,
             code_end_pointer: INITIAL_MEMORY_POINTER,
+            initial_pointer: INITIAL_MEMORY_POINTER,
         }
     }
 
+    /// Get memory size.
+    ///
+    /// # Returns
+    ///
+    /// * Memory size in bytes.
+    ///
+    pub fn get_size(&self) -> usize {
+        self.data.len()
+    }
+
     /// Write data at offset.
     ///
     /// # Arguments
@@ -49,26 +131,60 @@ impl Memory {
     /// * `offset` - Offset.
     /// * `data` - Data (bytes).
     ///
-    pub fn write_data_at_offset(&mut self, offset: C8Addr, data: &[C8Byte]) {
-        for (idx, v) in data.iter().enumerate() {
-            self.data[(offset + idx as C8Addr) as usize] = *v;
+    /// # Returns
+    ///
+    /// * Result, failing if data goes past memory end.
+    ///
+    pub fn write_data_at_offset(
+        &mut self,
+        offset: C8Addr,
+        data: &[C8Byte],
+    ) -> Result<(), Chip8Error> {
+        let offset = offset as usize;
+        let end = offset + data.len();
+        if end > self.data.len() {
+            return Err(MemoryOutOfBounds {
+                start: offset,
+                len: data.len(),
+                size: self.data.len(),
+            }
+            .into());
         }
+        self.data[offset..end].copy_from_slice(data);
 
-        let end_ptr = offset + (data.len() as C8Addr);
+        let end_ptr = end.min(C8Addr::MAX as usize) as C8Addr;
         if end_ptr > self.code_end_pointer {
             self.code_end_pointer = end_ptr;
         }
+        Ok(())
     }
 
     /// Write byte at offset.
     ///
+    /// Offsets wrap around memory size.
+    ///
     /// # Arguments
     ///
     /// * `offset` - Offset.
     /// * `byte` - Byte.
     ///
     pub fn write_byte_at_offset(&mut self, offset: C8Addr, byte: C8Byte) {
-        self.data[offset as usize] = byte
+        let offset = self.wrap_address(offset as usize) as usize;
+        self.data[offset] = byte
+    }
+
+    /// Wrap address around memory size.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address, possibly past memory end.
+    ///
+    /// # Returns
+    ///
+    /// * Address in memory.
+    ///
+    pub fn wrap_address(&self, addr: usize) -> C8Addr {
+        (addr % self.data.len()) as C8Addr
     }
 
     /// Write data at pointer.
@@ -77,7 +193,11 @@ impl Memory {
     ///
     /// * `data` - Data (bytes).
     ///
-    pub fn write_data_at_pointer(&mut self, data: &[C8Byte]) {
+    /// # Returns
+    ///
+    /// * Result, failing if data goes past memory end.
+    ///
+    pub fn write_data_at_pointer(&mut self, data: &[C8Byte]) -> Result<(), Chip8Error> {
         let pointer = self.pointer;
 
         self.write_data_at_offset(pointer, data)
@@ -85,6 +205,9 @@ impl Memory {
 
     /// Get data at offset.
     ///
+    /// Offsets wrap around memory size, like byte reads: reading past
+    /// memory end continues from address 0.
+    ///
     /// # Arguments
     ///
     /// * `offset` - Offset.
@@ -94,12 +217,22 @@ impl Memory {
     ///
     /// * Data.
     ///
-    pub fn read_data_at_offset(&self, offset: C8Addr, count: C8Addr) -> &[C8Byte] {
-        &self.data[(offset as usize)..((offset + count) as usize)]
+    pub fn read_data_at_offset(&self, offset: C8Addr, count: C8Addr) -> Cow<'_, [C8Byte]> {
+        let size = self.data.len();
+        let start = offset as usize % size;
+        let end = start + count as usize;
+
+        if end <= size {
+            Cow::Borrowed(&self.data[start..end])
+        } else {
+            Cow::Owned((start..end).map(|addr| self.data[addr % size]).collect())
+        }
     }
 
     /// Get byte at offset.
     ///
+    /// Offsets wrap around memory size.
+    ///
     /// # Arguments
     ///
     /// * `offset` - Offset.
@@ -109,7 +242,7 @@ impl Memory {
     /// * Byte.
     ///
     pub fn read_byte_at_offset(&self, offset: C8Addr) -> C8Byte {
-        self.data[offset as usize]
+        self.data[self.wrap_address(offset as usize) as usize]
     }
 
     /// Set pointer.
@@ -134,13 +267,36 @@ impl Memory {
 
     /// Advance pointer of 2.
     pub fn advance_pointer(&mut self) {
-        self.pointer += 2;
+        self.pointer = self.pointer.wrapping_add(2);
     }
 
     /// Reset pointer at initial value.
     pub fn reset_pointer(&mut self) {
-        self.pointer = INITIAL_MEMORY_POINTER;
-        self.code_end_pointer = INITIAL_MEMORY_POINTER;
+        self.pointer = self.initial_pointer;
+        self.code_end_pointer = self.initial_pointer;
+    }
+
+    /// Set initial pointer.
+    ///
+    /// Entry point used when loading a cartridge or resetting.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address.
+    ///
+    pub fn set_initial_pointer(&mut self, addr: C8Addr) {
+        self.initial_pointer = addr;
+        self.reset_pointer();
+    }
+
+    /// Get initial pointer.
+    ///
+    /// # Returns
+    ///
+    /// * Initial pointer value.
+    ///
+    pub fn get_initial_pointer(&self) -> C8Addr {
+        self.initial_pointer
     }
 
     /// Read opcode.
@@ -191,7 +347,7 @@ impl Memory {
     pub fn read_opcodes_at_address(&self, addr: C8Addr, count: C8Addr) -> Vec<C8Addr> {
         let mut output = vec![];
         for c in 0..count {
-            let current = addr + (c * 2);
+            let current = addr.wrapping_add(c * 2);
             output.push(self.read_opcode_at_address(current));
         }
 
@@ -208,11 +364,84 @@ impl Memory {
         &self.data
     }
 
+    /// Get whole address space.
+    ///
+    /// # Returns
+    ///
+    /// * Memory bytes.
+    ///
+    pub fn as_slice(&self) -> &[C8Byte] {
+        &self.data
+    }
+
+    /// Get memory region.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - Start address.
+    /// * `len` - Region length.
+    ///
+    /// # Returns
+    ///
+    /// * Region bytes result.
+    ///
+    pub fn region(&self, start: C8Addr, len: usize) -> Result<&[C8Byte], Chip8Error> {
+        let start = start as usize;
+        match start.checked_add(len) {
+            Some(end) if end <= self.data.len() => Ok(&self.data[start..end]),
+            _ => Err(MemoryOutOfBounds {
+                start,
+                len,
+                size: self.data.len(),
+            }
+            .into()),
+        }
+    }
+
+    /// Find byte pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - Byte pattern.
+    ///
+    /// # Returns
+    ///
+    /// * Addresses where the pattern starts.
+    ///
+    pub fn find_pattern(&self, pattern: &[C8Byte]) -> Vec<C8Addr> {
+        if pattern.is_empty() {
+            return vec![];
+        }
+
+        self.data
+            .windows(pattern.len())
+            .enumerate()
+            .filter(|(_, window)| *window == pattern)
+            .map(|(addr, _)| addr as C8Addr)
+            .collect()
+    }
+
     /// Reset memory.
     pub fn reset(&mut self) {
-        self.data = vec![0; MEMORY_SIZE];
-        self.pointer = INITIAL_MEMORY_POINTER;
-        self.code_end_pointer = INITIAL_MEMORY_POINTER;
+        self.data = vec![0; self.data.len()];
+        self.reset_pointer();
+    }
+
+    /// Fill memory from an address to the end.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - Start address.
+    /// * `fill` - Fill pattern.
+    ///
+    pub fn fill_from(&mut self, start: usize, fill: MemoryFill) {
+        for byte in self.data.iter_mut().skip(start) {
+            *byte = match fill {
+                MemoryFill::Zero => 0x00,
+                MemoryFill::Ones => 0xFF,
+                MemoryFill::Random => gen_range(0u16, 256) as C8Byte,
+            };
+        }
     }
 
     /// Load from save.
@@ -225,6 +454,7 @@ impl Memory {
         self.data = memory.data;
         self.pointer = memory.pointer;
         self.code_end_pointer = memory.code_end_pointer;
+        self.initial_pointer = memory.initial_pointer;
     }
 }
 
@@ -274,3 +504,81 @@ fn print_row(f: &mut fmt::Formatter<'_>) -> fmt::Result {
 
     writeln!(f)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extended_memory() {
+        let mut memory = Memory::with_size(EXTENDED_MEMORY_SIZE);
+        assert_eq!(memory.get_size(), EXTENDED_MEMORY_SIZE);
+
+        memory.write_data_at_offset(0x3000, &[0x12, 0x34]).unwrap();
+        assert_eq!(*memory.read_data_at_offset(0x3000, 2), [0x12, 0x34]);
+        assert_eq!(memory.read_opcode_at_address(0x3000), 0x1234);
+
+        memory.write_byte_at_offset(0xFFFF, 0xAB);
+        memory.write_byte_at_offset(0x0000, 0xCD);
+        assert_eq!(*memory.read_data_at_offset(0xFFFF, 2), [0xAB, 0xCD]);
+
+        memory.reset();
+        assert_eq!(memory.get_size(), EXTENDED_MEMORY_SIZE);
+    }
+
+    #[test]
+    fn test_write_past_end() {
+        let mut memory = Memory::new();
+        memory.write_data_at_offset(0x0FFE, &[0x12, 0x34]).unwrap();
+        assert_eq!(*memory.read_data_at_offset(0x0FFE, 2), [0x12, 0x34]);
+
+        assert!(matches!(
+            memory.write_data_at_offset(0x0FFF, &[0xAB, 0xCD]),
+            Err(Chip8Error::BadAddress(_))
+        ));
+        assert_eq!(*memory.read_data_at_offset(0x0FFF, 1), [0x34]);
+    }
+
+    #[test]
+    fn test_read_past_end() {
+        let mut memory = Memory::new();
+        memory.write_byte_at_offset(0x0FFF, 0xAB);
+
+        let font = memory.read_data_at_offset(0x0000, 2).to_vec();
+
+        assert_eq!(
+            *memory.read_data_at_offset(0x0FFE, 4),
+            [0x00, 0xAB, font[0], font[1]]
+        );
+        assert_eq!(memory.read_data_at_offset(0x1000, 2), font);
+        assert_eq!(memory.read_data_at_offset(0xFFFF, 0xFFFF).len(), 0xFFFF);
+    }
+
+    #[test]
+    fn test_fill_random() {
+        let mut memory = Memory::with_size(EXTENDED_MEMORY_SIZE);
+        memory.fill_from(0, MemoryFill::Random);
+
+        assert!(memory.get_data().contains(&0xFF));
+        assert!(memory.get_data().contains(&0x00));
+    }
+
+    #[test]
+    fn test_region() {
+        let mut memory = Memory::new();
+        assert_eq!(memory.as_slice().len(), MEMORY_SIZE);
+
+        memory
+            .write_data_at_offset(0x200, &[0x12, 0x34, 0x56])
+            .unwrap();
+        assert_eq!(memory.region(0x200, 3).unwrap(), &[0x12, 0x34, 0x56]);
+        assert_eq!(memory.region(0, MEMORY_SIZE).unwrap().len(), MEMORY_SIZE);
+        assert!(memory.region(0x0FFF, 1).is_ok());
+        assert!(memory.region(0x0FFF, 2).is_err());
+        assert!(memory.region(0x1000, 0).is_ok());
+        assert!(matches!(
+            memory.region(0x2000, 1),
+            Err(Chip8Error::BadAddress(_))
+        ));
+    }
+}
@@ -1,8 +1,10 @@
 //! CHIP-8 video memory.
 
-use std::fmt;
+use std::{collections::BTreeSet, fmt, time::Duration};
 
 use nanoserde::{DeBin, SerBin};
+use quad_rand::gen_range;
+use tracing::debug;
 
 use crate::{core::types::C8Byte, drivers::RenderInterface, errors::CResult};
 
@@ -18,7 +20,11 @@ pub const SPRITE_WIDTH: usize = 8;
 /// Super sprite width.
 pub const SUPER_SPRITE_WIDTH: usize = 16;
 
-const PIXEL_FADE_COEFFICIENT: f32 = 0.8;
+/// Default fade half-life, in milliseconds.
+///
+/// Close to the former 0.8 decay per 60 Hz frame.
+pub const DEFAULT_FADE_HALF_LIFE_MS: u32 = 52;
+
 const VIDEO_MEMORY_SIZE: usize = VIDEO_MEMORY_WIDTH * VIDEO_MEMORY_HEIGHT;
 
 /// Color.
@@ -46,7 +52,7 @@ impl Color {
 }
 
 /// Screen mode.
-#[derive(Debug, Clone, SerBin, DeBin)]
+#[derive(Debug, Clone, PartialEq, SerBin, DeBin)]
 pub enum ScreenMode {
     /// Standard screen.
     Standard,
@@ -65,6 +71,8 @@ pub enum ScreenScrollDirection {
     Left,
     /// Disabled.
     Disabled,
+    /// Up.
+    Up,
 }
 
 /// Screen scroll.
@@ -88,10 +96,56 @@ pub struct ScreenData {
     pub scroll: ScreenScroll,
 }
 
+impl ScreenData {
+    /// Get pixels, row by row.
+    ///
+    /// # Returns
+    ///
+    /// * Pixel values (0 when off).
+    ///
+    pub fn get_pixels(&self) -> &[C8Byte] {
+        &self.data
+    }
+
+    /// Get screen mode.
+    ///
+    /// # Returns
+    ///
+    /// * Screen mode.
+    ///
+    pub fn get_mode(&self) -> ScreenMode {
+        self.mode.clone()
+    }
+
+    /// Get screen dimensions.
+    ///
+    /// # Returns
+    ///
+    /// * Width and height, in pixels.
+    ///
+    pub fn get_dimensions(&self) -> (usize, usize) {
+        let coef = get_screen_size_coef(&self.mode);
+        (VIDEO_MEMORY_WIDTH * coef, VIDEO_MEMORY_HEIGHT * coef)
+    }
+}
+
 /// Screen memory struct.
+///
+/// Drawing and scrolling operations write to the back buffer (`data`),
+/// while rendering reads the front buffer, updated on `present`.
 pub struct Screen {
-    /// Screen data.
+    /// Screen data (back buffer).
     pub data: ScreenData,
+    front: ScreenData,
+    dirty: BTreeSet<usize>,
+    full_redraw: bool,
+    pending_dirty: BTreeSet<usize>,
+    pending_full_redraw: bool,
+    last_collision: u8,
+    clear_on_mode_switch: bool,
+    wrap_x: bool,
+    wrap_y: bool,
+    fade_half_life_ms: u32,
 }
 
 impl Default for Screen {
@@ -99,17 +153,29 @@ impl Default for Screen {
         let data = vec![0; VIDEO_MEMORY_SIZE];
         let alpha = vec![0; VIDEO_MEMORY_SIZE];
 
-        Screen {
-            data: ScreenData {
-                data,
-                alpha,
-                mode: ScreenMode::Standard,
-                scroll: ScreenScroll {
-                    scrolling: false,
-                    lines: 0,
-                    direction: ScreenScrollDirection::Disabled,
-                },
+        let data = ScreenData {
+            data,
+            alpha,
+            mode: ScreenMode::Standard,
+            scroll: ScreenScroll {
+                scrolling: false,
+                lines: 0,
+                direction: ScreenScrollDirection::Disabled,
             },
+        };
+
+        Screen {
+            front: data.clone(),
+            data,
+            dirty: BTreeSet::new(),
+            full_redraw: true,
+            pending_dirty: BTreeSet::new(),
+            pending_full_redraw: true,
+            last_collision: 0,
+            clear_on_mode_switch: true,
+            wrap_x: true,
+            wrap_y: true,
+            fade_half_life_ms: DEFAULT_FADE_HALF_LIFE_MS,
         }
     }
 }
@@ -125,6 +191,98 @@ impl Screen {
         Default::default()
     }
 
+    /// Set whether the screen is cleared on mode switch.
+    ///
+    /// When disabled, pixels are scaled to the new resolution instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value.
+    ///
+    pub fn set_clear_on_mode_switch(&mut self, value: bool) {
+        self.clear_on_mode_switch = value;
+    }
+
+    /// Check if the screen is cleared on mode switch.
+    ///
+    /// # Returns
+    ///
+    /// * `true` if cleared.
+    /// * `false` if scaled.
+    ///
+    pub fn get_clear_on_mode_switch(&self) -> bool {
+        self.clear_on_mode_switch
+    }
+
+    /// Set whether sprites wrap around the horizontal edges.
+    ///
+    /// When disabled, sprite pixels past the right edge are clipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value.
+    ///
+    pub fn set_wrap_x(&mut self, value: bool) {
+        self.wrap_x = value;
+    }
+
+    /// Set whether sprites wrap around the vertical edges.
+    ///
+    /// When disabled, sprite pixels past the bottom edge are clipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value.
+    ///
+    pub fn set_wrap_y(&mut self, value: bool) {
+        self.wrap_y = value;
+    }
+
+    /// Check if sprites wrap around the horizontal edges.
+    ///
+    /// # Returns
+    ///
+    /// * `true` if wrapping.
+    /// * `false` if clipping.
+    ///
+    pub fn get_wrap_x(&self) -> bool {
+        self.wrap_x
+    }
+
+    /// Check if sprites wrap around the vertical edges.
+    ///
+    /// # Returns
+    ///
+    /// * `true` if wrapping.
+    /// * `false` if clipping.
+    ///
+    pub fn get_wrap_y(&self) -> bool {
+        self.wrap_y
+    }
+
+    /// Set fade half-life.
+    ///
+    /// Time for an erased pixel to fade to half its intensity, independent
+    /// of CPU speed. `0` disables the fade effect.
+    ///
+    /// # Arguments
+    ///
+    /// * `ms` - Half-life in milliseconds.
+    ///
+    pub fn set_fade_half_life(&mut self, ms: u32) {
+        self.fade_half_life_ms = ms;
+    }
+
+    /// Get fade half-life.
+    ///
+    /// # Returns
+    ///
+    /// * Half-life in milliseconds.
+    ///
+    pub fn get_fade_half_life(&self) -> u32 {
+        self.fade_half_life_ms
+    }
+
     /// Reload screen for mode.
     ///
     /// # Arguments
@@ -132,11 +290,68 @@ impl Screen {
     /// * `mode` - Screen mode.
     ///
     pub fn reload_screen_for_mode(&mut self, mode: ScreenMode) {
+        debug!(
+            target: "chip8::screen",
+            message = "Screen mode changed.",
+            mode = ?mode
+        );
+
+        let prev_coef = self.get_screen_size_coef();
+        let prev_data = std::mem::take(&mut self.data.data);
         self.data.mode = mode;
 
         let coef = self.get_screen_size_coef();
         self.data.data = vec![0; VIDEO_MEMORY_SIZE * coef * coef];
         self.data.alpha = vec![0; VIDEO_MEMORY_SIZE * coef * coef];
+
+        if !self.clear_on_mode_switch {
+            let width = VIDEO_MEMORY_WIDTH * coef;
+            let prev_width = VIDEO_MEMORY_WIDTH * prev_coef;
+
+            for pos in 0..self.data.data.len() {
+                // Map each new pixel to its source pixel in the previous resolution.
+                let x = (pos % width) * prev_coef / coef;
+                let y = (pos / width) * prev_coef / coef;
+                let pixel = prev_data[x + y * prev_width];
+                if pixel != 0 {
+                    self.data.data[pos] = pixel;
+                    self.data.alpha[pos] = 255;
+                }
+            }
+        }
+
+        self.invalidate();
+    }
+
+    /// Mark the whole screen for redraw on next dirty render.
+    pub fn invalidate(&mut self) {
+        self.pending_dirty.clear();
+        self.pending_full_redraw = true;
+    }
+
+    /// Present the back buffer, making it visible to rendering.
+    pub fn present(&mut self) {
+        self.front = self.data.clone();
+
+        if self.pending_full_redraw {
+            self.dirty.clear();
+            self.full_redraw = true;
+        } else {
+            self.dirty.append(&mut self.pending_dirty);
+        }
+
+        self.pending_dirty.clear();
+        self.pending_full_redraw = false;
+    }
+
+    /// Take a snapshot of the presented frame.
+    ///
+    /// # Returns
+    ///
+    /// * Front buffer data.
+    ///
+    pub fn snapshot(&self) -> ScreenData {
+        self.front.clone()
     }
 
     /// Get screen size coef.
@@ -146,10 +361,7 @@ impl Screen {
     /// * Screen size coef.
     ///
     fn get_screen_size_coef(&self) -> usize {
-        match self.data.mode {
-            ScreenMode::Standard => 1,
-            ScreenMode::Extended => 2,
-        }
+        get_screen_size_coef(&self.data.mode)
     }
 
     /// Check if screen is scrolling.
@@ -173,24 +385,28 @@ impl Screen {
     ///
     /// # Returns
     ///
-    /// `true` if collision.
-    /// `false` if not.
+    /// * Number of collided pixels.
     ///
-    pub fn draw_sprite(&mut self, r1: C8Byte, r2: C8Byte, sprite: &[C8Byte]) -> bool {
+    pub fn draw_sprite(&mut self, r1: C8Byte, r2: C8Byte, sprite: &[C8Byte]) -> u8 {
         let coef = self.get_screen_size_coef();
 
         let byte = sprite.len();
-        let mut collision = false;
+        let mut collisions = 0;
 
         for (i, code) in sprite.iter().enumerate().take(byte) {
-            let y = ((r2 as usize) + (i as usize)) % (VIDEO_MEMORY_HEIGHT * coef);
+            let y = match sprite_coord(r2, i, VIDEO_MEMORY_HEIGHT * coef, self.wrap_y) {
+                Some(y) => y,
+                None => continue,
+            };
             let mut shift = SPRITE_WIDTH - 1;
 
             for j in 0..SPRITE_WIDTH {
-                let x = ((r1 as usize) + (j as usize)) % (VIDEO_MEMORY_WIDTH * coef);
+                let x = sprite_coord(r1, j as usize, VIDEO_MEMORY_WIDTH * coef, self.wrap_x);
 
-                if code & (0x1 << shift) != 0 && self.toggle_pixel_xy(x, y) {
-                    collision = true;
+                if let Some(x) = x {
+                    if code & (0x1 << shift) != 0 && self.toggle_pixel_xy(x, y) {
+                        collisions += 1;
+                    }
                 }
 
                 if shift > 0 {
@@ -199,11 +415,14 @@ impl Screen {
             }
         }
 
-        collision
+        self.last_collision = collisions;
+        collisions
     }
 
     /// Draw super sprite.
     ///
+    /// Super sprites are 16x16, with 2 bytes per row.
+    ///
     /// # Arguments
     ///
     /// * `r1` - X position.
@@ -212,33 +431,87 @@ impl Screen {
     ///
     /// # Returns
     ///
-    /// `true` if collision.
-    /// `false` if not.
+    /// * Number of rows with at least one collided pixel.
     ///
-    pub fn draw_super_sprite(&mut self, r1: C8Byte, r2: C8Byte, sprite: &[C8Byte]) -> bool {
+    pub fn draw_super_sprite(&mut self, r1: C8Byte, r2: C8Byte, sprite: &[C8Byte]) -> u8 {
         let coef = self.get_screen_size_coef();
+        let mut collided_rows = 0;
 
-        let byte = sprite.len();
-        let mut collision = false;
-
-        for (i, code) in sprite.iter().enumerate().take(byte) {
-            let y = ((r2 as usize) + (i as usize)) % (VIDEO_MEMORY_HEIGHT * coef);
-            let mut shift = SUPER_SPRITE_WIDTH - 1;
+        for (i, row) in sprite.chunks(2).enumerate() {
+            let code = row
+                .iter()
+                .fold(0u16, |acc, byte| (acc << 8) | u16::from(*byte))
+                << (8 * (2 - row.len()));
+            let y = match sprite_coord(r2, i, VIDEO_MEMORY_HEIGHT * coef, self.wrap_y) {
+                Some(y) => y,
+                None => continue,
+            };
+            let mut collision = false;
 
             for j in 0..SUPER_SPRITE_WIDTH {
-                let x = ((r1 as usize) + (j as usize)) % (VIDEO_MEMORY_WIDTH * coef);
+                let x = match sprite_coord(r1, j as usize, VIDEO_MEMORY_WIDTH * coef, self.wrap_x) {
+                    Some(x) => x,
+                    None => continue,
+                };
+                let shift = SUPER_SPRITE_WIDTH - 1 - j;
 
                 if code & (0x1 << shift) != 0 && self.toggle_pixel_xy(x, y) {
                     collision = true;
                 }
+            }
 
-                if shift > 0 {
-                    shift -= 1;
-                }
+            if collision {
+                collided_rows += 1;
+            }
+        }
+
+        self.last_collision = collided_rows;
+        collided_rows
+    }
+
+    /// Get last collision.
+    ///
+    /// Kept until the next draw, even if VF gets overwritten.
+    ///
+    /// # Returns
+    ///
+    /// * Collided pixels (or rows for super sprites) of the last draw.
+    ///
+    pub fn last_collision(&self) -> u8 {
+        self.last_collision
+    }
+
+    /// Render screen as ASCII art.
+    ///
+    /// Lit pixels are rendered as `#`, others as `.`, one line per row.
+    ///
+    /// # Returns
+    ///
+    /// * ASCII art.
+    ///
+    pub fn to_ascii(&self) -> String {
+        let width = VIDEO_MEMORY_WIDTH * self.get_screen_size_coef();
+        let mut output = String::with_capacity(self.data.data.len() + self.data.data.len() / width);
+
+        for row in self.data.data.chunks(width) {
+            for pixel in row {
+                output.push(if *pixel == 0 { '.' } else { '#' });
             }
+
+            output.push('\n');
         }
 
-        collision
+        output
+    }
+
+    /// Get screen mode.
+    ///
+    /// # Returns
+    ///
+    /// * Screen mode.
+    ///
+    pub fn get_mode(&self) -> ScreenMode {
+        self.data.mode.clone()
     }
 
     /// Clear screen.
@@ -246,13 +519,46 @@ impl Screen {
         for x in 0..self.data.data.len() {
             self.data.data[x] = 0
         }
+
+        self.invalidate();
     }
 
-    /// Fade pixels.
-    pub fn fade_pixels(&mut self) {
+    /// Fill screen with random pixels.
+    pub fn randomize(&mut self) {
+        for x in 0..self.data.data.len() {
+            self.data.data[x] = gen_range(0, 2);
+            self.data.alpha[x] = 255;
+        }
+
+        self.invalidate();
+    }
+
+    /// Advance the fade effect.
+    ///
+    /// Erased pixels fade with real time, whatever the CPU speed.
+    ///
+    /// # Arguments
+    ///
+    /// * `delta` - Time elapsed since last tick.
+    ///
+    pub fn tick(&mut self, delta: Duration) {
+        let coefficient = if self.fade_half_life_ms == 0 {
+            0.0
+        } else {
+            0.5f32.powf(delta.as_secs_f32() * 1000.0 / self.fade_half_life_ms as f32)
+        };
+        let fade = |alpha: C8Byte| (f32::from(alpha) * coefficient).round() as C8Byte;
+
         for x in 0..self.data.data.len() {
             if self.data.data[x] == 0 && self.data.alpha[x] > 0 {
-                self.data.alpha[x] = (f32::from(self.data.alpha[x]) * PIXEL_FADE_COEFFICIENT) as u8;
+                self.data.alpha[x] = fade(self.data.alpha[x]);
+            }
+        }
+
+        for x in 0..self.front.data.len() {
+            if self.front.data[x] == 0 && self.front.alpha[x] > 0 {
+                self.front.alpha[x] = fade(self.front.alpha[x]);
+                self.dirty.insert(x);
             }
         }
     }
@@ -303,10 +609,33 @@ impl Screen {
                     }
                 }
             }
+            ScreenScrollDirection::Up => self.scroll_up(qty),
             _ => (),
         }
 
         self.data.scroll.scrolling = false;
+        self.invalidate();
+    }
+
+    /// Scroll up.
+    ///
+    /// Bottom lines are cleared.
+    ///
+    /// # Arguments
+    ///
+    /// * `lines` - Lines to scroll.
+    ///
+    pub fn scroll_up(&mut self, lines: C8Byte) {
+        let width = VIDEO_MEMORY_WIDTH * self.get_screen_size_coef();
+        let offset = (width * lines as usize).min(self.data.data.len());
+        let data_sz = self.data.data.len();
+
+        self.data.data.copy_within(offset.., 0);
+        for pixel in &mut self.data.data[data_sz - offset..] {
+            *pixel = 0;
+        }
+
+        self.invalidate();
     }
 
     /// Toggle pixel position.
@@ -324,6 +653,7 @@ impl Screen {
         // For now, only handle 0 and 1.
         let mut flip = false;
         let pixel = self.data.data[pos];
+        self.pending_dirty.insert(pos);
 
         if pixel == 1 {
             self.data.data[pos] = 0;
@@ -345,21 +675,46 @@ impl Screen {
         frame_width: usize,
         driver: &mut impl RenderInterface,
     ) -> CResult {
-        let scale = match self.data.mode {
-            ScreenMode::Standard => RENDERER_SCALE,
-            ScreenMode::Extended => RENDERER_SCALE / 2,
-        };
-        let coef = self.get_screen_size_coef();
+        let scale = get_renderer_scale(&self.front.mode);
+        let coef = get_screen_size_coef(&self.front.mode);
 
-        for (pos, px) in self.data.data.iter().enumerate() {
+        for (pos, px) in self.front.data.iter().enumerate() {
             let x = pos % (VIDEO_MEMORY_WIDTH * coef);
             let y = pos / (VIDEO_MEMORY_WIDTH * coef);
-            let alpha = &self.data.alpha[pos];
+            let alpha = &self.front.alpha[pos];
             let color = color_from_byte(*px, *alpha);
             driver.render_pixel(origin_x, origin_y, x, y, scale, color, frame_width)?;
         }
 
-        self.fade_pixels();
+        self.dirty.clear();
+        self.full_redraw = false;
+
+        Ok(())
+    }
+
+    /// Render pixels changed since last render.
+    ///
+    /// Falls back to a full render after a screen invalidation.
+    pub fn render_pixels_dirty(
+        &mut self,
+        origin_x: u32,
+        origin_y: u32,
+        frame_width: usize,
+        driver: &mut impl RenderInterface,
+    ) -> CResult {
+        if self.full_redraw {
+            return self.render_pixels(origin_x, origin_y, frame_width, driver);
+        }
+
+        let scale = get_renderer_scale(&self.front.mode);
+        let coef = get_screen_size_coef(&self.front.mode);
+
+        for pos in std::mem::take(&mut self.dirty) {
+            let x = pos % (VIDEO_MEMORY_WIDTH * coef);
+            let y = pos / (VIDEO_MEMORY_WIDTH * coef);
+            let color = color_from_byte(self.front.data[pos], self.front.alpha[pos]);
+            driver.render_pixel(origin_x, origin_y, x, y, scale, color, frame_width)?;
+        }
 
         Ok(())
     }
@@ -382,10 +737,20 @@ impl Screen {
     }
 
     /// Reset screen.
+    ///
+    /// Restore the power-on display state (standard resolution, no scroll,
+    /// blank buffers), keeping configuration.
+    /// Use `clear_screen` to only clear pixels.
     pub fn reset(&mut self) {
-        self.data.data = vec![0; VIDEO_MEMORY_SIZE];
-        self.data.alpha = vec![255; VIDEO_MEMORY_SIZE];
-        self.data.mode = ScreenMode::Standard;
+        let configured = Self {
+            clear_on_mode_switch: self.clear_on_mode_switch,
+            wrap_x: self.wrap_x,
+            wrap_y: self.wrap_y,
+            fade_half_life_ms: self.fade_half_life_ms,
+            ..Self::default()
+        };
+
+        *self = configured;
     }
 
     /// Load from save.
@@ -398,6 +763,7 @@ impl Screen {
         self.data.data = screen_data.data;
         self.data.alpha = screen_data.alpha;
         self.data.mode = screen_data.mode;
+        self.invalidate();
     }
 }
 
@@ -430,9 +796,342 @@ impl fmt::Debug for Screen {
     }
 }
 
+/// Get sprite pixel coordinate on one axis.
+///
+/// The sprite origin always wraps; pixels past the edge wrap or are clipped.
+///
+/// # Arguments
+///
+/// * `origin` - Sprite origin.
+/// * `offset` - Pixel offset in sprite.
+/// * `size` - Screen size on axis.
+/// * `wrap` - Wrap past the edge.
+///
+/// # Returns
+///
+/// * Coordinate, or `None` if clipped.
+///
+fn sprite_coord(origin: C8Byte, offset: usize, size: usize, wrap: bool) -> Option<usize> {
+    let coord = (origin as usize) % size + offset;
+
+    if coord < size {
+        Some(coord)
+    } else if wrap {
+        Some(coord % size)
+    } else {
+        None
+    }
+}
+
+fn get_screen_size_coef(mode: &ScreenMode) -> usize {
+    match mode {
+        ScreenMode::Standard => 1,
+        ScreenMode::Extended => 2,
+    }
+}
+
+fn get_renderer_scale(mode: &ScreenMode) -> usize {
+    match mode {
+        ScreenMode::Standard => RENDERER_SCALE,
+        ScreenMode::Extended => RENDERER_SCALE / 2,
+    }
+}
+
 fn color_from_byte(byte: C8Byte, alpha: C8Byte) -> Color {
     match byte {
         0 => Color::from_rgb(alpha, alpha, alpha),
         _ => Color::from_rgb(255, 255, 255),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingDriver {
+        pixels: Vec<(usize, usize)>,
+        lit: Vec<(usize, usize)>,
+    }
+
+    impl RenderInterface for RecordingDriver {
+        fn render_pixel(
+            &mut self,
+            _origin_x: u32,
+            _origin_y: u32,
+            x: usize,
+            y: usize,
+            _scale: usize,
+            color: Color,
+            _frame_width: usize,
+        ) -> CResult {
+            self.pixels.push((x, y));
+            if color.r == 255 {
+                self.lit.push((x, y));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_render_pixels_dirty() {
+        let mut screen = Screen::new();
+        let mut driver = RecordingDriver::default();
+
+        screen.draw_sprite(0, 0, &[0b1100_0000]);
+        screen.present();
+        screen.render_pixels_dirty(0, 0, 0, &mut driver).unwrap();
+        assert_eq!(driver.pixels.len(), VIDEO_MEMORY_SIZE);
+
+        driver.pixels.clear();
+        screen.draw_sprite(10, 5, &[0b1000_0000, 0b1000_0000]);
+        screen.present();
+        screen.render_pixels_dirty(0, 0, 0, &mut driver).unwrap();
+        assert_eq!(driver.pixels, vec![(10, 5), (10, 6)]);
+
+        driver.pixels.clear();
+        screen.clear_screen();
+        screen.present();
+        screen.render_pixels_dirty(0, 0, 0, &mut driver).unwrap();
+        assert_eq!(driver.pixels.len(), VIDEO_MEMORY_SIZE);
+    }
+
+    #[test]
+    fn test_draw_sprite_collision_count() {
+        let mut screen = Screen::new();
+
+        assert_eq!(screen.draw_sprite(0, 0, &[0b1111_0000]), 0);
+        assert_eq!(screen.draw_sprite(2, 0, &[0b1111_0000, 0b1000_0000]), 2);
+        assert_eq!(screen.draw_sprite(0, 1, &[0b1000_0000]), 0);
+        assert_eq!(screen.draw_sprite(0, 0, &[0b1111_1111, 0b1000_0000]), 5);
+    }
+
+    #[test]
+    fn test_draw_super_sprite_collided_rows() {
+        let mut screen = Screen::new();
+        screen.reload_screen_for_mode(ScreenMode::Extended);
+
+        let sprite = [0xFF; 32];
+        assert_eq!(screen.draw_super_sprite(0, 0, &sprite), 0);
+        screen.clear_screen();
+
+        assert_eq!(screen.draw_sprite(8, 3, &[0x01; 4]), 0);
+        assert_eq!(screen.draw_super_sprite(0, 0, &sprite), 4);
+    }
+
+    #[test]
+    fn test_mode_switch_clears_screen() {
+        let mut screen = Screen::new();
+        assert!(screen.get_clear_on_mode_switch());
+        screen.toggle_pixel_xy(3, 2);
+
+        screen.reload_screen_for_mode(ScreenMode::Extended);
+        assert!(screen.data.get_pixels().iter().all(|&p| p == 0));
+    }
+
+    #[test]
+    fn test_mode_switch_scales_screen() {
+        let mut screen = Screen::new();
+        screen.set_clear_on_mode_switch(false);
+        screen.toggle_pixel_xy(3, 2);
+
+        screen.reload_screen_for_mode(ScreenMode::Extended);
+        let width = VIDEO_MEMORY_WIDTH * 2;
+        let lit: Vec<_> = screen
+            .data
+            .get_pixels()
+            .iter()
+            .enumerate()
+            .filter(|(_, &p)| p != 0)
+            .map(|(pos, _)| (pos % width, pos / width))
+            .collect();
+        assert_eq!(lit, vec![(6, 4), (7, 4), (6, 5), (7, 5)]);
+
+        screen.reload_screen_for_mode(ScreenMode::Standard);
+        let lit: Vec<_> = screen
+            .data
+            .get_pixels()
+            .iter()
+            .enumerate()
+            .filter(|(_, &p)| p != 0)
+            .map(|(pos, _)| (pos % VIDEO_MEMORY_WIDTH, pos / VIDEO_MEMORY_WIDTH))
+            .collect();
+        assert_eq!(lit, vec![(3, 2)]);
+    }
+
+    fn lit_pixels(screen: &Screen) -> Vec<(usize, usize)> {
+        let width = VIDEO_MEMORY_WIDTH * get_screen_size_coef(&screen.get_mode());
+        screen
+            .data
+            .get_pixels()
+            .iter()
+            .enumerate()
+            .filter(|(_, &p)| p != 0)
+            .map(|(pos, _)| (pos % width, pos / width))
+            .collect()
+    }
+
+    #[test]
+    fn test_sprite_wrap_axes() {
+        // 2x2 sprite drawn across the bottom-right corner.
+        let sprite = [0b1100_0000, 0b1100_0000];
+        let (x, y) = (VIDEO_MEMORY_WIDTH as u8 - 1, VIDEO_MEMORY_HEIGHT as u8 - 1);
+
+        let mut screen = Screen::new();
+        screen.draw_sprite(x, y, &sprite);
+        assert_eq!(
+            lit_pixels(&screen),
+            vec![(0, 0), (63, 0), (0, 31), (63, 31)]
+        );
+
+        let mut screen = Screen::new();
+        screen.set_wrap_x(false);
+        screen.draw_sprite(x, y, &sprite);
+        assert_eq!(lit_pixels(&screen), vec![(63, 0), (63, 31)]);
+
+        let mut screen = Screen::new();
+        screen.set_wrap_y(false);
+        screen.draw_sprite(x, y, &sprite);
+        assert_eq!(lit_pixels(&screen), vec![(0, 31), (63, 31)]);
+
+        let mut screen = Screen::new();
+        screen.set_wrap_x(false);
+        screen.set_wrap_y(false);
+        screen.draw_sprite(x, y, &sprite);
+        assert_eq!(lit_pixels(&screen), vec![(63, 31)]);
+    }
+
+    #[test]
+    fn test_super_sprite_wrap_axes() {
+        let mut sprite = [0; 32];
+        sprite[0] = 0b1000_0000;
+        sprite[1] = 0b0000_0001;
+        sprite[30] = 0b1000_0000;
+
+        let mut screen = Screen::new();
+        screen.reload_screen_for_mode(ScreenMode::Extended);
+        screen.set_wrap_x(false);
+        screen.draw_super_sprite(120, 60, &sprite);
+        assert_eq!(lit_pixels(&screen), vec![(120, 11), (120, 60)]);
+
+        let mut screen = Screen::new();
+        screen.reload_screen_for_mode(ScreenMode::Extended);
+        screen.set_wrap_y(false);
+        screen.draw_super_sprite(120, 60, &sprite);
+        assert_eq!(lit_pixels(&screen), vec![(7, 60), (120, 60)]);
+    }
+
+    #[test]
+    fn test_reset_restores_display_state() {
+        let mut screen = Screen::new();
+        screen.set_wrap_x(false);
+        screen.reload_screen_for_mode(ScreenMode::Extended);
+        screen.toggle_pixel_xy(100, 50);
+        screen.data.scroll.scrolling = true;
+        screen.data.scroll.lines = 4;
+        screen.data.scroll.direction = ScreenScrollDirection::Down;
+        screen.present();
+
+        screen.reset();
+
+        assert_eq!(screen.get_mode(), ScreenMode::Standard);
+        assert_eq!(screen.data.get_pixels().len(), VIDEO_MEMORY_SIZE);
+        assert!(screen.data.get_pixels().iter().all(|&p| p == 0));
+        assert!(!screen.is_scrolling());
+        assert_eq!(screen.data.scroll.lines, 0);
+        assert_eq!(screen.snapshot().get_mode(), ScreenMode::Standard);
+        assert_eq!(screen.last_collision(), 0);
+        assert!(!screen.get_wrap_x());
+    }
+
+    #[test]
+    fn test_scroll_up() {
+        let mut screen = Screen::new();
+        screen.toggle_pixel_xy(3, 2);
+        screen.toggle_pixel_xy(5, 0);
+        screen.toggle_pixel_xy(7, VIDEO_MEMORY_HEIGHT - 1);
+
+        screen.data.scroll.scrolling = true;
+        screen.data.scroll.lines = 2;
+        screen.data.scroll.direction = ScreenScrollDirection::Up;
+        screen.apply_scroll();
+
+        assert_eq!(
+            lit_pixels(&screen),
+            vec![(3, 0), (7, VIDEO_MEMORY_HEIGHT - 3)]
+        );
+        assert!(!screen.is_scrolling());
+
+        screen.scroll_up(VIDEO_MEMORY_HEIGHT as C8Byte + 1);
+        assert!(lit_pixels(&screen).is_empty());
+    }
+
+    #[test]
+    fn test_to_ascii() {
+        let mut screen = Screen::new();
+        screen.draw_sprite(1, 0, &[0b1010_0000]);
+
+        let ascii = screen.to_ascii();
+        let lines: Vec<_> = ascii.lines().collect();
+        assert_eq!(lines.len(), VIDEO_MEMORY_HEIGHT);
+        assert_eq!(&lines[0][..5], ".#.#.");
+        assert_eq!(lines[1], ".".repeat(VIDEO_MEMORY_WIDTH));
+
+        screen.reload_screen_for_mode(ScreenMode::Extended);
+        assert_eq!(screen.to_ascii().lines().count(), VIDEO_MEMORY_HEIGHT * 2);
+    }
+
+    #[test]
+    fn test_present_hides_back_buffer_writes() {
+        let mut screen = Screen::new();
+        let mut driver = RecordingDriver::default();
+
+        screen.draw_sprite(1, 0, &[0b1000_0000]);
+        screen.present();
+
+        // Mid-frame scroll on the back buffer.
+        screen.data.scroll.scrolling = true;
+        screen.data.scroll.lines = 1;
+        screen.data.scroll.direction = ScreenScrollDirection::Down;
+        screen.apply_scroll();
+
+        screen.render_pixels(0, 0, 0, &mut driver).unwrap();
+        assert_eq!(driver.lit, vec![(1, 0)]);
+
+        driver.lit.clear();
+        screen.present();
+        screen.render_pixels(0, 0, 0, &mut driver).unwrap();
+        assert!(driver.lit.contains(&(1, 1)));
+    }
+
+    #[test]
+    fn test_fade_half_life() {
+        let mut screen = Screen::new();
+        screen.set_fade_half_life(100);
+        screen.draw_sprite(0, 0, &[0b1000_0000]);
+        screen.draw_sprite(0, 0, &[0b1000_0000]);
+        screen.present();
+        assert_eq!(screen.front.alpha[0], 255);
+
+        // Ten 10 ms frames reach the 100 ms half-life.
+        for _ in 0..10 {
+            screen.tick(Duration::from_millis(10));
+        }
+        assert!((126..=129).contains(&screen.front.alpha[0]));
+
+        // Same decay in one big step: frame rate does not matter.
+        screen.tick(Duration::from_millis(100));
+        assert!((62..=65).contains(&screen.front.alpha[0]));
+
+        // Lit pixels do not fade.
+        screen.draw_sprite(1, 0, &[0b1000_0000]);
+        screen.present();
+        screen.tick(Duration::from_millis(100));
+        assert_eq!(screen.front.alpha[1], 255);
+
+        // No half-life: no fade effect.
+        screen.set_fade_half_life(0);
+        screen.tick(Duration::from_millis(1));
+        assert_eq!(screen.front.alpha[0], 0);
+    }
+}
@@ -3,8 +3,13 @@
 use crate::drivers::AudioInterface;
 
 /// Sound peripheral.
+///
+/// Gates the audio driver: a sustained beep starts the driver once and
+/// stops it once, instead of restarting it on each timer decrement.
 #[derive(Default)]
-pub struct Sound;
+pub struct Sound {
+    gate_open: bool,
+}
 
 impl Sound {
     /// New sound peripheral.
@@ -12,8 +17,39 @@ impl Sound {
         Default::default()
     }
 
-    /// Play beep.
-    pub fn play_beep(&self, driver: &mut dyn AudioInterface) {
-        driver.play_beep()
+    /// Open the beep gate.
+    ///
+    /// Starts the driver if the gate was closed.
+    pub fn on_beep_start(&mut self, driver: &mut dyn AudioInterface) {
+        if !self.gate_open {
+            self.gate_open = true;
+            driver.play_beep();
+        }
+    }
+
+    /// Close the beep gate.
+    ///
+    /// Stops the driver if the gate was open.
+    pub fn on_beep_stop(&mut self, driver: &mut dyn AudioInterface) {
+        if self.gate_open {
+            self.stop(driver);
+        }
+    }
+
+    /// Stop beep, whatever the gate state.
+    pub fn stop(&mut self, driver: &mut dyn AudioInterface) {
+        self.gate_open = false;
+        driver.stop_beep()
+    }
+
+    /// Check if the beep gate is open.
+    ///
+    /// # Returns
+    ///
+    /// * `true` if open.
+    /// * `false` if not.
+    ///
+    pub fn is_gate_open(&self) -> bool {
+        self.gate_open
     }
 }
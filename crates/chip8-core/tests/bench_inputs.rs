@@ -0,0 +1,33 @@
+//! Check that benchmark inputs stay valid.
+
+#[path = "../benches/inputs.rs"]
+mod inputs;
+
+use chip8_core::{
+    core::opcodes::{get_opcode_enum, get_opcode_str},
+    peripherals::screen::Screen,
+};
+use inputs::{OPCODES, SPRITE, SPRITE_LIT_PIXELS};
+
+#[test]
+fn test_opcodes_decode() {
+    for (opcode, expected) in OPCODES {
+        let (assembly, _) = get_opcode_str(&get_opcode_enum(*opcode));
+        assert_eq!(&assembly, expected, "opcode {:04X}", opcode);
+    }
+}
+
+#[test]
+fn test_sprite_draw() {
+    let mut screen = Screen::new();
+    assert_eq!(screen.draw_sprite(0, 0, &SPRITE), 0);
+
+    let lit = screen.to_ascii().chars().filter(|c| *c == '#').count();
+    assert_eq!(lit, SPRITE_LIT_PIXELS);
+
+    // Drawing again erases the sprite, colliding on every lit pixel.
+    assert_eq!(
+        screen.draw_sprite(0, 0, &SPRITE) as usize,
+        SPRITE_LIT_PIXELS
+    );
+}
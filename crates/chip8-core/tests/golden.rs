@@ -0,0 +1,55 @@
+//! Compare rendered frames with golden snapshots.
+//!
+//! Run with `CHIP8_UPDATE_GOLDEN=1` to regenerate snapshots.
+
+use std::{env, fs, path::PathBuf};
+
+use chip8_core::{
+    emulator::Emulator,
+    peripherals::{cartridge::Cartridge, screen::VIDEO_MEMORY_WIDTH},
+};
+
+fn to_ascii(pixels: &[u8]) -> String {
+    let width = if pixels.len() > VIDEO_MEMORY_WIDTH * 32 {
+        VIDEO_MEMORY_WIDTH * 2
+    } else {
+        VIDEO_MEMORY_WIDTH
+    };
+
+    pixels
+        .chunks(width)
+        .map(|row| {
+            row.iter()
+                .map(|&p| if p == 0 { '.' } else { '#' })
+                .chain(std::iter::once('\n'))
+                .collect::<String>()
+        })
+        .collect()
+}
+
+fn check_golden(game: &str, frames: usize) {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let cartridge = Cartridge::load_from_path(root.join("../../games").join(game)).unwrap();
+    let golden_path = root.join("tests/golden").join(game).with_extension("txt");
+
+    let mut emulator = Emulator::new();
+    emulator.load_game(&cartridge).unwrap();
+    let frame = to_ascii(&emulator.run_and_capture(frames));
+
+    if env::var_os("CHIP8_UPDATE_GOLDEN").is_some() {
+        fs::write(&golden_path, &frame).unwrap();
+    }
+
+    let golden = fs::read_to_string(&golden_path).unwrap();
+    assert!(
+        frame == golden,
+        "frame differs from {:?}:\n{}",
+        golden_path,
+        frame
+    );
+}
+
+#[test]
+fn test_golden_test_rom() {
+    check_golden("test.ch8", 60);
+}
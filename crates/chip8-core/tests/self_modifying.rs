@@ -0,0 +1,49 @@
+//! Check that code written at runtime is executed.
+
+use chip8_core::{
+    core::assembler::Assembler,
+    emulator::{EmulationState, EmulatorBuilder},
+};
+
+const PROGRAM: &str = r#"
+LD V0, 12       ; high byte of JP 020E
+LD V1, 0E       ; low byte of JP 020E
+LD I, 020A
+LD [I], V1      ; overwrite the instruction at 020A
+JP 020A
+LD V5, FF       ; replaced by JP 020E
+LD V5, EE
+LD V6, 42
+JP 0210
+"#;
+
+#[test]
+fn test_execute_written_opcode() {
+    let cartridge = Assembler::from_string(PROGRAM)
+        .assemble_cartridge()
+        .unwrap();
+    let (mut emulator, mut ctx) = EmulatorBuilder::new()
+        .with_cartridge(cartridge)
+        .build()
+        .unwrap();
+
+    for _ in 0..1000 {
+        if emulator.cpu.peripherals.memory.get_pointer() == 0x0210 {
+            break;
+        }
+
+        assert!(matches!(emulator.step(&mut ctx), EmulationState::Normal));
+    }
+
+    assert_eq!(emulator.cpu.peripherals.memory.get_pointer(), 0x0210);
+    assert_eq!(
+        emulator
+            .cpu
+            .peripherals
+            .memory
+            .read_opcode_at_address(0x020A),
+        0x120E
+    );
+    assert_eq!(emulator.cpu.registers.get_register(0x5), 0x00);
+    assert_eq!(emulator.cpu.registers.get_register(0x6), 0x42);
+}
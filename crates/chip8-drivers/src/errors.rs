@@ -0,0 +1,65 @@
+//! Driver errors.
+
+use std::{any::Any, error::Error, fmt};
+
+/// Rendering surface creation error.
+#[derive(Debug)]
+pub struct SurfaceError(pub String);
+
+impl Error for SurfaceError {
+    fn description(&self) -> &str {
+        "surface error"
+    }
+}
+
+impl fmt::Display for SurfaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "could not create the rendering surface: {}. Try again with `--software` to use the terminal renderer.",
+            self.0
+        )
+    }
+}
+
+/// Build a surface error from a window creation panic.
+///
+/// # Arguments
+///
+/// * `payload` - Panic payload.
+///
+/// # Returns
+///
+/// * Surface error.
+///
+pub fn surface_error_from_panic(payload: Box<dyn Any + Send>) -> SurfaceError {
+    let cause = if let Some(s) = payload.downcast_ref::<&str>() {
+        (*s).to_owned()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown error".to_owned()
+    };
+
+    SurfaceError(cause)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_surface_error_from_panic() {
+        let error = surface_error_from_panic(Box::new("EGL context creation failed"));
+        assert_eq!(
+            error.to_string(),
+            "could not create the rendering surface: EGL context creation failed. Try again with `--software` to use the terminal renderer."
+        );
+
+        let error = surface_error_from_panic(Box::new(String::from("no adapter")));
+        assert_eq!(error.0, "no adapter");
+
+        let error = surface_error_from_panic(Box::new(42));
+        assert_eq!(error.0, "unknown error");
+    }
+}
@@ -0,0 +1,225 @@
+//! Keyboard mappings.
+
+use chip8_core::{core::types::C8Byte, peripherals::input::InputState};
+use macroquad::prelude::KeyCode;
+
+/// Keyboard mapping, from physical keys to CHIP-8 keys.
+///
+/// Several physical keys can drive the same CHIP-8 key, e.g. when merging a
+/// second player map into the primary one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyMap {
+    bindings: Vec<(KeyCode, C8Byte)>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self::primary()
+    }
+}
+
+impl KeyMap {
+    /// Create empty key map.
+    ///
+    /// # Returns
+    ///
+    /// * Key map.
+    ///
+    pub fn empty() -> Self {
+        Self {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Primary key map, on the left side of a QWERTY keyboard.
+    ///
+    /// ```text
+    /// 1 2 3 4    1 2 3 C
+    /// Q W E R    4 5 6 D
+    /// A S D F    7 8 9 E
+    /// Z X C V    A 0 B F
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// * Key map.
+    ///
+    pub fn primary() -> Self {
+        Self::empty()
+            .bind(KeyCode::Key1, 0x1)
+            .bind(KeyCode::Key2, 0x2)
+            .bind(KeyCode::Key3, 0x3)
+            .bind(KeyCode::Key4, 0xC)
+            .bind(KeyCode::Q, 0x4)
+            .bind(KeyCode::W, 0x5)
+            .bind(KeyCode::E, 0x6)
+            .bind(KeyCode::R, 0xD)
+            .bind(KeyCode::A, 0x7)
+            .bind(KeyCode::S, 0x8)
+            .bind(KeyCode::D, 0x9)
+            .bind(KeyCode::F, 0xE)
+            .bind(KeyCode::Z, 0xA)
+            .bind(KeyCode::X, 0x0)
+            .bind(KeyCode::C, 0xB)
+            .bind(KeyCode::V, 0xF)
+    }
+
+    /// Second player key map, on arrow keys and space.
+    ///
+    /// Maps onto the right column of the keypad, used by the second player
+    /// in most two-player games (e.g. `C`/`D` in Pong).
+    ///
+    /// # Returns
+    ///
+    /// * Key map.
+    ///
+    pub fn secondary() -> Self {
+        Self::empty()
+            .bind(KeyCode::Up, 0xC)
+            .bind(KeyCode::Down, 0xD)
+            .bind(KeyCode::Left, 0xE)
+            .bind(KeyCode::Right, 0xF)
+            .bind(KeyCode::Space, 0xB)
+    }
+
+    /// Two-player key map: primary and secondary maps merged.
+    ///
+    /// # Returns
+    ///
+    /// * Key map.
+    ///
+    pub fn two_player() -> Self {
+        Self::primary().merge(Self::secondary())
+    }
+
+    /// Bind a physical key to a CHIP-8 key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Physical key.
+    /// * `code` - CHIP-8 key.
+    ///
+    /// # Returns
+    ///
+    /// * Key map.
+    ///
+    pub fn bind(mut self, key: KeyCode, code: C8Byte) -> Self {
+        if !self.bindings.contains(&(key, code)) {
+            self.bindings.push((key, code));
+        }
+
+        self
+    }
+
+    /// Merge another key map.
+    ///
+    /// Bindings from both maps are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Other key map.
+    ///
+    /// # Returns
+    ///
+    /// * Key map.
+    ///
+    pub fn merge(self, other: Self) -> Self {
+        other
+            .bindings
+            .into_iter()
+            .fold(self, |map, (key, code)| map.bind(key, code))
+    }
+
+    /// Get physical keys bound to a CHIP-8 key.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - CHIP-8 key.
+    ///
+    /// # Returns
+    ///
+    /// * Physical keys.
+    ///
+    pub fn keys_for(&self, code: C8Byte) -> Vec<KeyCode> {
+        self.bindings
+            .iter()
+            .filter(|(_, c)| *c == code)
+            .map(|(k, _)| *k)
+            .collect()
+    }
+
+    /// Update input state from physical key events.
+    ///
+    /// A CHIP-8 key is released once none of its physical keys is down.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - Input state.
+    /// * `is_pressed` - Physical key was pressed this frame.
+    /// * `is_released` - Physical key was released this frame.
+    /// * `is_down` - Physical key is down.
+    ///
+    pub fn update_input_state<P, R, D>(
+        &self,
+        state: &mut InputState,
+        is_pressed: P,
+        is_released: R,
+        is_down: D,
+    ) where
+        P: Fn(KeyCode) -> bool,
+        R: Fn(KeyCode) -> bool,
+        D: Fn(KeyCode) -> bool,
+    {
+        for &(key, code) in &self.bindings {
+            if is_pressed(key) {
+                state.press(code);
+            }
+
+            if is_released(key) && !self.keys_for(code).into_iter().any(&is_down) {
+                state.release(code);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_player() {
+        let map = KeyMap::two_player();
+        assert_eq!(map.keys_for(0xC), vec![KeyCode::Key4, KeyCode::Up]);
+        assert_eq!(
+            KeyMap::secondary().merge(KeyMap::primary()).keys_for(0xC),
+            vec![KeyCode::Up, KeyCode::Key4]
+        );
+
+        let mut state = InputState::new();
+        let none = |_| false;
+
+        // Second player key.
+        map.update_input_state(&mut state, |k| k == KeyCode::Up, none, none);
+        assert!(state.is_pressed(0xC));
+        map.update_input_state(&mut state, none, |k| k == KeyCode::Up, none);
+        assert!(!state.is_pressed(0xC));
+
+        // First player key.
+        map.update_input_state(&mut state, |k| k == KeyCode::Key4, none, none);
+        assert!(state.is_pressed(0xC));
+
+        // Held by the first player: not released by the second one.
+        map.update_input_state(
+            &mut state,
+            none,
+            |k| k == KeyCode::Up,
+            |k| k == KeyCode::Key4,
+        );
+        assert!(state.is_pressed(0xC));
+
+        // Primary map only.
+        let mut state = InputState::new();
+        KeyMap::primary().update_input_state(&mut state, |k| k == KeyCode::Up, none, none);
+        assert!(!state.is_pressed(0xC));
+    }
+}
@@ -1,5 +1,13 @@
 //! CHIP-8 drivers.
 
+mod errors;
+mod keymap;
+mod mock_driver;
 mod mq_driver;
+mod term_driver;
 
+pub use errors::{surface_error_from_panic, SurfaceError};
+pub use keymap::KeyMap;
+pub use mock_driver::{AudioEvent, MockAudioDriver, ScriptedInputDriver};
 pub use mq_driver::{MQAudioDriver, MQInputDriver, MQRenderDriver, MQWindowDriver};
+pub use term_driver::TermWindowDriver;
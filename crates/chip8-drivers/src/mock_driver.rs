@@ -0,0 +1,246 @@
+//! Mock drivers, for headless tests.
+
+use std::sync::{Arc, Mutex};
+
+use chip8_core::{
+    core::types::C8Byte,
+    drivers::{AudioInterface, InputInterface},
+    peripherals::input::InputState,
+};
+
+/// Audio event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioEvent {
+    /// Beep played.
+    Play,
+    /// Beep stopped.
+    Stop,
+}
+
+/// Mock audio driver.
+///
+/// Records beep events. Clones share the same event log, so a clone can be
+/// kept to inspect events after installing the driver.
+#[derive(Clone, Default)]
+pub struct MockAudioDriver {
+    events: Arc<Mutex<Vec<AudioEvent>>>,
+}
+
+impl MockAudioDriver {
+    /// Create new mock audio driver.
+    ///
+    /// # Returns
+    ///
+    /// * Mock audio driver.
+    ///
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Get recorded events.
+    ///
+    /// # Returns
+    ///
+    /// * Events, in order.
+    ///
+    pub fn events(&self) -> Vec<AudioEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// Count played beeps.
+    ///
+    /// # Returns
+    ///
+    /// * Number of `Play` events.
+    ///
+    pub fn play_count(&self) -> usize {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| **e == AudioEvent::Play)
+            .count()
+    }
+
+    /// Check if beep is playing.
+    ///
+    /// # Returns
+    ///
+    /// * `true` if the last event is `Play`.
+    /// * `false` if not.
+    ///
+    pub fn is_playing(&self) -> bool {
+        self.events.lock().unwrap().last() == Some(&AudioEvent::Play)
+    }
+}
+
+impl AudioInterface for MockAudioDriver {
+    fn play_beep(&mut self) {
+        self.events.lock().unwrap().push(AudioEvent::Play);
+    }
+
+    fn stop_beep(&mut self) {
+        self.events.lock().unwrap().push(AudioEvent::Stop);
+    }
+}
+
+/// Scripted input driver.
+///
+/// Replays key events at given frames, each `update_input_state` call
+/// being a frame.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedInputDriver {
+    events: Vec<(usize, C8Byte, bool)>,
+    frame: usize,
+}
+
+impl ScriptedInputDriver {
+    /// Create new scripted input driver.
+    ///
+    /// # Arguments
+    ///
+    /// * `events` - Events, as `(frame, key, down)`.
+    ///
+    /// # Returns
+    ///
+    /// * Scripted input driver.
+    ///
+    pub fn new(events: Vec<(usize, C8Byte, bool)>) -> Self {
+        Self { events, frame: 0 }
+    }
+
+    /// Get current frame.
+    ///
+    /// # Returns
+    ///
+    /// * Frame count.
+    ///
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// Check if all events were replayed.
+    ///
+    /// # Returns
+    ///
+    /// * `true` if finished.
+    /// * `false` if not.
+    ///
+    pub fn is_finished(&self) -> bool {
+        self.events.iter().all(|(frame, _, _)| *frame < self.frame)
+    }
+}
+
+impl InputInterface for ScriptedInputDriver {
+    fn update_input_state(&mut self, state: &mut InputState) {
+        state.apply_pending_releases();
+
+        for &(frame, key, down) in &self.events {
+            if frame == self.frame {
+                if down {
+                    state.press(key);
+                } else {
+                    state.release(key);
+                }
+            }
+        }
+
+        self.frame += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chip8_core::{
+        core::{assembler::Assembler, cpu::CPU},
+        emulator::{EmulationState, EmulatorBuilder},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_sound_timer_beep() {
+        let audio = MockAudioDriver::new();
+        let mut cpu = CPU::new();
+        cpu.drivers.set_audio_driver(Box::new(audio.clone()));
+
+        cpu.sound_timer.reset(5);
+        for _ in 0..4 {
+            cpu.decrement_timers();
+            assert!(audio.is_playing());
+        }
+
+        // A sustained beep is a single gate.
+        cpu.decrement_timers();
+        assert_eq!(cpu.sound_timer.get_value(), 0);
+        assert_eq!(audio.play_count(), 1);
+        assert_eq!(audio.events(), vec![AudioEvent::Play, AudioEvent::Stop]);
+        assert!(!audio.is_playing());
+
+        // Expired timer stays silent.
+        cpu.decrement_timers();
+        assert_eq!(audio.events().len(), 2);
+    }
+
+    #[test]
+    fn test_beep_gate_reopens() {
+        let audio = MockAudioDriver::new();
+        let mut cpu = CPU::new();
+        cpu.drivers.set_audio_driver(Box::new(audio.clone()));
+
+        cpu.sound_timer.reset(10);
+        cpu.decrement_timers();
+        cpu.decrement_timers();
+        assert_eq!(audio.play_count(), 1);
+
+        // Paused: the gate closes, and reopens on next decrement.
+        cpu.stop_sound();
+        assert!(!cpu.peripherals.sound.is_gate_open());
+        cpu.decrement_timers();
+        assert!(cpu.peripherals.sound.is_gate_open());
+        assert_eq!(
+            audio.events(),
+            vec![AudioEvent::Play, AudioEvent::Stop, AudioEvent::Play]
+        );
+
+        // Re-triggered before expiring: still the same gate.
+        cpu.sound_timer.reset(10);
+        cpu.decrement_timers();
+        assert_eq!(audio.play_count(), 2);
+
+        // Reset closes the gate.
+        cpu.reset();
+        assert!(!audio.is_playing());
+    }
+
+    #[test]
+    fn test_scripted_input() {
+        let (mut emulator, mut ctx) = EmulatorBuilder::new()
+            .with_cartridge(
+                Assembler::from_string("LD V0, 05\nSKP V0\nJP 0202\nLD V1, 01\nEXIT")
+                    .assemble_cartridge()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+        let mut input = ScriptedInputDriver::new(vec![(3, 0x5, true), (5, 0x5, false)]);
+
+        let mut pointers = vec![];
+        loop {
+            input.update_input_state(&mut emulator.cpu.peripherals.input);
+            pointers.push(emulator.cpu.peripherals.memory.get_pointer());
+            if let EmulationState::Quit = emulator.step(&mut ctx) {
+                break;
+            }
+        }
+
+        // Not pressed on frame 1, pressed on frame 3.
+        assert_eq!(
+            pointers,
+            vec![0x0200, 0x0202, 0x0204, 0x0202, 0x0206, 0x0208]
+        );
+        assert_eq!(emulator.cpu.registers.get_register(1), 0x01);
+        assert!(input.is_finished());
+        assert!(!emulator.cpu.peripherals.input.is_pressed(0x5));
+    }
+}
@@ -1,7 +1,12 @@
-use std::time::Instant;
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
 use chip8_core::{
-    core::types::C8Byte,
     debugger::{Debugger, DebuggerContext, DebuggerStream},
     drivers::{
         AudioInterface, InputInterface, RenderInterface, WindowInterface, SCREEN_HEIGHT,
@@ -9,17 +14,16 @@ use chip8_core::{
     },
     emulator::{EmulationState, Emulator, EmulatorContext},
     errors::CResult,
-    peripherals::{
-        cartridge::Cartridge,
-        input::{InputState, INPUT_STATE_COUNT},
-    },
+    peripherals::{cartridge::Cartridge, input::InputState},
 };
 use futures::executor::block_on;
+
+use crate::{errors::surface_error_from_panic, keymap::KeyMap};
 use macroquad::{
-    audio::{load_sound_from_bytes, play_sound_once},
+    audio::{load_sound_from_bytes, play_sound, stop_sound, PlaySoundParams},
     prelude::{
-        clear_background, draw_text, draw_texture, is_key_pressed, is_key_released, next_frame,
-        screen_height, screen_width, Conf, Image, KeyCode, Texture2D,
+        clear_background, draw_text, draw_texture, is_key_down, is_key_pressed, is_key_released,
+        next_frame, screen_height, screen_width, Conf, Image, KeyCode, Texture2D,
     },
 };
 
@@ -29,24 +33,69 @@ pub struct MQRenderDriver {
 }
 
 #[derive(Default)]
-pub struct MQInputDriver;
+pub struct MQInputDriver {
+    keymap: KeyMap,
+}
 
 #[derive(Default)]
-pub struct MQWindowDriver;
+pub struct MQWindowDriver {
+    keymap: KeyMap,
+}
 
 impl MQWindowDriver {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Use a custom key map.
+    ///
+    /// # Arguments
+    ///
+    /// * `keymap` - Key map.
+    ///
+    /// # Returns
+    ///
+    /// * Window driver.
+    ///
+    pub fn with_keymap(mut self, keymap: KeyMap) -> Self {
+        self.keymap = keymap;
+        self
+    }
+}
+
+/// Open window and run main loop.
+///
+/// Panics raised before the main loop starts come from window or surface
+/// creation, and are reported as a `SurfaceError`.
+///
+/// # Arguments
+///
+/// * `config` - Window configuration.
+/// * `started` - Set by the main loop when it starts.
+/// * `future` - Main loop.
+///
+fn open_window<F>(config: Conf, started: Rc<Cell<bool>>, future: F) -> CResult
+where
+    F: Future<Output = ()> + 'static,
+{
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        macroquad::Window::from_config(config, future)
+    }));
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(payload) if !started.get() => Err(Box::new(surface_error_from_panic(payload))),
+        Err(payload) => panic::resume_unwind(payload),
+    }
 }
 
 impl WindowInterface for MQWindowDriver {
     fn run_emulator(
         &mut self,
-        mut emulator: Emulator,
+        emulator: Emulator,
         mut emulator_ctx: EmulatorContext,
         cartridge: Cartridge,
-    ) -> CResult {
+    ) -> CResult<Emulator> {
         let config = Conf {
             window_title: WINDOW_TITLE.into(),
             window_width: SCREEN_WIDTH as i32,
@@ -55,104 +104,135 @@ impl WindowInterface for MQWindowDriver {
             ..Default::default()
         };
 
+        // Shared with the window loop, to get the emulator back on exit.
+        let shared_emulator = Rc::new(RefCell::new(emulator));
+        let loop_emulator = shared_emulator.clone();
+        let started = Rc::new(Cell::new(false));
+        let loop_started = started.clone();
+        let keymap = self.keymap.clone();
+
         let run = || async move {
+            loop_started.set(true);
+
             let mut last_elapsed_time = Instant::now();
             let mut fps_timer = Instant::now();
             let mut fps_str = format!("FPS: {} ({} ms)", 0, 0);
 
             let mut render_driver = MQRenderDriver::new();
             let texture = Texture2D::from_image(&render_driver.image);
-            let mut input = MQInputDriver::new();
-
-            emulator
-                .cpu
-                .drivers
-                .set_audio_driver(Box::new(MQAudioDriver::default()));
+            let mut input = MQInputDriver::with_keymap(keymap);
 
             let origin_x = ((screen_width() - SCREEN_WIDTH as f32) / 2.) as u32;
             let origin_y = ((screen_height() - SCREEN_HEIGHT as f32) / 2.) as u32;
 
-            'mainloop: loop {
-                let frame_time = last_elapsed_time.elapsed().as_micros();
-                last_elapsed_time = Instant::now();
-
-                clear_background(macroquad::color::BLACK);
-
-                if fps_timer.elapsed().as_millis() > 500 {
-                    let frame_time_millis = frame_time as f32 / 1_000.0;
-                    let frame_time_secs = frame_time_millis as f32 / 1_000.0;
-                    let fps = (1.0 / frame_time_secs) as u32;
-
-                    fps_str = format!("FPS: {} ({} ms)", fps, frame_time_millis);
-                    fps_timer = Instant::now();
+            {
+                let mut emulator = loop_emulator.borrow_mut();
+                if emulator.cpu.drivers.is_audio_enabled() {
+                    emulator
+                        .cpu
+                        .drivers
+                        .set_audio_driver(Box::new(MQAudioDriver::default()));
                 }
 
-                // Render
-                emulator
-                    .cpu
-                    .peripherals
-                    .screen
-                    .render_pixels(
-                        origin_x,
-                        origin_y,
-                        SCREEN_WIDTH as usize,
-                        &mut render_driver,
-                    )
-                    .expect("oops");
-
-                // Input handling
-                if is_key_pressed(KeyCode::Escape) {
-                    break 'mainloop;
-                }
+                emulator_ctx.prepare_tracefile(&emulator.cpu.tracefile);
+                emulator.warmup(&mut emulator_ctx);
+            }
 
-                if is_key_pressed(KeyCode::F5) {
-                    emulator.reset(&cartridge, &mut emulator_ctx);
-                }
+            'mainloop: loop {
+                {
+                    let mut emulator = loop_emulator.borrow_mut();
+                    let frame_time = last_elapsed_time.elapsed().as_micros();
+                    last_elapsed_time = Instant::now();
 
-                if is_key_pressed(KeyCode::F6) {
-                    emulator.save_state(cartridge.get_title());
-                }
+                    clear_background(macroquad::color::BLACK);
 
-                if is_key_pressed(KeyCode::F7) {
-                    emulator.load_state(cartridge.get_title()).ok();
-                }
+                    if fps_timer.elapsed().as_millis() > 500 {
+                        let frame_time_millis = frame_time as f32 / 1_000.0;
+                        let frame_time_secs = frame_time_millis as f32 / 1_000.0;
+                        let fps = (1.0 / frame_time_secs) as u32;
 
-                for _ in 0..emulator.cpu.speed_multiplicator {
-                    input.update_input_state(&mut emulator.cpu.peripherals.input);
-                    let state = emulator.step(&mut emulator_ctx);
+                        fps_str = format!("FPS: {} ({} ms)", fps, frame_time_millis);
+                        fps_timer = Instant::now();
+                    }
+
+                    // Render
+                    emulator.cpu.peripherals.screen.present();
+                    emulator
+                        .cpu
+                        .peripherals
+                        .screen
+                        .render_pixels(
+                            origin_x,
+                            origin_y,
+                            SCREEN_WIDTH as usize,
+                            &mut render_driver,
+                        )
+                        .expect("oops");
+                    emulator
+                        .cpu
+                        .peripherals
+                        .screen
+                        .tick(Duration::from_micros(frame_time as u64));
+
+                    // Input handling
+                    if is_key_pressed(KeyCode::Escape) {
+                        break 'mainloop;
+                    }
 
-                    match state {
-                        EmulationState::Quit => {
+                    if is_key_pressed(KeyCode::F5) {
+                        if let Err(e) = emulator.reset(&cartridge, &mut emulator_ctx) {
+                            eprintln!("reset error: {}", e);
                             break 'mainloop;
                         }
-                        EmulationState::WaitForInput => {
-                            fps_str = "WAITING FOR INPUT".into();
-                            break;
+                    }
+
+                    if is_key_pressed(KeyCode::F6) {
+                        emulator.save_state(cartridge.get_title());
+                    }
+
+                    if is_key_pressed(KeyCode::F7) {
+                        emulator.load_state(cartridge.get_title()).ok();
+                    }
+
+                    for _ in 0..emulator.cpu.speed_multiplicator {
+                        input.update_input_state(&mut emulator.cpu.peripherals.input);
+                        let state = emulator.step(&mut emulator_ctx);
+
+                        match state {
+                            EmulationState::Quit | EmulationState::Error => {
+                                break 'mainloop;
+                            }
+                            EmulationState::WaitForInput => {
+                                fps_str = "WAITING FOR INPUT".into();
+                                break;
+                            }
+                            _ => (),
                         }
-                        _ => (),
                     }
+
+                    texture.update(&render_driver.image);
+                    draw_texture(texture, 0., 0., macroquad::color::WHITE);
+                    draw_text(&fps_str, 4., 14., 14., macroquad::color::WHITE);
                 }
 
-                texture.update(&render_driver.image);
-                draw_texture(texture, 0., 0., macroquad::color::WHITE);
-                draw_text(&fps_str, 4., 14., 14., macroquad::color::WHITE);
                 next_frame().await;
             }
         };
 
-        macroquad::Window::from_config(config, run());
+        open_window(config, started, run())?;
 
-        Ok(())
+        let emulator = shared_emulator.replace(Emulator::new());
+        Ok(emulator)
     }
 
     fn run_debugger(
         &mut self,
         debugger: Debugger,
         mut debugger_ctx: DebuggerContext,
-        mut emulator: Emulator,
+        emulator: Emulator,
         mut emulator_ctx: EmulatorContext,
         cartridge: Cartridge,
-    ) -> CResult {
+    ) -> CResult<Emulator> {
         let config = Conf {
             window_title: WINDOW_TITLE.into(),
             window_width: SCREEN_WIDTH as i32,
@@ -161,102 +241,130 @@ impl WindowInterface for MQWindowDriver {
             ..Default::default()
         };
 
+        // Shared with the window loop, to get the emulator back on exit.
+        let shared_emulator = Rc::new(RefCell::new(emulator));
+        let loop_emulator = shared_emulator.clone();
+        let started = Rc::new(Cell::new(false));
+        let loop_started = started.clone();
+        let keymap = self.keymap.clone();
+
         let run = || async move {
+            loop_started.set(true);
+
             let mut last_elapsed_time = Instant::now();
             let mut fps_timer = Instant::now();
             let mut fps_str = format!("FPS: {} ({} ms)", 0, 0);
 
             let mut render_driver = MQRenderDriver::new();
-            let mut input = MQInputDriver::new();
+            let mut input = MQInputDriver::with_keymap(keymap);
 
             let mut stream = DebuggerStream::new();
             stream.use_console(true);
             debugger_ctx.is_continuing = true;
 
-            emulator
-                .cpu
-                .drivers
-                .set_audio_driver(Box::new(MQAudioDriver::default()));
+            {
+                let mut emulator = loop_emulator.borrow_mut();
+                if emulator.cpu.drivers.is_audio_enabled() {
+                    emulator
+                        .cpu
+                        .drivers
+                        .set_audio_driver(Box::new(MQAudioDriver::default()));
+                }
+            }
 
             let origin_x = ((screen_width() - SCREEN_WIDTH as f32) / 2.) as u32;
             let origin_y = ((screen_height() - SCREEN_HEIGHT as f32) / 2.) as u32;
 
             'mainloop: loop {
-                let frame_time = last_elapsed_time.elapsed().as_micros();
-                last_elapsed_time = Instant::now();
+                {
+                    let mut emulator = loop_emulator.borrow_mut();
+                    let frame_time = last_elapsed_time.elapsed().as_micros();
+                    last_elapsed_time = Instant::now();
 
-                clear_background(macroquad::color::BLACK);
+                    clear_background(macroquad::color::BLACK);
 
-                if fps_timer.elapsed().as_millis() > 500 {
-                    let frame_time_millis = frame_time as f32 / 1_000.0;
-                    let frame_time_secs = frame_time_millis as f32 / 1_000.0;
-                    let fps = (1.0 / frame_time_secs) as u32;
+                    if fps_timer.elapsed().as_millis() > 500 {
+                        let frame_time_millis = frame_time as f32 / 1_000.0;
+                        let frame_time_secs = frame_time_millis as f32 / 1_000.0;
+                        let fps = (1.0 / frame_time_secs) as u32;
 
-                    fps_str = format!("FPS: {} ({} ms)", fps, frame_time_millis);
-                    fps_timer = Instant::now();
-                }
+                        fps_str = format!("FPS: {} ({} ms)", fps, frame_time_millis);
+                        fps_timer = Instant::now();
+                    }
 
-                // Render
-                emulator
-                    .cpu
-                    .peripherals
-                    .screen
-                    .render_pixels(
-                        origin_x,
-                        origin_y,
-                        SCREEN_WIDTH as usize,
-                        &mut render_driver,
-                    )
-                    .expect("oops");
-
-                // Input handling
-                if is_key_pressed(KeyCode::Escape) {
-                    break 'mainloop;
-                }
+                    // Render
+                    emulator.cpu.peripherals.screen.present();
+                    emulator
+                        .cpu
+                        .peripherals
+                        .screen
+                        .render_pixels(
+                            origin_x,
+                            origin_y,
+                            SCREEN_WIDTH as usize,
+                            &mut render_driver,
+                        )
+                        .expect("oops");
+                    emulator
+                        .cpu
+                        .peripherals
+                        .screen
+                        .tick(Duration::from_micros(frame_time as u64));
+
+                    // Input handling
+                    if is_key_pressed(KeyCode::Escape) {
+                        break 'mainloop;
+                    }
 
-                if is_key_pressed(KeyCode::F5) {
-                    emulator.reset(&cartridge, &mut emulator_ctx);
-                }
+                    if is_key_pressed(KeyCode::F5) {
+                        if let Err(e) = emulator.reset(&cartridge, &mut emulator_ctx) {
+                            eprintln!("reset error: {}", e);
+                            break 'mainloop;
+                        }
+                    }
 
-                if is_key_pressed(KeyCode::F6) {
-                    emulator.save_state(cartridge.get_title());
-                }
+                    if is_key_pressed(KeyCode::F6) {
+                        emulator.save_state(cartridge.get_title());
+                    }
 
-                if is_key_pressed(KeyCode::F7) {
-                    emulator.load_state(cartridge.get_title()).ok();
-                }
+                    if is_key_pressed(KeyCode::F7) {
+                        emulator.load_state(cartridge.get_title()).ok();
+                    }
 
-                for _ in 0..emulator.cpu.speed_multiplicator {
-                    input.update_input_state(&mut emulator.cpu.peripherals.input);
-                    let state = debugger.step(
-                        &mut emulator,
-                        &mut emulator_ctx,
-                        &mut debugger_ctx,
-                        &mut stream,
-                    );
-
-                    match state {
-                        EmulationState::Quit => {
-                            break 'mainloop;
-                        }
-                        EmulationState::WaitForInput => {
-                            fps_str = "WAITING FOR INPUT".into();
-                            break;
+                    for _ in 0..emulator.cpu.speed_multiplicator {
+                        input.update_input_state(&mut emulator.cpu.peripherals.input);
+                        let state = debugger.step(
+                            &mut emulator,
+                            &mut emulator_ctx,
+                            &mut debugger_ctx,
+                            &mut stream,
+                        );
+
+                        match state {
+                            EmulationState::Quit | EmulationState::Error => {
+                                break 'mainloop;
+                            }
+                            EmulationState::WaitForInput => {
+                                fps_str = "WAITING FOR INPUT".into();
+                                break;
+                            }
+                            _ => (),
                         }
-                        _ => (),
                     }
+
+                    render_driver.texture.update(&render_driver.image);
+                    draw_texture(render_driver.texture, 0., 0., macroquad::color::WHITE);
+                    draw_text(&fps_str, 4., 14., 14., macroquad::color::WHITE);
                 }
 
-                render_driver.texture.update(&render_driver.image);
-                draw_texture(render_driver.texture, 0., 0., macroquad::color::WHITE);
-                draw_text(&fps_str, 4., 14., 14., macroquad::color::WHITE);
                 next_frame().await;
             }
         };
 
-        macroquad::Window::from_config(config, run());
+        open_window(config, started, run())?;
 
-        Ok(())
+        let emulator = shared_emulator.replace(Emulator::new());
+        Ok(emulator)
     }
 }
 
@@ -265,43 +373,27 @@ impl MQInputDriver {
         Self::default()
     }
 
-    fn code_to_key(code: C8Byte) -> KeyCode {
-        match code {
-            0x1 => KeyCode::Key1,
-            0x2 => KeyCode::Key2,
-            0x3 => KeyCode::Key3,
-            0xC => KeyCode::Key4,
-            0x4 => KeyCode::Q,
-            0x5 => KeyCode::W,
-            0x6 => KeyCode::E,
-            0xD => KeyCode::R,
-            0x7 => KeyCode::A,
-            0x8 => KeyCode::S,
-            0x9 => KeyCode::D,
-            0xE => KeyCode::F,
-            0xA => KeyCode::Z,
-            0x0 => KeyCode::X,
-            0xB => KeyCode::C,
-            0xF => KeyCode::V,
-            _ => unreachable!(),
-        }
+    /// Create input driver with a custom key map.
+    ///
+    /// # Arguments
+    ///
+    /// * `keymap` - Key map.
+    ///
+    /// # Returns
+    ///
+    /// * Input driver.
+    ///
+    pub fn with_keymap(keymap: KeyMap) -> Self {
+        Self { keymap }
     }
 }
 
 impl InputInterface for MQInputDriver {
     fn update_input_state(&mut self, state: &mut InputState) {
-        for key in 0..INPUT_STATE_COUNT {
-            let key8 = key as C8Byte;
-            let val = Self::code_to_key(key8);
-
-            if is_key_pressed(val) {
-                state.press(key8);
-            }
+        state.apply_pending_releases();
 
-            if is_key_released(val) {
-                state.release(key8);
-            }
-        }
+        self.keymap
+            .update_input_state(state, is_key_pressed, is_key_released, is_key_down);
     }
 }
 
@@ -373,6 +465,17 @@ impl Default for MQAudioDriver {
 
 impl AudioInterface for MQAudioDriver {
     fn play_beep(&mut self) {
-        play_sound_once(self.beep);
+        // Loop the beep while the sound timer runs, so it does not click.
+        play_sound(
+            self.beep,
+            PlaySoundParams {
+                looped: true,
+                volume: 1.0,
+            },
+        );
+    }
+
+    fn stop_beep(&mut self) {
+        stop_sound(self.beep);
     }
 }
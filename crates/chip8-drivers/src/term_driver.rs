@@ -0,0 +1,103 @@
+//! Terminal driver, for hosts without a usable GPU.
+
+use std::{
+    io::{self, Write},
+    thread,
+    time::Duration,
+};
+
+use chip8_core::{
+    debugger::{Debugger, DebuggerContext, DebuggerStream},
+    drivers::WindowInterface,
+    emulator::{EmulationState, Emulator, EmulatorContext},
+    errors::CResult,
+    peripherals::cartridge::Cartridge,
+};
+
+/// Frame duration.
+const FRAME_DURATION: Duration = Duration::from_millis(16);
+
+/// Terminal window driver.
+///
+/// Renders the screen as ASCII art on stdout, without audio nor keyboard input.
+#[derive(Default)]
+pub struct TermWindowDriver;
+
+impl TermWindowDriver {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl WindowInterface for TermWindowDriver {
+    fn run_emulator(
+        &mut self,
+        mut emulator: Emulator,
+        mut emulator_ctx: EmulatorContext,
+        _cartridge: Cartridge,
+    ) -> CResult<Emulator> {
+        let mut stdout = io::stdout();
+
+        emulator_ctx.prepare_tracefile(&emulator.cpu.tracefile);
+        emulator.warmup(&mut emulator_ctx);
+
+        // Clear terminal.
+        write!(stdout, "\x1b[2J")?;
+
+        'mainloop: loop {
+            // Render
+            write!(
+                stdout,
+                "\x1b[H{}",
+                emulator.cpu.peripherals.screen.to_ascii()
+            )?;
+            stdout.flush()?;
+
+            for _ in 0..emulator.cpu.speed_multiplicator {
+                match emulator.step(&mut emulator_ctx) {
+                    EmulationState::Quit | EmulationState::Error => break 'mainloop,
+                    EmulationState::WaitForInput => break,
+                    _ => (),
+                }
+            }
+
+            thread::sleep(FRAME_DURATION);
+        }
+
+        Ok(emulator)
+    }
+
+    fn run_debugger(
+        &mut self,
+        debugger: Debugger,
+        mut debugger_ctx: DebuggerContext,
+        mut emulator: Emulator,
+        mut emulator_ctx: EmulatorContext,
+        _cartridge: Cartridge,
+    ) -> CResult<Emulator> {
+        let mut stream = DebuggerStream::new();
+        stream.use_console(true);
+        debugger_ctx.is_continuing = true;
+
+        'mainloop: loop {
+            for _ in 0..emulator.cpu.speed_multiplicator {
+                let state = debugger.step(
+                    &mut emulator,
+                    &mut emulator_ctx,
+                    &mut debugger_ctx,
+                    &mut stream,
+                );
+
+                match state {
+                    EmulationState::Quit | EmulationState::Error => break 'mainloop,
+                    EmulationState::WaitForInput => break,
+                    _ => (),
+                }
+            }
+
+            thread::sleep(FRAME_DURATION);
+        }
+
+        Ok(emulator)
+    }
+}
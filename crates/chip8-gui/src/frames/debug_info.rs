@@ -18,15 +18,15 @@ impl DebugInfoFrame {
         }
     }
 
-    /// Render.
-    pub fn render(&self, emulator: &Emulator, debug_ctx: &DebuggerContext) {
-        let font_size = 9;
+    /// Collect debug info lines from live emulator state.
+    pub fn collect_lines(emulator: &Emulator, debug_ctx: &DebuggerContext) -> Vec<String> {
         let mut output = String::new();
 
         {
             output.push_str("REGISTERS:");
 
-            for (idx, rgx) in emulator.cpu.registers.get_registers().iter().enumerate() {
+            let (registers, i) = emulator.cpu.registers.all();
+            for (idx, rgx) in registers.iter().enumerate() {
                 if idx % 5 == 0 {
                     output.push('\n');
                 }
@@ -34,10 +34,7 @@ impl DebugInfoFrame {
                 output.push_str(&format!("V{:X}={:02X} ", idx, rgx));
             }
 
-            output.push_str(&format!(
-                "I={:04X}\n",
-                emulator.cpu.registers.get_i_register()
-            ));
+            output.push_str(&format!("I={:04X}\n", i));
         }
 
         {
@@ -97,11 +94,22 @@ impl DebugInfoFrame {
                 "\nCPU multiplicator: {}x",
                 emulator.cpu.speed_multiplicator
             ));
+            output.push_str(&format!(
+                "\nLast collision: {}",
+                emulator.cpu.peripherals.screen.last_collision()
+            ));
             output.push_str(&format!("\nEmulation state: {}", emulation_state));
         }
 
+        output.lines().map(String::from).collect()
+    }
+
+    /// Render.
+    pub fn render(&self, lines: &[String]) {
+        let font_size = 9;
+
         ui_draw_text(
-            &output,
+            &lines.join("\n"),
             self.frame.rect.x + 4.,
             self.frame.rect.y + font_size as f32 + 4.,
             font_size,
@@ -110,3 +118,22 @@ impl DebugInfoFrame {
         self.frame.render();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_lines() {
+        let mut emulator = Emulator::new();
+        emulator.cpu.registers.set_register(0, 0x05);
+        emulator.cpu.registers.set_register(0xF, 0x01);
+        emulator.cpu.registers.set_i_register(0x0300);
+        let debug_ctx = DebuggerContext::new();
+
+        let lines = DebugInfoFrame::collect_lines(&emulator, &debug_ctx);
+        assert_eq!(lines[0], "REGISTERS:");
+        assert_eq!(lines[1], "V0=05 V1=00 V2=00 V3=00 V4=00 ");
+        assert_eq!(lines[4], "VF=01 I=0300");
+    }
+}
@@ -1,11 +1,13 @@
 //! Game frame.
 
+use std::time::Duration;
+
 use chip8_core::{
     drivers::{SCREEN_HEIGHT, SCREEN_WIDTH},
     emulator::Emulator,
 };
 use chip8_drivers::MQRenderDriver;
-use macroquad::prelude::{draw_texture, Rect};
+use macroquad::prelude::{draw_texture, get_frame_time, Rect};
 
 use crate::frame::Frame;
 
@@ -34,12 +36,18 @@ impl GameFrame {
 
     /// Render.
     pub fn render(&mut self, emulator: &mut Emulator) {
+        emulator.cpu.peripherals.screen.present();
         emulator
             .cpu
             .peripherals
             .screen
             .render_pixels(0, 0, 0, &mut self.driver)
             .unwrap();
+        emulator
+            .cpu
+            .peripherals
+            .screen
+            .tick(Duration::from_secs_f32(get_frame_time()));
         self.driver.texture.update(&self.driver.image);
         draw_texture(
             self.driver.texture,
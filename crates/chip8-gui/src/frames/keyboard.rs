@@ -2,12 +2,15 @@
 
 use std::collections::HashMap;
 
-use chip8_core::{core::types::C8Byte, emulator::Emulator};
+use chip8_core::{
+    core::types::{C8Byte, C8RegIdx},
+    peripherals::input::{InputState, INPUT_STATE_COUNT},
+};
 use macroquad::prelude::Rect;
 use once_cell::sync::Lazy;
 
 use crate::{
-    draw::{ui_draw_frame_ex, ui_draw_text_ex, ui_text_size},
+    draw::{ui_draw_fill_rect, ui_draw_frame_ex, ui_draw_text_ex, ui_text_size},
     frame::Frame,
 };
 
@@ -63,7 +66,17 @@ impl KeyboardFrame {
         }
     }
 
-    fn render_wait_indicator(&self, emulator: &Emulator) {
+    /// Get active keys from input state.
+    pub fn active_keys(input: &InputState) -> [bool; INPUT_STATE_COUNT] {
+        let mut keys = [false; INPUT_STATE_COUNT];
+        for (idx, key) in keys.iter_mut().enumerate() {
+            *key = input.is_pressed(idx as C8RegIdx);
+        }
+
+        keys
+    }
+
+    fn render_wait_indicator(&self, input: &InputState) {
         let font_size = 16;
         let grey_color = macroquad::color::GRAY;
         let white_color = macroquad::color::WHITE;
@@ -73,7 +86,7 @@ impl KeyboardFrame {
         let wait_w = (KEY_SIZE + KEY_MARGIN * 2) * 2;
         let wait_h = KEY_SIZE + KEY_MARGIN * 2;
 
-        let locked = emulator.cpu.peripherals.input.is_locked();
+        let locked = input.is_locked();
         let color = if locked { white_color } else { grey_color };
 
         let wait_sz = ui_text_size("WAIT", font_size);
@@ -98,15 +111,17 @@ impl KeyboardFrame {
         );
     }
 
-    fn render_keyboard(&self, emulator: &Emulator) {
+    fn render_keyboard(&self, input: &InputState) {
         let font_size = 16;
         let grey_color = macroquad::color::GRAY;
         let white_color = macroquad::color::WHITE;
+        let black_color = macroquad::color::BLACK;
 
-        for (idx, v) in emulator.cpu.peripherals.input.get_data().iter().enumerate() {
-            let color = match v {
-                0 => grey_color,
-                _ => white_color,
+        for (idx, active) in Self::active_keys(input).iter().enumerate() {
+            let (color, text_color) = if *active {
+                (white_color, black_color)
+            } else {
+                (grey_color, grey_color)
             };
 
             let (x, y) = KEY_POSITIONS[&(idx as C8Byte)];
@@ -122,35 +137,59 @@ impl KeyboardFrame {
                 + (KEY_SIZE + KEY_MARGIN * 2) / 2
                 + font_height as u32 / 2;
 
+            let key_rect = Rect::new(
+                self.frame.rect.x + x as f32 * (KEY_SIZE + KEY_MARGIN * 2) as f32,
+                self.frame.rect.y + y as f32 * (KEY_SIZE + KEY_MARGIN * 2) as f32,
+                (KEY_SIZE + KEY_MARGIN * 2) as f32,
+                (KEY_SIZE + KEY_MARGIN * 2) as f32,
+            );
+
+            // Highlight pressed keys.
+            if *active {
+                ui_draw_fill_rect(key_rect, white_color);
+            }
+
             ui_draw_text_ex(
                 &character,
                 self.frame.rect.x + c_x as f32,
                 self.frame.rect.y + c_y as f32,
                 font_size,
-                color,
+                text_color,
             );
 
-            ui_draw_frame_ex(
-                Rect::new(
-                    self.frame.rect.x + x as f32 * (KEY_SIZE + KEY_MARGIN * 2) as f32,
-                    self.frame.rect.y + y as f32 * (KEY_SIZE + KEY_MARGIN * 2) as f32,
-                    (KEY_SIZE + KEY_MARGIN * 2) as f32,
-                    (KEY_SIZE + KEY_MARGIN * 2) as f32,
-                ),
-                color,
-            );
+            ui_draw_frame_ex(key_rect, color);
         }
     }
 
     /// Render.
-    pub fn render(&mut self, emulator: &Emulator) {
+    pub fn render(&mut self, input: &InputState) {
         // Render keyboard.
-        self.render_keyboard(emulator);
+        self.render_keyboard(input);
 
         // Render wait indicator.
-        self.render_wait_indicator(emulator);
+        self.render_wait_indicator(input);
 
         // Render.
         self.frame.render();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_active_keys() {
+        let mut input = InputState::new();
+        input.press(0x1);
+        input.press(0xC);
+
+        let active: Vec<_> = KeyboardFrame::active_keys(&input)
+            .iter()
+            .enumerate()
+            .filter(|(_, active)| **active)
+            .map(|(idx, _)| idx)
+            .collect();
+        assert_eq!(active, vec![0x1, 0xC]);
+    }
+}
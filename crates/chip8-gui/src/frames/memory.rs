@@ -1,11 +1,14 @@
 //! Memory frame.
 
-use chip8_core::{emulator::Emulator, peripherals::memory::CHUNK_SIZE};
+use std::cmp;
+
+use chip8_core::{core::types::C8Addr, emulator::Emulator, peripherals::memory::CHUNK_SIZE};
 use macroquad::prelude::Rect;
 
 use crate::{
     draw::{ui_draw_fill_rect, ui_draw_text},
     frame::Frame,
+    frames::ListFrameData,
 };
 
 /// Memory frame.
@@ -21,39 +24,56 @@ impl MemoryFrame {
         }
     }
 
-    /// Render.
-    pub fn render(&self, emulator: &Emulator) {
-        let font_size = 6;
-        let mut output = String::new();
-
-        // Draw background.
-        ui_draw_fill_rect(self.frame.rect, macroquad::color::BLACK);
-
-        for (idx, chunk) in emulator
-            .cpu
-            .peripherals
-            .memory
+    /// Collect memory lines from live emulator state.
+    ///
+    /// Returns one line per memory chunk, and the cursor on the chunk
+    /// containing the program counter.
+    pub fn collect_lines(emulator: &Emulator) -> (Vec<String>, i32) {
+        let memory = &emulator.cpu.peripherals.memory;
+        let lines = memory
             .get_data()
             .chunks(CHUNK_SIZE)
             .enumerate()
-        {
-            output.push_str(&format!(
-                "{:04X}-{:04X}|",
-                idx * CHUNK_SIZE,
-                (idx + 1) * CHUNK_SIZE
-            ));
-
-            for chunk_value in chunk.iter() {
-                output.push_str(&format!("{:02X}", chunk_value));
-            }
-
-            output.push('\n');
+            .map(|(idx, chunk)| {
+                let mut line = format!("{:04X}-{:04X}|", idx * CHUNK_SIZE, (idx + 1) * CHUNK_SIZE);
+                for chunk_value in chunk.iter() {
+                    line.push_str(&format!("{:02X}", chunk_value));
+                }
+
+                line
+            })
+            .collect();
+        let cursor = memory.get_pointer() as usize / CHUNK_SIZE;
+
+        (lines, cursor as i32)
+    }
+
+    /// Render a memory window around the cursor.
+    pub fn render(&self, data: &ListFrameData, pointer: C8Addr) {
+        let font_size = 6;
+        let char_height = font_size as usize + 2;
+        let max_lines = (self.frame.rect.h as usize / char_height).saturating_sub(2);
+        let total_lines = data.data.len();
+
+        // Center window on cursor.
+        let start_idx = cmp::max(data.cursor - max_lines as i32 / 2, 0) as usize;
+        let start_idx = cmp::min(start_idx, total_lines.saturating_sub(max_lines));
+        let end_idx = cmp::min(total_lines, start_idx + max_lines);
+
+        let mut output = String::new();
+        for (idx, line) in data.data[start_idx..end_idx].iter().enumerate() {
+            let marker = if (start_idx + idx) as i32 == data.cursor {
+                ">"
+            } else {
+                " "
+            };
+            output.push_str(&format!("{}{}\n", marker, line));
         }
 
-        output.push_str(&format!(
-            "PC: {:04X}",
-            emulator.cpu.peripherals.memory.get_pointer()
-        ));
+        output.push_str(&format!("PC: {:04X}", pointer));
+
+        // Draw background.
+        ui_draw_fill_rect(self.frame.rect, macroquad::color::BLACK);
 
         ui_draw_text(
             &output,
@@ -65,3 +85,24 @@ impl MemoryFrame {
         self.frame.render();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_lines() {
+        let mut emulator = Emulator::new();
+        emulator
+            .cpu
+            .peripherals
+            .memory
+            .write_data_at_offset(0x0240, &[0xAB, 0xCD])
+            .unwrap();
+        emulator.cpu.peripherals.memory.set_pointer(0x0242);
+
+        let (lines, cursor) = MemoryFrame::collect_lines(&emulator);
+        assert_eq!(cursor, 9);
+        assert!(lines[9].starts_with("0240-0280|ABCD00"));
+    }
+}
@@ -4,6 +4,7 @@ mod game;
 mod keyboard;
 mod list;
 mod memory;
+mod preview;
 mod shell;
 mod status;
 mod title;
@@ -14,6 +15,7 @@ pub use game::GameFrame;
 pub use keyboard::{KeyboardFrame, KEYBOARD_HEIGHT, KEYBOARD_WIDTH};
 pub use list::{ListFrame, ListFrameData};
 pub use memory::MemoryFrame;
+pub use preview::PreviewFrame;
 pub use shell::ShellFrame;
 pub use status::{StatusFrame, STATUS_HEIGHT};
 pub use title::{TitleFrame, TITLE_HEIGHT};
@@ -0,0 +1,40 @@
+//! Preview frame.
+
+use macroquad::prelude::Rect;
+
+use crate::{draw::ui_draw_text, frame::Frame};
+
+/// Preview frame.
+pub struct PreviewFrame {
+    frame: Frame,
+    lines: Vec<String>,
+}
+
+impl PreviewFrame {
+    /// Create new preview frame.
+    pub fn new(rect: Rect, title: &str) -> Self {
+        Self {
+            frame: Frame::new(rect, title),
+            lines: vec![],
+        }
+    }
+
+    /// Set lines.
+    pub fn set_lines(&mut self, lines: Vec<String>) {
+        self.lines = lines;
+    }
+
+    /// Render.
+    pub fn render(&self) {
+        let font_size = 8;
+        let char_height = font_size + 4;
+        let mut cursor_y = self.frame.rect.y + char_height as f32;
+
+        for line in &self.lines {
+            ui_draw_text(line, self.frame.rect.x + 4., cursor_y, font_size);
+            cursor_y += char_height as f32;
+        }
+
+        self.frame.render();
+    }
+}
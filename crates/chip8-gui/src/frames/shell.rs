@@ -2,11 +2,14 @@
 
 use std::{cmp, time::Instant};
 
-use chip8_core::debugger::DebuggerStream;
+use chip8_core::{
+    core::cpu::CPU,
+    debugger::{Debugger, DebuggerContext, DebuggerStream, DebuggerStreamLine},
+};
 use macroquad::prelude::Rect;
 
 use crate::{
-    draw::{ui_draw_fill_rect, ui_draw_text, ui_text_size},
+    draw::{ui_draw_fill_rect, ui_draw_text, ui_draw_text_ex, ui_text_size},
     frame::Frame,
 };
 
@@ -61,6 +64,39 @@ impl ShellFrame {
         cmd
     }
 
+    /// Submit current command to the debugger.
+    ///
+    /// The command is echoed to the stream, followed by its output.
+    pub fn submit(
+        &mut self,
+        debugger: &Debugger,
+        cpu: &CPU,
+        debug_ctx: &mut DebuggerContext,
+        stream: &mut DebuggerStream,
+    ) {
+        let cmd_str = self.validate();
+        stream.writeln_stdout(format!("> {}", cmd_str));
+
+        if let Some(cmd) = debugger.read_command(&cmd_str, stream) {
+            debugger.handle_command(cpu, debug_ctx, stream, cmd);
+        }
+    }
+
+    /// Get visible stream lines.
+    pub fn visible_lines<'a>(
+        &self,
+        stream: &'a DebuggerStream,
+        max_lines: usize,
+    ) -> &'a [DebuggerStreamLine] {
+        let lines = stream.get_lines();
+        let total_lines = lines.len();
+
+        let start_idx = cmp::max(total_lines as i32 + 1 - max_lines as i32, 0) as usize;
+        let end_idx = cmp::min(total_lines, max_lines + start_idx);
+
+        &lines[start_idx..end_idx]
+    }
+
     /// Get max lines.
     pub fn get_max_lines(&self, font_size: u16) -> usize {
         let char_height = font_size as usize;
@@ -78,16 +114,19 @@ impl ShellFrame {
         // Get max lines.
         let max_lines = self.get_max_lines(char_height as u16);
 
-        // Get buffer lines.
-        let lines = stream.get_lines();
-        let total_lines = lines.len();
-        let base_cursor = total_lines as usize;
-
-        let start_idx = cmp::max(base_cursor as i32 + 1 - max_lines as i32, 0) as usize;
-        let end_idx = cmp::min(total_lines, max_lines + start_idx) as usize;
-
-        for b in lines[start_idx..end_idx].iter() {
-            ui_draw_text(&b.content, self.frame.rect.x + 4., cursor_y, font_size);
+        for b in self.visible_lines(stream, max_lines).iter() {
+            let color = if b.error {
+                macroquad::color::RED
+            } else {
+                macroquad::color::WHITE
+            };
+            ui_draw_text_ex(
+                &b.content,
+                self.frame.rect.x + 4.,
+                cursor_y,
+                font_size,
+                color,
+            );
 
             cursor_y += char_height;
         }
@@ -146,3 +185,43 @@ impl ShellFrame {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_submit() {
+        let debugger = Debugger::new();
+        let cpu = CPU::new();
+        let mut debug_ctx = DebuggerContext::new();
+        let mut stream = DebuggerStream::new();
+        let mut frame = ShellFrame::new(Rect::new(0., 0., 256., 128.));
+
+        for c in "rreg i".chars() {
+            frame.add_char(c);
+        }
+        frame.submit(&debugger, &cpu, &mut debug_ctx, &mut stream);
+
+        frame.add_char('x');
+        frame.submit(&debugger, &cpu, &mut debug_ctx, &mut stream);
+
+        let lines: Vec<_> = frame
+            .visible_lines(&stream, 10)
+            .iter()
+            .map(|l| (l.error, l.content.as_str()))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                (false, "> rreg i"),
+                (false, "I = 0"),
+                (false, "> x"),
+                (true, "unknown command: x"),
+            ]
+        );
+
+        // Only the latest lines are visible.
+        assert_eq!(frame.visible_lines(&stream, 3).len(), 2);
+    }
+}
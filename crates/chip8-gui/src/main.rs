@@ -26,6 +26,10 @@ struct Args {
     /// use debug UI
     #[argh(switch)]
     pub debug: bool,
+
+    /// directory to list games from
+    #[argh(option)]
+    pub roms_dir: Option<PathBuf>,
 }
 
 fn window_conf() -> Conf {
@@ -51,6 +55,10 @@ fn main() {
         mgr.register_scene("game", Box::new(GameScene::new()));
         mgr.register_scene("debug", Box::new(DebugScene::new()));
 
+        if let Some(roms_dir) = args.roms_dir {
+            ctx.set_cache_data("roms_dir", roms_dir.to_string_lossy().to_string());
+        }
+
         if let Some(game_path) = args.game_path {
             if let Err(e) = Cartridge::load_from_path(&game_path) {
                 eprintln!(
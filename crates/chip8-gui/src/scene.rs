@@ -1,5 +1,10 @@
 use std::collections::HashMap;
 
+/// Minimum emulation speed (instructions per frame).
+pub const MIN_SPEED: u16 = 1;
+/// Maximum emulation speed (instructions per frame).
+pub const MAX_SPEED: u16 = 64;
+
 /// Scene context.
 #[derive(Debug)]
 pub struct SceneContext {
@@ -46,6 +51,18 @@ impl SceneContext {
     pub fn get_cache_data(&self, key: &str) -> Option<String> {
         self.cache_data.get(key).cloned()
     }
+
+    /// Set emulation speed, clamped between `MIN_SPEED` and `MAX_SPEED`.
+    pub fn set_speed(&mut self, speed: u16) -> u16 {
+        let speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        self.set_cache_data("speed", speed.to_string());
+        speed
+    }
+
+    /// Get emulation speed, if set.
+    pub fn get_speed(&self) -> Option<u16> {
+        self.get_cache_data("speed").and_then(|s| s.parse().ok())
+    }
 }
 
 /// Scene trait.
@@ -165,3 +182,22 @@ impl SceneManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speed() {
+        let mut ctx = SceneContext::new();
+        assert_eq!(ctx.get_speed(), None);
+
+        assert_eq!(ctx.set_speed(12), 12);
+        assert_eq!(ctx.get_speed(), Some(12));
+
+        assert_eq!(ctx.set_speed(0), MIN_SPEED);
+        assert_eq!(ctx.get_speed(), Some(MIN_SPEED));
+        assert_eq!(ctx.set_speed(1000), MAX_SPEED);
+        assert_eq!(ctx.get_speed(), Some(MAX_SPEED));
+    }
+}
@@ -13,18 +13,22 @@ use macroquad::prelude::{get_char_pressed, is_key_pressed, KeyCode, Rect};
 
 use crate::{
     frames::{
-        CodeFrame, DebugInfoFrame, GameFrame, MemoryFrame, ShellFrame, StatusFrame, TitleFrame,
-        STATUS_HEIGHT, TITLE_HEIGHT,
+        CodeFrame, DebugInfoFrame, GameFrame, ListFrameData, MemoryFrame, ShellFrame, StatusFrame,
+        TitleFrame, STATUS_HEIGHT, TITLE_HEIGHT,
     },
-    scene::{Scene, SceneContext},
+    scene::{Scene, SceneContext, MIN_SPEED},
 };
 
-const STATUS_TEXT: &str = "\
-                           F2 - Shell          F4 - Step\n\
-                           F3 - Memory         F5 - Continue\n\
-                           F10 - Dump          F6 - Pause\n\
-                           ESC - Back to game list\
-                           ";
+fn status_text(speed: u16) -> String {
+    format!(
+        "\
+         F2 - Shell          F4 - Step           +/- - Speed ({}x)\n\
+         F3 - Memory         F5 - Continue\n\
+         F10 - Dump          F6 - Pause\n\
+         ESC - Back to game list",
+        speed
+    )
+}
 
 /// Debug focus.
 pub enum DebugFocus {
@@ -47,6 +51,9 @@ pub struct DebugScene {
     status_frame: StatusFrame,
     shell_frame: ShellFrame,
     memory_frame: MemoryFrame,
+    debug_info_lines: Vec<String>,
+    memory_lines: Vec<String>,
+    memory_cursor: i32,
     debugger: Debugger,
     debugger_context: DebuggerContext,
     debugger_stream: DebuggerStream,
@@ -88,6 +95,9 @@ impl Default for DebugScene {
                 WINDOW_WIDTH as f32 - 64. * 2.,
                 WINDOW_HEIGHT as f32 - 64. * 2.,
             )),
+            debug_info_lines: vec![],
+            memory_lines: vec![],
+            memory_cursor: 0,
             emulator: Emulator::new(),
             emulator_context: EmulatorContext::new(),
             debugger: Debugger::new(),
@@ -106,6 +116,32 @@ impl DebugScene {
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Check speed keys, ignored while typing in the shell.
+    fn is_speed_key_pressed(&self, keys: &[KeyCode]) -> bool {
+        match self.focus {
+            DebugFocus::Shell => false,
+            _ => keys.iter().any(|k| is_key_pressed(*k)),
+        }
+    }
+
+    /// Set emulation speed.
+    fn set_speed(&mut self, ctx: &mut SceneContext, speed: u16) {
+        let speed = ctx.set_speed(speed);
+        self.status_frame.set_status(&status_text(speed));
+    }
+
+    /// Refresh live panels from emulator state.
+    fn refresh_panels(&mut self) {
+        self.debug_info_lines =
+            DebugInfoFrame::collect_lines(&self.emulator, &self.debugger_context);
+
+        if let DebugFocus::Memory = self.focus {
+            let (lines, cursor) = MemoryFrame::collect_lines(&self.emulator);
+            self.memory_lines = lines;
+            self.memory_cursor = cursor;
+        }
+    }
 }
 
 impl Scene for DebugScene {
@@ -134,7 +170,9 @@ impl Scene for DebugScene {
 
         self.emulator = Emulator::new();
         self.emulator_context = EmulatorContext::new();
-        self.emulator.load_game(&self.cartridge);
+        self.emulator
+            .load_game(&self.cartridge)
+            .expect("cartridge should fit in memory");
         self.emulator
             .cpu
             .drivers
@@ -146,7 +184,11 @@ impl Scene for DebugScene {
         self.debugger_context.set_address(INITIAL_MEMORY_POINTER);
         self.debugger_stream = DebuggerStream::new();
 
-        self.status_frame.set_status(STATUS_TEXT);
+        let speed = ctx
+            .get_speed()
+            .unwrap_or(self.emulator.cpu.speed_multiplicator);
+        self.set_speed(ctx, speed);
+        self.refresh_panels();
     }
 
     fn destroy(&mut self, _ctx: &mut SceneContext) {
@@ -159,14 +201,19 @@ impl Scene for DebugScene {
     fn render(&mut self) {
         self.title_frame.render();
         self.game_frame.render(&mut self.emulator);
-        self.debug_info_frame
-            .render(&self.emulator, &self.debugger_context);
+        self.debug_info_frame.render(&self.debug_info_lines);
         self.code_frame.render(&self.debugger_context);
         self.status_frame.render();
 
         match self.focus {
             DebugFocus::Shell => self.shell_frame.render(&self.debugger_stream),
-            DebugFocus::Memory => self.memory_frame.render(&self.emulator),
+            DebugFocus::Memory => self.memory_frame.render(
+                &ListFrameData {
+                    cursor: self.memory_cursor,
+                    data: &self.memory_lines,
+                },
+                self.emulator.cpu.peripherals.memory.get_pointer(),
+            ),
             _ => {}
         }
     }
@@ -206,27 +253,24 @@ impl Scene for DebugScene {
             );
         } else if is_key_pressed(KeyCode::F6) {
             self.debugger_context.is_continuing = false;
+        } else if self.is_speed_key_pressed(&[KeyCode::KpAdd, KeyCode::Equal]) {
+            let speed = ctx.get_speed().unwrap_or(MIN_SPEED);
+            self.set_speed(ctx, speed.saturating_add(1));
+        } else if self.is_speed_key_pressed(&[KeyCode::KpSubtract, KeyCode::Minus]) {
+            let speed = ctx.get_speed().unwrap_or(MIN_SPEED);
+            self.set_speed(ctx, speed.saturating_sub(1));
         } else if is_key_pressed(KeyCode::Backspace) {
             if let DebugFocus::Shell = self.focus {
                 self.shell_frame.remove_char();
             }
         } else if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter) {
             if let DebugFocus::Shell = self.focus {
-                let cmd_str = self.shell_frame.validate();
-                self.debugger_stream
-                    .writeln_stdout(format!("> {}", cmd_str));
-
-                let cmd = self
-                    .debugger
-                    .read_command(&cmd_str, &mut self.debugger_stream);
-                if let Some(cmd) = cmd {
-                    self.debugger.handle_command(
-                        &self.emulator.cpu,
-                        &mut self.debugger_context,
-                        &mut self.debugger_stream,
-                        cmd,
-                    );
-                }
+                self.shell_frame.submit(
+                    &self.debugger,
+                    &self.emulator.cpu,
+                    &mut self.debugger_context,
+                    &mut self.debugger_stream,
+                );
             }
         } else if is_key_pressed(KeyCode::F10) {
             let filename = format!("{}.dump", self.cartridge.get_title());
@@ -238,6 +282,11 @@ impl Scene for DebugScene {
             }
         }
 
+        // Apply user speed.
+        if let Some(speed) = ctx.get_speed() {
+            self.emulator.cpu.speed_multiplicator = speed;
+        }
+
         for _ in 0..self.emulator.cpu.speed_multiplicator {
             self.input_driver
                 .update_input_state(&mut self.emulator.cpu.peripherals.input);
@@ -248,5 +297,7 @@ impl Scene for DebugScene {
                 &mut self.debugger_stream,
             );
         }
+
+        self.refresh_panels();
     }
 }
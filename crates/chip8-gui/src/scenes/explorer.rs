@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use chip8_core::{
     core::math::modulo,
     drivers::{WINDOW_HEIGHT, WINDOW_WIDTH},
@@ -6,7 +8,7 @@ use chip8_core::{
 use macroquad::prelude::{is_key_pressed, KeyCode, Rect};
 
 use crate::{
-    frames::{ListFrame, ListFrameData, StatusFrame, TitleFrame},
+    frames::{ListFrame, ListFrameData, PreviewFrame, StatusFrame, TitleFrame},
     input::ui_is_key_auto_pressed,
     scene::{Scene, SceneContext},
 };
@@ -18,11 +20,16 @@ const STATUS_TEXT: &str = "\
                            ESC - Quit\
                            ";
 
+/// Previewed instruction count.
+const PREVIEW_LINES: usize = 24;
+
 /// Explorer scene.
 pub struct ExplorerScene {
     list_frame: ListFrame,
+    preview_frame: PreviewFrame,
     status_frame: StatusFrame,
     title_frame: TitleFrame,
+    games_dir: PathBuf,
     game_list: Vec<String>,
     game_cursor: i32,
 }
@@ -39,8 +46,18 @@ impl Default for ExplorerScene {
                 ),
                 "GAME LIST",
             ),
+            preview_frame: PreviewFrame::new(
+                Rect::new(
+                    WINDOW_WIDTH as f32 * 3. / 4. + 8.,
+                    WINDOW_HEIGHT as f32 / 4.,
+                    WINDOW_WIDTH as f32 / 4. - 16.,
+                    WINDOW_HEIGHT as f32 / 2.,
+                ),
+                "PREVIEW",
+            ),
             status_frame: StatusFrame::new_default(),
             title_frame: TitleFrame::new("CHIP-8 EMULATOR - Select game"),
+            games_dir: Cartridge::get_games_directory(),
             game_list: vec![],
             game_cursor: 0,
         }
@@ -52,17 +69,56 @@ impl ExplorerScene {
     pub fn new() -> Self {
         Default::default()
     }
+
+    fn update_preview(&mut self) {
+        let lines = self
+            .game_list
+            .get(self.game_cursor as usize)
+            .and_then(|game| Cartridge::load_from_path(self.games_dir.join(game)).ok())
+            .map(|cartridge| cartridge.preview_disassembly(PREVIEW_LINES))
+            .unwrap_or_default();
+
+        self.preview_frame.set_lines(lines);
+    }
+
+    fn select_game(&self, ctx: &mut SceneContext, scene: &str) {
+        if let Some(game) = self.game_list.get(self.game_cursor as usize) {
+            let game_path = self.games_dir.join(game);
+
+            ctx.set_cache_data(
+                "selected_game_path",
+                game_path.to_string_lossy().to_string(),
+            );
+            ctx.set_current_scene(scene);
+        }
+    }
 }
 
 impl Scene for ExplorerScene {
-    fn init(&mut self, _ctx: &mut SceneContext) {
-        self.game_list = Cartridge::list_from_games_directory();
+    fn init(&mut self, ctx: &mut SceneContext) {
+        self.games_dir = ctx
+            .get_cache_data("roms_dir")
+            .map(PathBuf::from)
+            .unwrap_or_else(Cartridge::get_games_directory);
+        self.game_list = Cartridge::list_from_directory(&self.games_dir);
+        self.game_cursor = 0;
+
+        if self.game_list.is_empty() {
+            self.title_frame.set_title(&format!(
+                "CHIP-8 EMULATOR - No games found in {}",
+                self.games_dir.display()
+            ));
+        } else {
+            self.title_frame.set_title("CHIP-8 EMULATOR - Select game");
+        }
 
         self.status_frame.set_status(STATUS_TEXT);
+        self.update_preview();
     }
 
     fn destroy(&mut self, _ctx: &mut SceneContext) {
         self.game_list.clear();
+        self.preview_frame.set_lines(vec![]);
     }
 
     fn render(&mut self) {
@@ -73,36 +129,25 @@ impl Scene for ExplorerScene {
 
         self.title_frame.render();
         self.list_frame.render(&data);
+        self.preview_frame.render();
         self.status_frame.render();
     }
 
     fn update(&mut self, ctx: &mut SceneContext) {
-        if ui_is_key_auto_pressed(KeyCode::Up) {
-            self.game_cursor = modulo(self.game_cursor - 1, self.game_list.len() as i32)
-        } else if ui_is_key_auto_pressed(KeyCode::Down) {
-            self.game_cursor = modulo(self.game_cursor + 1, self.game_list.len() as i32)
-        } else if is_key_pressed(KeyCode::F3) {
-            let mut game_dir = Cartridge::get_games_directory();
-            let game = &self.game_list[self.game_cursor as usize];
-            game_dir.push(game);
+        let game_count = self.game_list.len() as i32;
 
-            ctx.set_cache_data(
-                "selected_game_path",
-                String::from(game_dir.to_str().unwrap()),
-            );
-            ctx.set_current_scene("debug");
+        if ui_is_key_auto_pressed(KeyCode::Up) && game_count > 0 {
+            self.game_cursor = modulo(self.game_cursor - 1, game_count);
+            self.update_preview();
+        } else if ui_is_key_auto_pressed(KeyCode::Down) && game_count > 0 {
+            self.game_cursor = modulo(self.game_cursor + 1, game_count);
+            self.update_preview();
+        } else if is_key_pressed(KeyCode::F3) {
+            self.select_game(ctx, "debug");
         } else if is_key_pressed(KeyCode::Escape) {
             ctx.quit();
         } else if is_key_pressed(KeyCode::Enter) {
-            let mut game_dir = Cartridge::get_games_directory();
-            let game = &self.game_list[self.game_cursor as usize];
-            game_dir.push(game);
-
-            ctx.set_cache_data(
-                "selected_game_path",
-                String::from(game_dir.to_str().unwrap()),
-            );
-            ctx.set_current_scene("game");
+            self.select_game(ctx, "game");
         }
     }
 }
@@ -70,7 +70,9 @@ impl Scene for GameScene {
 
         self.emulator = Emulator::new();
         self.emulator_context = EmulatorContext::new();
-        self.emulator.load_game(&self.cartridge);
+        self.emulator
+            .load_game(&self.cartridge)
+            .expect("cartridge should fit in memory");
         self.emulator
             .cpu
             .drivers
@@ -87,7 +89,8 @@ impl Scene for GameScene {
         self.title_frame.render();
         self.status_frame.render();
         self.game_frame.render(&mut self.emulator);
-        self.keyboard_frame.render(&self.emulator);
+        self.keyboard_frame
+            .render(&self.emulator.cpu.peripherals.input);
     }
 
     fn update(&mut self, ctx: &mut SceneContext) {
@@ -95,7 +98,8 @@ impl Scene for GameScene {
             ctx.set_current_scene("explorer");
         } else if is_key_pressed(KeyCode::F5) {
             self.emulator
-                .reset(&self.cartridge, &mut self.emulator_context);
+                .reset(&self.cartridge, &mut self.emulator_context)
+                .expect("cartridge should fit in memory");
         } else if is_key_pressed(KeyCode::F6) {
             self.emulator.save_state(&self.game_name);
         } else if is_key_pressed(KeyCode::F7) {